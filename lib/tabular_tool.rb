@@ -1,10 +1,28 @@
 # frozen_string_literal: true
 
 require_relative "tabular_tool/version"
+require_relative "tabular_tool/log"
+require_relative "tabular_tool/progress"
+require_relative "tabular_tool/timings"
+require_relative "tabular_tool/watch"
+require_relative "tabular_tool/config"
+require_relative "tabular_tool/dtypes"
+require_relative "tabular_tool/parquet_footer"
 require_relative "tabular_tool/formats"
 require_relative "tabular_tool/operations"
 require_relative "tabular_tool/cli"
+require_relative "tabular_tool/api"
 
 module TabularTool
   class Error < StandardError; end
+
+  # Raised when checks find problems under --strict; carries the report to print before exiting non-zero
+  class CheckFailed < Error
+    attr_reader :output
+
+    def initialize(output, message = "Checks failed")
+      @output = output
+      super(message)
+    end
+  end
 end
@@ -0,0 +1,36 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # In-process entry points, for using tt from other Ruby code without shelling out:
+  #
+  #   df = TabularTool.read("events.parquet", streaming: true)
+  #   df = TabularTool.transform(df, where: "status = 'active'", select: ["city", "score"])
+  #   TabularTool.stats(df)
+  #
+  # transform takes the option keys CLI.parse_args produces (where:, select:, sort_keys:, ...)
+  class << self
+    def read(path, **options)
+      Formats.read(path, **options)
+    end
+
+    # The filter, selection, sort and column steps tt applies before any command
+    def transform(df, **options)
+      CLI.transform(df, options)
+    end
+
+    # describe-shaped statistics; fast streams count/null_count/min/max without collecting
+    def stats(df, fast: false)
+      fast ? Operations::Stats.fast(df)[:stats] : Operations::Stats.call(df.lazy.collect)[:stats]
+    end
+
+    # Lint options as for Operations::Lint.call (unique_columns:, high_null_threshold:, ...)
+    def lint(df, **options)
+      result = Operations::Lint.call(df.lazy.collect, **options)
+      Operations::LintReport.new(result, unique_columns: options[:unique_columns], outliers_checked: options[:outliers])
+    end
+
+    def write(df, path, **options)
+      Formats.write(df, path, **options)
+    end
+  end
+end
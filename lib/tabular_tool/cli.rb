@@ -1,9 +1,50 @@
 # frozen_string_literal: true
 
+require "fileutils"
+require "json"
 require "optparse"
 
 module TabularTool
   module CLI
+    # Input path meaning "read from stdin"
+    STDIN_PATH = "-"
+
+    COMMANDS = [:cat, :head, :tail, :peek, :sample, :shuffle, :less, :lint, :stats, :count, :nunique, :clean, :resample,
+                :convert, :version].freeze
+
+    # Widest cell peek prints before truncating
+    PEEK_MAX_WIDTH = 40
+
+    # Options that change row values or membership; the shell decompression path can't apply them
+    ROW_TRANSFORM_OPTIONS = [:where, :expr_filter, :where_null, :where_not_null, :where_in, :unique, :unique_on, :trim,
+                             :lower, :upper, :shuffle, :per_group, :hash_columns, :redact_columns, :truncate_strings,
+                             :max_cell_bytes, :with_row_index, :cumsum, :diff, :lag, :with, :drop_nulls, :drop_nulls_on,
+                             :keep_keys_file, :drop_keys_file, :header_only].freeze
+
+    # Column name for --with-row-index when none is given
+    DEFAULT_ROW_INDEX_NAME = "row_nr"
+
+    # Inputs above this size are shuffled externally when writing to -o, one bucket per this many bytes
+    EXTERNAL_SHUFFLE_BYTES = 256 * 1024 * 1024
+
+    # Largest row count Polars' default index type (u32) can address; --limit/--offset beyond it mean "all"/"none"
+    MAX_ROWS = 2**32 - 1
+
+    # Input bytes per sorted run for --external-sort; each run is sorted in memory on its own
+    EXTERNAL_SORT_RUN_BYTES = 256 * 1024 * 1024
+
+    # Steps that run on the sorted rows, which --external-sort only has once it writes the output
+    POST_SORT_OPTIONS = [:with_row_index, :cumsum, :diff, :lag, :with].freeze
+
+    # Commands whose result is rows on stdout, which --tee can also save
+    TEE_COMMANDS = [:cat, :head, :tail, :sample, :shuffle].freeze
+
+    # External programs behind the compressed-input fast paths; without them tt falls back or fails
+    SHELL_TOOLS = ["gzip", "zstd"].freeze
+
+    # Commands whose query plan can be shown with --explain
+    EXPLAIN_COMMANDS = [:cat, :head, :tail, :count, :stats].freeze
+
     class << self
       def parse_args(argv)
         args = {
@@ -17,29 +58,116 @@ module TabularTool
 
         parser = build_parser(args)
 
+        config_path = extract_config_path!(argv)
+        # Positional parsing can already warn, before OptionParser has seen --quiet
+        Log.configure(quiet: (argv & ["-q", "--quiet"]).any?)
         parse_command_and_args(argv, args)
+        apply_config(parser, config_path)
         parser.parse!(argv)
+        # Also covers --watch, whose separators print before execute runs
+        Log.configure(quiet: args[:quiet], verbose: args[:verbose])
+        parse_convert_args(argv, args) if args[:command] == :convert
+        parse_stats_args(argv, args) if args[:command] == :stats
         args[:file] = argv.pop if argv.any?
         raise Error, "Unexpected argument(s): #{argv.join(' ')}" if argv.any?
+        resolve_row_count(args)
+
+        # `--trim data.csv`: the optional column list swallowed the input path. Only an existing
+        # file counts, so `--trim city` with no input still reports the missing file
+        if args[:file].nil? && args[:trim].is_a?(Array) && args[:trim].length == 1 && File.exist?(args[:trim].first)
+          args[:file] = args[:trim].first
+          args[:trim] = :all
+        end
+
+        # Same for `--with-row-index data.csv`; `--with-row-index idx` alone keeps idx as the name
+        if args[:file].nil? && args[:with_row_index] && args[:with_row_index] != DEFAULT_ROW_INDEX_NAME &&
+           File.exist?(args[:with_row_index])
+          args[:file] = args[:with_row_index]
+          args[:with_row_index] = DEFAULT_ROW_INDEX_NAME
+        end
 
         args
       end
 
       def execute(command:, file: nil, **options)
+        Log.configure(quiet: options[:quiet], verbose: options[:verbose])
+        return version_report if command == :version
+        if command == :convert && (inputs = options.delete(:convert_inputs))
+          return inputs.map do |input|
+            output = template_output(options[:output_template], input, inputs)
+            execute(command: :convert, file: input, **options, output: output)
+          end.join("\n")
+        end
+        if command == :stats && (options[:stats_inputs] || options[:output])
+          return execute_stats_files(options.delete(:stats_inputs) || [file], options)
+        end
+        check_convert(file, options) if command == :convert
+
+        # Read by Polars when a streaming query runs, so it must be set before any scan or sink
+        ENV["POLARS_STREAMING_CHUNK_SIZE"] = options[:chunk_size].to_s if options[:chunk_size]
+        raise Error, "--append requires -o/--output" if options[:append] && !options[:output]
+        raise Error, "--append cannot be used with --in-place" if options[:append] && options[:in_place]
+        raise Error, "--limit-bytes requires -o/--output" if options[:limit_bytes] && !options[:output]
+        raise Error, "--columns-as-rows only applies to terminal output, not -o" if options[:columns_as_rows] && options[:output]
+        raise Error, "--limit-bytes cannot be used with --append" if options[:limit_bytes] && options[:append]
+        if options[:tee]
+          raise Error, "--tee only applies to cat, head, tail, sample and shuffle" unless TEE_COMMANDS.include?(command)
+          raise Error, "--tee prints as well as writing; use -o alone to only write" if options[:output] || options[:in_place]
+        end
+        options[:shuffle] = true if command == :shuffle
+        raise Error, "--per-group only applies to head and sample" if options[:per_group] && ![:head, :sample].include?(command)
+        raise Error, "--sample-by only applies to sample" if options[:sample_by] && command != :sample
+        raise Error, "--sample-by cannot be combined with --per-group" if options[:sample_by] && options[:per_group]
+        raise Error, "--balanced requires --sample-by" if options[:balanced] && !options[:sample_by]
+        raise Error, "--shuffle cannot be combined with --key" if options[:shuffle] && options[:sort_keys]&.any?
+        check_external_sort(file, command, options) if options[:external_sort]
+        # Sorting every row only for sampling to discard most of them (or --sample-method full to
+        # shuffle them) is wasted work; sample sorts the rows it keeps instead
+        options[:sample_sort_keys] = options.delete(:sort_keys) if command == :sample && options[:sort_keys]&.any?
+        if options[:partition_by]
+          raise Error, "--partition-by requires -o/--output naming a directory" unless options[:output]
+          [:append, :limit_bytes, :in_place, :write_schema].each do |key|
+            raise Error, "--partition-by cannot be combined with --#{key.to_s.tr('_', '-')}" if options[key]
+          end
+        elsif options[:output]
+          options[:output] = resolve_output_path(file, options[:output])
+        end
+        if options[:write_schema] && ![:csv, :tsv].include?(options[:output] && output_format(options[:output], options))
+          raise Error, "--write-schema requires -o/--output with a CSV or TSV file"
+        end
+        # clean applies --trim itself, ahead of the rest of the pipeline, so it can count what changed
+        options[:clean_trim] = options.delete(:trim) if command == :clean
+
         df, use_shell_optimization = read_input(file, command, options)
+        return execute_explain(df, command, options) if options[:explain]
+        return execute_explain_streaming(df, file, command, options, use_shell_optimization) if options[:explain_streaming]
 
         result = case command
         when :cat    then execute_cat(df, file, options, use_shell_optimization)
         when :head   then execute_head(df, file, options, use_shell_optimization)
         when :tail   then execute_tail(df, file, options, use_shell_optimization)
+        when :peek   then execute_peek(df, options)
         when :sample then execute_sample(df, file, options)
+        when :clean  then output_dataframe(df, file, options, default_pretty: true)
+        when :shuffle then output_dataframe(df, file, options, default_pretty: true)
         when :less   then execute_less(df, file, options, use_shell_optimization)
-        when :lint   then execute_lint(collect_if_lazy(df), options)
-        when :stats  then execute_stats(collect_if_lazy(df), options)
-        when :count  then execute_count(df, options)
+        when :lint   then execute_lint(collect_if_lazy(df), file, options)
+        when :stats  then execute_stats(options[:fast] ? df : collect_if_lazy(df), file, options)
+        when :count  then execute_count(df, file, options)
+        when :nunique then execute_nunique(df, file, options)
+        when :resample then execute_resample(df, file, options)
+        when :convert then execute_convert(df, file, options)
         else raise Error, "Unknown command: #{command}"
         end
 
+        # Explicitly requested, so printed even with --quiet
+        if options[:timings]
+          warn timer(options).summary
+        elsif options[:time]
+          warn timer(options).report
+        elsif Log.verbose?
+          Log.debug(timer(options).summary)
+        end
         result
       rescue Interrupt
         raise
@@ -47,6 +175,14 @@ module TabularTool
         # TypeErrors during interrupt likely mean interrupted IO left nil values
         raise Interrupt if e.message.include?("no implicit conversion of nil")
         raise
+      ensure
+        # Transcoded --encoding copies of the input belong to this run; --watch would pile them up
+        options[:temp_files]&.each(&:close!)
+      end
+
+      # apply_transformations for TabularTool.transform; the caller's options stay untouched
+      def transform(df, options)
+        apply_transformations(df, options.dup)
       end
 
       private
@@ -56,24 +192,63 @@ module TabularTool
 
         first_arg = argv.first
 
-        case first_arg
-        when "cat", "head", "tail", "sample", "less", "lint", "stats", "count"
+        if COMMANDS.include?(first_arg.to_sym)
           args[:command] = argv.shift.to_sym
           parse_numeric_args(argv, args)
-        else
+        elsif argv.length > 1 && !first_arg.include?(".") && !first_arg.include?("/")
           # Heuristic: paths contain . or /, command names don't
           # If multiple args remain and first_arg doesn't look like a path, it's likely a typo
-          if argv.length > 1 && !first_arg.include?(".") && !first_arg.include?("/")
-            raise Error, "Unknown command: #{first_arg}. Valid commands: cat, head, tail, sample, less, lint, stats, count"
-          end
+          raise Error, "Unknown command: #{first_arg}. Valid commands: #{COMMANDS.join(', ')}"
+        end
+      end
+
+      # --config is consumed before regular parsing so its defaults can be applied first
+      def extract_config_path!(argv)
+        index = argv.index { |arg| arg == "--config" || arg.start_with?("--config=") }
+        return unless index
+
+        arg = argv.delete_at(index)
+        path = arg.include?("=") ? arg.split("=", 2).last : argv.delete_at(index)
+        raise Error, "--config requires a file path" if path.nil? || path.empty?
+        raise Error, "Config file not found: #{path}" unless File.exist?(path)
+        path
+      end
+
+      # `convert IN OUT`, or any number of inputs with --output-template
+      def parse_convert_args(argv, args)
+        if args[:output_template]
+          args[:convert_inputs] = argv.dup
+          argv.replace(argv.first(1))
+        elsif argv.length == 2 && !args[:output]
+          args[:output] = argv.pop
         end
       end
 
+      # `stats a.csv b.csv`: one combined table rather than a printout per file
+      def parse_stats_args(argv, args)
+        return unless argv.length > 1
+
+        args[:stats_inputs] = argv.dup
+        argv.replace(argv.first(1))
+      end
+
+      # Config values are parsed as flags ahead of argv, so explicit flags win
+      def apply_config(parser, path)
+        path ||= Config.default_path
+        return unless path && File.file?(path)
+
+        parser.parse!(Config.to_argv(Config.load(path)))
+      rescue OptionParser::ParseError => e
+        raise Error, "Invalid config #{path}: #{e.message}"
+      end
+
       def parse_numeric_args(argv, args)
-        if [:head, :tail].include?(args[:command]) && argv.first && argv.first.match?(/^\d+$/)
+        if [:head, :tail].include?(args[:command]) && numeric_positional?(argv.first, /^\d+$/)
+          args[:positional_count] = argv.first
           args[:limit] = argv.shift.to_i
         elsif args[:command] == :sample
-          if argv.first && argv.first.match?(/^\.?\d+\.?\d*$/)
+          if numeric_positional?(argv.first, /^\.?\d+\.?\d*$/)
+            args[:positional_count] = argv.first
             value = argv.shift
             num = value.to_f
 
@@ -86,60 +261,426 @@ module TabularTool
           else
             args[:sample_n] = 10
           end
+        elsif args[:command] == :resample && argv.length > 1 && !argv.first.start_with?("-")
+          # `resample ts ...`: the timestamp column comes before the options
+          args[:resample_column] = argv.shift
+        end
+      end
+
+      # "10MB", "512K", "1.5GiB" or plain bytes; K/M/G/T are powers of 1024
+      def parse_byte_size(value)
+        match = value.strip.match(/\A(\d+(?:\.\d+)?)\s*([KMGT]?)(?:i?B)?\z/i)
+        raise Error, "Invalid byte size: #{value} (e.g. 500K, 10MB, 2G)" unless match
+
+        exponent = ["", "K", "M", "G", "T"].index(match[2].upcase)
+        (match[1].to_f * 1024**exponent).to_i
+      end
+
+      # A number that is also a file on disk (e.g. `tt head 2024`) is treated as the file
+      def numeric_positional?(arg, pattern)
+        return false unless arg&.match?(pattern)
+
+        if File.exist?(arg)
+          Log.warn "treating '#{arg}' as a file; use -n N to set the row count"
+          return false
         end
+        true
+      end
+
+      # -n/--rows takes precedence over the positional `head 10` form
+      def resolve_row_count(args)
+        positional = args.delete(:positional_count)
+        rows = args[:rows]
+        return unless rows
+
+        case args[:command]
+        when :head, :tail
+          args[:limit] = rows
+        when :sample
+          args[:sample_n] = rows
+          args.delete(:sample_fraction)
+        else
+          return
+        end
+        Log.warn "-n #{rows} overrides positional count #{positional}" if positional
       end
 
       def read_input(file, command, options)
         raise Error, "No input file specified" unless file
+
+        if file == STDIN_PATH
+          raise Error, "--in-place cannot be used with stdin input" if options[:in_place]
+          df = timer(options).measure("read") { read_stdin(options) }
+          df = timer(options).measure("clean") { apply_cleaning(df, options) } if command == :clean
+          return [timer(options).measure("transform") { apply_transformations(df, options) }, false]
+        end
+
         raise Error, "File not found: #{file}" unless File.exist?(file)
 
+        sniff_delimiter(file, options)
+
+        # Too big to permute in memory: stay lazy and shuffle through temp buckets at write time
+        if [:cat, :shuffle].include?(command) && options[:shuffle] && options[:output] &&
+           File.size(file) > EXTERNAL_SHUFFLE_BYTES
+          options[:external_shuffle] = (File.size(file).to_f / EXTERNAL_SHUFFLE_BYTES).ceil
+        end
+        # Sorted runs are spilled to temp files and merged at write time
+        options[:external_sort_runs] = (File.size(file).to_f / EXTERNAL_SORT_RUN_BYTES).ceil if options[:external_sort]
+        # Before the shell fast path is chosen, since a forced scan rules it out
+        enforce_memory_budget(file, command, options) if options[:max_memory]
+
         use_shell_optimization = should_use_shell_decompression?(file, command, options)
+        bar = progress_bar(options)
+
+        # Lazy scans let Polars push limits and filters into the reader
+        streaming = options[:streaming]
+        streaming = true if streaming.nil? && (options[:explain] || parquet_head_fast_path?(file, command, options) ||
+                                               tail_fast_path?(file, command, options) || lazy_jsonl?(file, options) ||
+                                               (command == :stats && options[:fast]) ||
+                                               options[:external_shuffle] || options[:external_sort_runs])
+
+        # Checked on raw bytes before reading; a bad file is then read lossily so lint can still run
+        read_options = options[:encoding] ? { encoding: options[:encoding] } : {}
+        read_options.merge!(schema: options[:input_schema], strict_schema: options[:strict_schema]) if options[:input_schema]
+        read_options[:infer_rows] = options[:json_infer_rows] if options[:json_infer_rows]
+        if command == :lint && options[:check_encoding]
+          options[:encoding_result] = timer(options).measure("encoding") do
+            Formats.open_raw(file) { |io| Operations::Lint.encoding_violations(io) }
+          end
+          if options[:encoding_result][:count] > 0 && [:csv, :tsv].include?(input_format(file, options)) &&
+             !options[:encoding]
+            read_options[:encoding] = "utf8-lossy"
+          end
+        end
+
+        Log.debug "read: #{file} (#{input_format(file, options)}, #{File.size(file)} bytes" \
+                  "#{', streaming' if streaming}#{', shell decompression' if use_shell_optimization})"
 
         df = nil
         unless use_shell_optimization
-          df = Formats.read(
-            file,
-            delimiter: options[:delimiter],
-            has_header: !options[:no_header],
-            streaming: options[:streaming],
-          )
+          bar.start("Reading #{File.basename(file)}", total: File.size(file))
+          df = timer(options).measure("read") do
+            Formats.read(
+              file,
+              format: options[:input_format],
+              delimiter: options[:delimiter],
+              has_header: !options[:no_header],
+              parse_dates: options[:parse_dates],
+              streaming: streaming,
+              progress: bar,
+              temp_files: options[:temp_files] ||= [],
+              **read_options,
+            )
+          end
+          timer(options).rows_in = df.height if df.is_a?(Polars::DataFrame)
+          options[:lazy_read] = df.is_a?(Polars::LazyFrame)
+          df = resolve_duplicate_columns(df, file, command, options)
 
-          df = apply_transformations(df, options)
+          df = timer(options).measure("clean") { apply_cleaning(df, options) } if command == :clean
+          df = timer(options).measure("transform") { apply_transformations(df, options) }
+          bar.finish(rows: df.is_a?(Polars::DataFrame) ? df.height : nil)
         end
 
         [df, use_shell_optimization]
       end
 
+      # Polars names a repeated header amount_duplicated_0; apply --on-duplicate-columns instead
+      # Only plain CSV/TSV files, whose header can be peeked; lint reports the duplicates itself
+      def resolve_duplicate_columns(df, file, command, options)
+        return df if options[:no_header]
+
+        header = Formats.read_header(file, format: input_format(file, options), delimiter: options[:delimiter])
+        return df if header.nil? || header.uniq.length == header.length
+        # Quoted delimiters in the header would misalign positions; leave Polars' names alone
+        return df unless header.length == df.columns.length
+
+        duplicates = header.tally.select { |_, count| count > 1 }.keys
+        policy = command == :lint ? :rename : options[:on_duplicate_columns] || :rename
+
+        case policy
+        when :error
+          raise Error, "Duplicate column name(s) in header: #{duplicates.join(', ')} " \
+                       "(use --on-duplicate-columns rename|keep-first to read it anyway)"
+        when :keep_first
+          Log.warn "duplicate column(s) #{duplicates.join(', ')}: keeping the first of each"
+          df.select(header.each_index.select { |i| header.index(header[i]) == i }.map { |i| df.columns[i] })
+        else
+          names = Formats.dedupe_names(header)
+          unless command == :lint
+            renamed = header.zip(names).reject { |old, new| old == new }.map { |old, new| "#{old} -> #{new}" }
+            Log.warn "renamed duplicate column(s): #{renamed.join(', ')}"
+          end
+          options[:duplicate_columns] = duplicates.to_h { |name| [name, names.select.with_index { |_, i| header[i] == name }] }
+          df.rename(df.columns.zip(names).reject { |old, new| old == new }.to_h)
+        end
+      end
+
+      # A .csv that is really ;- or |-delimited reads as one wide column, so a one-column header
+      # triggers the same guess --sniff-delimiter asks for; an explicit --delimiter always wins
+      def sniff_delimiter(file, options)
+        return if options[:delimiter]
+
+        format = input_format(file, options)
+        return unless [:csv, :tsv].include?(format)
+        unless options[:sniff_delimiter]
+          header = Formats.read_header(file, format: format)
+          return unless header&.length == 1
+        end
+
+        delimiter = Formats.sniff_delimiter(file)
+        default = format == :tsv ? "\t" : ","
+        return if delimiter.nil? || (delimiter == default && !options[:sniff_delimiter])
+
+        options[:delimiter] = delimiter unless delimiter == default
+        Log.info "detected delimiter #{delimiter.inspect}"
+      end
+
+      # Over the --max-memory budget, a command that can stream is forced to; one with a step that
+      # needs the whole input in memory is refused before reading anything
+      def enforce_memory_budget(file, command, options)
+        estimate = Formats.memory_estimate(file, format: input_format(file, options))
+        return if estimate <= options[:max_memory]
+
+        needed = "#{file} needs an estimated #{Progress.format_bytes(estimate)} in memory, " \
+                 "over --max-memory #{Progress.format_bytes(options[:max_memory])}"
+        raise Error, "#{needed}; drop --no-streaming" if options[:streaming] == false
+
+        # A sort that won't fit is done externally, in runs of about half the budget each
+        if !options[:external_sort_runs] && external_sort_applies?(command, options)
+          options[:external_sort_runs] = (estimate * 2.0 / options[:max_memory]).ceil
+        end
+        fallbacks = streaming_fallbacks(file, command, options.merge(lazy_read: true), false)
+        # Window sampling collects one row per query, which stays small however big the file is
+        fallbacks.reject! { |stage, _| stage == :sample } if options[:sample_method] == :window
+        if fallbacks.any?
+          raise Error, "#{needed}, and these steps hold the whole input in memory:\n" +
+                       fallbacks.map { |stage, reason| "  #{stage}: #{reason}" }.join("\n")
+        end
+
+        options[:streaming] = true
+        Log.info "#{needed}; streaming it"
+      end
+
+      def read_stdin(options)
+        require 'stringio'
+
+        bar = progress_bar(options)
+        bar.start("Reading stdin")
+        data = bar.wrap($stdin.binmode).read
+        bar.finish
+        # Auto-detect an Arrow IPC stream from an upstream `tt --pipe-format arrow`
+        format = options[:pipe_format] == :arrow || Formats.arrow_stream?(data) ? :arrow : options[:input_format] || :csv
+        unless format == :arrow
+          source = Formats::INPUT_ENCODINGS[options[:encoding]]
+          data = if source.is_a?(Encoding)
+                   data.force_encoding(source).encode(Encoding::UTF_8, invalid: :replace, undef: :replace)
+                 else
+                   data.force_encoding(Encoding::UTF_8)
+                 end
+          data = data.scrub if source == "utf8-lossy"
+        end
+
+        # There's no header to check stdin against, so the given types only override inference
+        schema_options = {}
+        if options[:input_schema]
+          raise Error, "--input-schema only applies to CSV/TSV input, not #{format}" if format == :arrow
+          raise Error, "--strict-schema cannot be used with stdin input" if options[:strict_schema]
+
+          schema_options[:schema_overrides] = options[:input_schema]
+        end
+
+        Formats.read_from_io(StringIO.new(data),
+          format: format,
+          delimiter: options[:delimiter],
+          has_header: !options[:no_header],
+          parse_dates: options[:parse_dates],
+          **schema_options,
+        )
+      end
+
       def execute_cat(df, file, options, use_shell_optimization)
-        df = read_compressed_full(file, options: options) if use_shell_optimization
+        df = timer(options).measure("read") { read_compressed_full(file, options: options) } if use_shell_optimization
         output_dataframe(df, file, options, default_pretty: true)
       end
 
       def execute_head(df, file, options, use_shell_optimization)
         if use_shell_optimization
-          df = read_compressed_partial(file, command: :head, limit: options[:limit] || 10, options: options)
+          df = timer(options).measure("read") do
+            read_compressed_partial(file, command: :head, limit: options[:limit] || 10, options: options)
+          end
+        elsif options[:per_group]
+          df = timer(options).measure("collect") do
+            Operations.head_per_group(df, n: options[:limit] || 10, by: options[:per_group])
+          end
         else
-          df = Operations.head(df, n: options[:limit] || 10)
+          df = timer(options).measure("collect") { Operations.head(df, n: options[:limit] || 10) }
         end
         output_dataframe(df, file, options, default_pretty: true)
       end
 
       def execute_tail(df, file, options, use_shell_optimization)
         if use_shell_optimization
-          df = read_compressed_partial(file, command: :tail, limit: options[:limit] || 10, options: options)
+          df = timer(options).measure("read") do
+            read_compressed_partial(file, command: :tail, limit: options[:limit] || 10, options: options)
+          end
         else
           # Operations.tail now handles lazy frames efficiently, no need to collect first
-          df = Operations.tail(df, n: options[:limit] || 10)
+          df = timer(options).measure("collect") { Operations.tail(df, n: options[:limit] || 10) }
         end
         output_dataframe(df, file, options, default_pretty: true)
       end
 
+      def execute_peek(df, options)
+        result = timer(options).measure("collect") { Operations.peek(df, n: options[:rows] || 5) }
+        timer(options).measure("format") { format_peek(result) }
+      end
+
+      # "rows × cols", then a table with dtypes under the header and … between head and tail
+      def format_peek(result)
+        head = result[:head]
+        rows = head.rows.map { |row| row.map { |value| peek_cell(value) } }
+        if result[:tail]
+          rows << Array.new(head.width, "…")
+          rows.concat(result[:tail].rows.map { |row| row.map { |value| peek_cell(value) } })
+        end
+
+        table = [head.columns, head.dtypes.map { |dtype| Dtypes.name(dtype) }] + rows
+        widths = head.columns.each_index.map { |i| table.map { |row| row[i].length }.max }
+        lines = table.map { |row| row.each_with_index.map { |cell, i| cell.ljust(widths[i]) }.join("  ").rstrip }
+
+        "#{result[:total]} rows × #{head.width} cols\n#{lines.join("\n")}"
+      end
+
+      # One "-[ RECORD n ]-" block per row with a "field | value" line per column, like psql's \x
+      def format_columns_as_rows(df)
+        width = df.columns.map(&:length).max || 0
+        blocks = df.rows.each_with_index.map do |row, i|
+          lines = df.columns.zip(row).map { |field, value| "#{field.ljust(width)} | #{value.nil? ? 'null' : value}" }
+          ["-[ RECORD #{i + 1} ]-", *lines].join("\n")
+        end
+        blocks.empty? ? "(0 rows)" : blocks.join("\n")
+      end
+
+      def peek_cell(value)
+        text = value.nil? ? "null" : value.to_s
+        text.length > PEEK_MAX_WIDTH ? "#{text[0, PEEK_MAX_WIDTH - 1]}…" : text
+      end
+
       def execute_sample(df, file, options)
         # Operations.sample now handles lazy frames efficiently, no need to collect first
-        if options[:sample_fraction]
-          df = Operations.sample(df, fraction: options[:sample_fraction])
-        else
-          df = Operations.sample(df, n: options[:sample_n])
+        method = options[:sample_method] || :reservoir
+        Log.debug "sample: method=#{method}"
+        # --offset/--limit pick the population; N or the fraction then applies to what's left
+        df = offset_and_limit(df, options)
+        options.delete(:offset)
+        options.delete(:row_limit)
+
+        df = timer(options).measure("sample") do
+          if options[:sample_by]
+            Operations.sample_stratified(df, by: options[:sample_by], n: options[:sample_n],
+                                             fraction: options[:sample_fraction], balanced: options[:balanced],
+                                             seed: options[:seed])
+          elsif options[:per_group]
+            Operations.sample_per_group(df, by: options[:per_group], n: options[:sample_n],
+                                            fraction: options[:sample_fraction], seed: options[:seed])
+          elsif options[:sample_fraction]
+            Operations.sample(df, fraction: options[:sample_fraction], method: method, seed: options[:seed],
+                                  total_rows: row_count(df, options))
+          else
+            Operations.sample(df, n: options[:sample_n], method: method, seed: options[:seed],
+                                  total_rows: row_count(df, options))
+          end
+        end
+        if options[:sample_sort_keys]
+          df = timer(options).measure("sort") { sort_rows(df, options[:sample_sort_keys], options) }
+        end
+        output_dataframe(df, file, options, default_pretty: true)
+      end
+
+      # Conversions always stream to a sink and never guess a format, since a wrong guess
+      # silently produces a bad file; the output is re-counted afterwards
+      def check_convert(file, options)
+        raise Error, "convert needs an output: tt convert IN OUT (or -o OUT, or --output-template)" unless options[:output]
+        [:partition_by, :append, :limit_bytes, :in_place].each do |key|
+          raise Error, "convert writes one complete file; --#{key.to_s.tr('_', '-')} doesn't apply" if options[key]
+        end
+        if file && file != STDIN_PATH && !options[:input_format] && !Formats.recognized_format?(file)
+          raise Error, "convert: can't tell the format of #{file} from its extension; pass --from FORMAT"
+        end
+        if !options[:output_format] && !Formats.recognized_format?(options[:output])
+          raise Error, "convert: can't tell the format of #{options[:output]} from its extension; pass --to FORMAT"
+        end
+
+        options[:streaming] = true
+      end
+
+      # {stem} is the input name without extensions, {name} the whole file name, {dir} its directory
+      def template_output(template, input, inputs)
+        if inputs.length > 1 && !template.match?(/\{(?:stem|name)\}/)
+          raise Error, "--output-template needs {stem} or {name} so each input gets its own output"
+        end
+
+        name = File.basename(input)
+        stem = name.sub(/\.(gz|zst)$/i, "").sub(/\.[^.]*\z/, "")
+        output = template.gsub("{stem}", stem).gsub("{name}", name).gsub("{dir}", File.dirname(input))
+        FileUtils.mkdir_p(File.dirname(output))
+        output
+      end
+
+      def execute_convert(df, file, options)
+        output = options[:output]
+        df = offset_and_limit(df, options)
+        options.delete(:offset)
+        options.delete(:row_limit)
+        expected = options[:header_only] ? 0 : timer(options).measure("count") { row_count(df, options) }
+
+        output_dataframe(df, file, options)
+        written = timer(options).measure("verify") { output_row_count(output, options) }
+        if written != expected
+          raise Error, "convert: #{output} has #{written} rows but #{file} has #{expected}; the output is incomplete"
+        end
+
+        out_size = File.size(output)
+        summary = "-> #{output} (#{Progress.format_bytes(out_size)}): #{written} rows"
+        return "stdin #{summary}" if file == STDIN_PATH
+
+        in_size = File.size(file)
+        ratio = out_size.zero? ? "" : format(", ratio %.1fx", in_size.fdiv(out_size))
+        "#{file} (#{Progress.format_bytes(in_size)}) #{summary}#{ratio}"
+      end
+
+      # Counted from the written file, so a short write can't go unnoticed; Parquet answers from its footer
+      def output_row_count(path, options)
+        Operations.row_count(Formats.read(path, format: output_format(path, options),
+                                                delimiter: options[:output_delimiter], streaming: true))
+      end
+
+      # Memoized per frame for the run, like timer and progress_bar, so a count taken for one step
+      # (convert's check, sample's population) isn't repeated by the next (external sort's runs)
+      def row_count(df, options)
+        counts = options[:row_counts] ||= {}.compare_by_identity
+        counts.fetch(df) { counts[df] = Operations.row_count(df) }
+      end
+
+      # Memoized the same way: the transformations and stats --fast both need the input's schema
+      def frame_schema(df, options)
+        schemas = options[:schemas] ||= {}.compare_by_identity
+        schemas.fetch(df) { schemas[df] = Operations.schema(df) }
+      end
+
+      def execute_resample(df, file, options)
+        raise Error, "resample needs a timestamp column: tt resample COLUMN --every 1h FILE" unless options[:resample_column]
+        raise Error, "resample needs --every (e.g. 1h, 15m, 1d)" unless options[:every]
+
+        df = timer(options).measure("aggregate") do
+          Operations::Resample.call(
+            df,
+            column: options[:resample_column],
+            every: options[:every],
+            aggregations: Operations::Resample.parse_aggregations(options[:agg] || "count()"),
+            by: options[:resample_by] || [],
+            fill_gaps: options[:fill_gaps],
+          )
         end
         output_dataframe(df, file, options, default_pretty: true)
       end
@@ -148,14 +689,8 @@ module TabularTool
         output_file = options[:in_place] ? file : options[:output]
 
         if output_file
-          df = read_compressed_full(file, options: options) if use_shell_optimization
-          df = collect_if_lazy(df)
-          Formats.write(
-            df,
-            output_file,
-            delimiter: options[:output_delimiter],
-            compression: options[:compression],
-          )
+          df = timer(options).measure("read") { read_compressed_full(file, options: options) } if use_shell_optimization
+          write_output(collect_if_lazy(df), output_file, options)
           nil
         elsif $stdout.tty?
           # For compressed files: always use shell bypass for performance
@@ -164,35 +699,183 @@ module TabularTool
           if compressed_file?(file) && can_bypass_dataframe?(options)
             page_compressed_file_directly(file, options)
           else
-            df = read_compressed_full(file, options: options) if use_shell_optimization
+            df = timer(options).measure("read") { read_compressed_full(file, options: options) } if use_shell_optimization
             df = collect_if_lazy(df)
             use_pretty = options[:pretty] != false
             output_to_pager(df, pretty: use_pretty)
           end
           nil
         else
-          df = read_compressed_full(file, options: options) if use_shell_optimization
+          df = timer(options).measure("read") { read_compressed_full(file, options: options) } if use_shell_optimization
           output_dataframe(df, file, options, default_pretty: false)
         end
       end
 
-      def execute_count(df, options)
-        count = df.is_a?(Polars::LazyFrame) ? df.collect.height : df.height
+      def execute_explain(df, command, options)
+        unless EXPLAIN_COMMANDS.include?(command)
+          raise Error, "--explain supports: #{EXPLAIN_COMMANDS.join(', ')}"
+        end
+
+        lf = explain_plan(df, command, options)
+        output = lf.explain
+        return output unless options[:analyze]
+
+        started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+        result = lf.collect
+        result = result.describe if command == :stats
+        elapsed_ms = ((Process.clock_gettime(Process::CLOCK_MONOTONIC) - started) * 1000).round(1)
+
+        "#{output}\n\nExecuted in #{elapsed_ms} ms, #{result.height} rows produced"
+      end
+
+      def explain_plan(df, command, options)
+        lf = df.lazy
+        case command
+        when :head  then lf.head(options[:limit] || 10)
+        when :tail  then lf.tail(options[:limit] || 10)
+        when :count then lf.select(Polars.len.alias("count"))
+        else lf
+        end
+      end
+
+      # Whether the command runs scan-to-sink with bounded memory, and if not, every step that
+      # holds the whole input in memory. Nothing is executed; the plan below is Polars' own
+      def execute_explain_streaming(df, file, command, options, use_shell_optimization)
+        fallbacks = streaming_fallbacks(file, command, options, use_shell_optimization)
+        lines = if fallbacks.empty?
+                  ["streaming: yes (scan to output without holding the input in memory)"]
+                else
+                  ["streaming: no (#{fallbacks.length} in-memory step#{'s' if fallbacks.length > 1})"] +
+                    fallbacks.map { |stage, reason| "  #{stage}: #{reason}" }
+                end
+        if df
+          plan = explain_plan(df, command, options).explain
+          lines << "plan:" << plan.lines.map { |line| "  #{line.chomp}" }.join("\n")
+        end
+        lines.join("\n")
+      end
+
+      # [stage, reason] for each step of the command that materializes the frame
+      def streaming_fallbacks(file, command, options, use_shell_optimization)
+        fallbacks = []
+        format = file == STDIN_PATH ? nil : input_format(file, options)
+
+        if file == STDIN_PATH
+          fallbacks << [:read, "stdin is buffered whole before parsing"]
+        elsif use_shell_optimization
+          fallbacks << [:read, "compressed input is decompressed through a shell pipe and parsed in memory " \
+                               "(--streaming scans it with Polars instead)"]
+        elsif format == :json
+          fallbacks << [:read, "JSON arrays have no lazy reader (use .jsonl, .parquet or .arrow)"]
+        elsif !options[:lazy_read]
+          reason = if options[:streaming] == false
+                     "--no-streaming reads the whole file"
+                   else
+                     "smaller than the #{Formats::STREAMING_THRESHOLD / 1024 / 1024} MiB streaming threshold, " \
+                       "so read eagerly (--streaming forces a scan)"
+                   end
+          fallbacks << [:read, reason]
+        end
+
+        fallbacks << [:transform, "--unique/--unique-on deduplicate in memory"] if options[:unique] || options[:unique_on]
+        if options[:sort_keys]&.any? && !options[:external_sort_runs]
+          fallbacks << [:transform, "-k/--key sorts the whole frame in memory (--external-sort spills to disk)"]
+        end
+        if options[:shuffle] && !options[:external_shuffle]
+          fallbacks << [:transform, "--shuffle permutes the whole frame in memory"]
+        end
+        if options[:over] && (options[:cumsum] || options[:diff] || options[:lag])
+          fallbacks << [:transform, "--cumsum/--diff/--lag --over evaluate each window over the whole frame"]
+        end
+        windows = options[:with].to_a.select { |spec| spec.key?(:partition_by) }
+        if windows.any? { |spec| spec[:order_by].any? }
+          fallbacks << [:transform, "--with ... OVER (ORDER BY ...) sorts the whole frame twice"]
+        elsif windows.any?
+          fallbacks << [:transform, "--with ... OVER (...) evaluates windows over the whole frame"]
+        end
+        if options[:offset]&.negative?
+          fallbacks << [:transform, "--offset -N keeps the last rows with a negative-offset slice, " \
+                                    "which buffers the input"]
+        end
+
+        case command
+        when :lint, :less, :resample, :clean
+          fallbacks << [command, "collects the whole frame"]
+        when :stats
+          fallbacks << [:stats, "collects the whole frame (--fast stays lazy)"] unless options[:fast]
+        when :peek
+          fallbacks << [:peek, "the last rows come from a negative-offset slice, which buffers the input"]
+        when :sample
+          if options[:sample_by] || options[:per_group]
+            fallbacks << [:sample, "grouped sampling collects the frame to shuffle it"]
+          elsif options[:sample_method] == :full
+            fallbacks << [:sample, "--method full collects everything"]
+          elsif options[:sample_method] == :window
+            fallbacks << [:sample, "--method window runs one slice query per sampled row"]
+          end
+        end
+
+        # Bounded commands print at most N rows, so collecting their result for stdout is cheap
+        output = options[:in_place] ? file : options[:output]
+        if output.nil?
+          if [:cat, :shuffle, :clean].include?(command)
+            fallbacks << [:output, "stdout output is collected before printing (write with -o to stream)"]
+          end
+        elsif options[:partition_by]
+          fallbacks << [:output, "--partition-by collects the frame to split it"]
+        elsif options[:append]
+          fallbacks << [:output, "--append collects the frame before writing"]
+        elsif output_format(output, options) == :json
+          fallbacks << [:output, "JSON arrays have no sink (use .jsonl for large outputs)"]
+        end
+
+        fallbacks
+      end
+
+      # What this installation can actually do, for telling a missing capability from a bad file
+      def version_report
+        tools = SHELL_TOOLS.map { |tool| "#{tool} #{executable?(tool) ? 'found' : 'missing'}" }
+        [
+          "tt #{VERSION}",
+          "polars-df #{Polars::VERSION}",
+          "ruby #{RUBY_VERSION}",
+          "formats: #{Formats::SUPPORTED_FORMATS.join(', ')}",
+          "unavailable: orc (polars-df has no ORC reader)",
+          "shell tools: #{tools.join(', ')}",
+        ].join("\n")
+      end
+
+      def executable?(name)
+        ENV.fetch("PATH", "").split(File::PATH_SEPARATOR).any? do |dir|
+          path = File.join(dir, name)
+          File.file?(path) && File.executable?(path)
+        end
+      end
+
+      def execute_count(df, file, options)
+        count = timer(options).measure("count") { row_count(df, options) }
+
+        # A one-row table, so -o gives a file other tools (and a later `tt cat`) can read
         if options[:output]
-          File.write(options[:output], "#{count}\n")
-          nil
-        else
-          "#{count}"
+          counts = Polars::DataFrame.new({ "rows" => [count], "cols" => [df.columns.length], "path" => [file] })
+          timer(options).measure("write") { write_output(counts, options[:output], options) }
+          return nil
+        end
+
+        output = "#{count}"
+        if options[:verbose]
+          # One indented "name: dtype" line per column, same layout for every input format
+          output += df.schema.map { |col, dtype| "\n  #{col}: #{Dtypes.name(dtype)}" }.join
         end
+        output
       end
 
       def can_bypass_dataframe?(options)
         options[:sort_keys]&.empty? != false &&
-          !options[:where] &&
           !options[:select] &&
+          !options[:select_dtypes] &&
           !options[:drop] &&
-          !options[:unique] &&
-          !options[:unique_on]
+          ROW_TRANSFORM_OPTIONS.none? { |key| options[key] }
       end
 
       def page_compressed_file_directly(file, options)
@@ -212,6 +895,24 @@ module TabularTool
         raise Error, "Decompression tool not found: #{e.message}. Please install gzip or zstd."
       end
 
+      def timer(options)
+        options[:timer] ||= Timings.new
+      end
+
+      def progress_bar(options)
+        options[:progress_bar] ||= Progress.new(enabled: options[:progress])
+      end
+
+      def rows_written(df, output_file, options)
+        return df.height if df.is_a?(Polars::DataFrame)
+
+        # Sinks don't report row counts, so they're counted back from the written file (Parquet
+        # answers from its footer); a partitioned directory or compressed output has no one file to scan
+        return if options[:partition_by] || compressed_file?(output_file)
+
+        output_row_count(output_file, options)
+      end
+
       def collect_if_lazy(df)
         df.is_a?(Polars::LazyFrame) ? df.collect : df
       end
@@ -226,15 +927,64 @@ module TabularTool
         # Shell-based decompression is 2-100x faster and interruptible (Polars blocks in Rust)
         return false unless [:cat, :head, :tail, :less].include?(command)
         return false if options[:sort_keys]&.any?
-        return false if options[:unique] || options[:unique_on]
-        return false if options[:where]
+        return false if ROW_TRANSFORM_OPTIONS.any? { |key| options[key] }
         return false if options[:streaming]
+        return false if options[:explain]
+        # The shell pipe hands Polars raw bytes, with no chance to transcode them
+        return false if options[:encoding]
+        # Likewise with no header to check --input-schema against
+        return false if options[:input_schema]
 
         true
       end
 
-      def detect_format_without_compression(file)
-        file.sub(/\.(gz|zst)$/i, '').then { |base| Formats.detect_format(base) }
+      def parquet_head_fast_path?(file, command, options)
+        return false unless command == :head
+        return false unless input_format(file, options) == :parquet
+        return false unless options[:streaming].nil?
+
+        # Row-altering transformations need the full file; select/drop are pushed into the scan
+        row_filters = [:where, :expr_filter, :where_null, :where_not_null, :where_in, :keep_keys_file, :drop_keys_file,
+                       :unique, :unique_on, :shuffle]
+        row_filters.none? { |key| options[key] } && options[:sort_keys].to_a.empty?
+      end
+
+      # tail scans lazily so memory stays proportional to N rather than the file
+      # Filters stream fine; sorting and dedupe need every row anyway
+      def tail_fast_path?(file, command, options)
+        return false unless command == :tail
+        return false unless options[:streaming].nil?
+        return false if compressed_file?(file)
+        return false unless [:parquet, :csv, :tsv].include?(input_format(file, options))
+
+        !options[:unique] && !options[:unique_on] && !options[:shuffle] && options[:sort_keys].to_a.empty?
+      end
+
+      # stats --fast can answer from the Parquet footer only while the frame still holds the file's
+      # rows and values; --select new=old renames, and the footer only knows the file's names
+      def footer_stats_path(file, options)
+        return if file.nil? || file == STDIN_PATH || compressed_file?(file)
+        return unless input_format(file, options) == :parquet
+        return if ROW_TRANSFORM_OPTIONS.any? { |key| options[key] }
+        return if Array(options[:select]).any? { |token| token.include?("=") }
+
+        file
+      end
+
+      # JSON lines are scanned lazily at any size, so head, filters and counts don't load the whole file;
+      # --in-place is left eager so the result isn't sunk into the file still being scanned
+      def lazy_jsonl?(file, options)
+        input_format(file, options) == :jsonl && !compressed_file?(file) && !options[:in_place]
+      end
+
+      # --input-format wins over the file extension
+      def input_format(file, options)
+        options[:input_format] || Formats.detect_format(file)
+      end
+
+      # --output-format wins over the output extension (extensionless paths, FIFOs)
+      def output_format(output_file, options)
+        options[:output_format] || Formats.detect_format(output_file)
       end
 
       def read_compressed_full(file, options:)
@@ -246,17 +996,20 @@ module TabularTool
                        end
 
         cmd = "#{decompressor} #{Shellwords.escape(file)}"
+        bar = progress_bar(options)
+        bar.start("Decompressing #{File.basename(file)}")
 
         IO.popen(cmd) do |io|
-          df = Formats.read_from_io(io,
-            format: detect_format_without_compression(file),
+          df = Formats.read_from_io(bar.wrap(io),
+            format: input_format(file, options),
             delimiter: options[:delimiter],
             has_header: !options[:no_header],
+            parse_dates: options[:parse_dates],
           )
 
-          df = Operations.select(df, columns: options[:select]) if options[:select]
-          df = Operations.drop(df, columns: options[:drop]) if options[:drop]
+          df = apply_column_selection(df, options)
 
+          bar.finish(rows: df.height)
           df
         end
       rescue Interrupt
@@ -281,13 +1034,13 @@ module TabularTool
 
           IO.popen(cmd) do |io|
             df = Formats.read_from_io(io,
-              format: detect_format_without_compression(file),
+              format: input_format(file, options),
               delimiter: options[:delimiter],
               has_header: !options[:no_header],
+              parse_dates: options[:parse_dates],
             )
 
-            df = Operations.select(df, columns: options[:select]) if options[:select]
-            df = Operations.drop(df, columns: options[:drop]) if options[:drop]
+            df = apply_column_selection(df, options)
             df.head(limit)
           end
 
@@ -300,15 +1053,19 @@ module TabularTool
                   "#{decompressor} #{Shellwords.escape(file)} | tail -n #{limit}"
                 end
 
+          bar = progress_bar(options)
+          bar.start("Decompressing #{File.basename(file)}")
+
           IO.popen(cmd) do |io|
-            df = Formats.read_from_io(io,
-              format: detect_format_without_compression(file),
+            df = Formats.read_from_io(bar.wrap(io),
+              format: input_format(file, options),
               delimiter: options[:delimiter],
               has_header: has_header,
+              parse_dates: options[:parse_dates],
             )
+            bar.finish
 
-            df = Operations.select(df, columns: options[:select]) if options[:select]
-            df = Operations.drop(df, columns: options[:drop]) if options[:drop]
+            df = apply_column_selection(df, options)
             df.tail(limit)
           end
         end
@@ -320,7 +1077,7 @@ module TabularTool
 
       def build_parser(args)
         OptionParser.new do |opts|
-          opts.banner = "Usage: tt [COMMAND] [OPTIONS] <file>"
+          opts.banner = "Usage: tt [COMMAND] [OPTIONS] <file>  (use - to read from stdin)"
           opts.separator ""
           opts.separator "Supported Formats:"
           opts.separator "  CSV        .csv, .txt"
@@ -328,6 +1085,7 @@ module TabularTool
           opts.separator "  Parquet    .parquet, .pq"
           opts.separator "  JSON       .json"
           opts.separator "  JSONL      .jsonl, .ndjson"
+          opts.separator "  Arrow IPC  .arrow, .ipc, .feather"
           opts.separator ""
           opts.separator "Compressed Files:"
           opts.separator "  Zstandard  .zst"
@@ -337,18 +1095,34 @@ module TabularTool
           opts.separator "  cat              Pass-through (default)"
           opts.separator "  head [N]         Show first N rows (default: 10)"
           opts.separator "  tail [N]         Show last N rows (default: 10)"
-          opts.separator "  sample [N]       Random sample: N rows or 0.N ratio (e.g., 0.1 = 10% of rows)"
+          opts.separator "  peek             First and last rows with shape and dtypes (--rows N, default: 5)"
+          opts.separator "  sample [N]       Random sample: N rows or 0.N ratio (e.g., 0.1 = 10%); -k sorts the sample"
+          opts.separator "                   --offset then --limit narrow the rows sampled from, not the result"
+          opts.separator "  shuffle          Random reordering of all rows (same as cat --shuffle)"
           opts.separator "  less             Page through data interactively"
           opts.separator "  lint             Data quality checks"
           opts.separator "  stats            Statistics"
           opts.separator "  count            Row count only"
+          opts.separator "  nunique          Distinct values per column (column, n_unique)"
+          opts.separator "  clean            Apply fixes: --normalize-headers --trim --empty-to-null --dedupe"
+          opts.separator "  resample COL     Aggregate into time buckets: --every 1h [--agg \"count(),sum(bytes)\"] [--by COLS]"
+          opts.separator "  convert IN OUT   Convert formats via the streaming sink, then verify the row count (--from/--to)"
+          opts.separator "  version          Versions, available formats and shell tools (same as --version)"
           opts.separator ""
           opts.separator "Transformation Options:"
 
-          opts.on("-k", "--key COLUMN", "Sort by column (repeatable)") do |col|
+          opts.on("-k", "--key COLUMN[:desc]", "Sort by column (repeatable; :asc/:desc sets its direction)") do |col|
             args[:sort_keys] << col
           end
 
+          opts.on("--per-group COLUMNS", "head/sample: take N rows from each group (comma-separated keys)") do |cols|
+            args[:per_group] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--shuffle", "Randomly reorder all rows (with --seed for a reproducible order)") do
+            args[:shuffle] = true
+          end
+
           opts.on("-r", "--reverse", "Sort in descending order") do
             args[:reverse] = true
           end
@@ -357,70 +1131,490 @@ module TabularTool
             args[:ignore_case] = true
           end
 
+          opts.on("--nulls POSITION", [:first, :last], "Sort nulls first or last (default: first)") do |position|
+            args[:nulls_last] = position == :last
+          end
+
           # Filtering
           opts.on("--where EXPRESSION", "Filter rows by expression") do |expr|
             args[:where] = expr
           end
 
-          # Column selection
-          opts.on("--select COLUMNS", "--only COLUMNS", "Select specific columns (comma-separated)") do |cols|
-            args[:select] = cols.split(",").map(&:strip)
+          opts.on("--filter-ignore-case", "Compare String columns case-insensitively in --where (=, !=, contains, LIKE)") do
+            args[:filter_ignore_case] = true
           end
 
-          opts.on("--drop COLUMNS", "Drop specific columns (comma-separated)") do |cols|
-            args[:drop] = cols.split(",").map(&:strip)
+          opts.on("--expr-filter EXPRESSION",
+                  "Filter with column arithmetic, e.g. 'revenue > cost * 2' (see README for the grammar)") do |expr|
+            args[:expr_filter] = expr
           end
 
-          # Deduplication
-          opts.on("--unique", "Remove duplicate rows") do
-            args[:unique] = true
+          opts.on("--where-null COLUMNS", "Keep rows where the column is null (comma-separated; repeatable)") do |cols|
+            (args[:where_null] ||= []).concat(cols.split(",").map(&:strip))
           end
 
-          opts.on("--unique-on COLUMNS", "Remove duplicates based on columns") do |cols|
-            args[:unique_on] = cols.split(",").map(&:strip)
+          opts.on("--where-not-null COLUMNS", "Keep rows where the column is not null (comma-separated; repeatable)") do |cols|
+            (args[:where_not_null] ||= []).concat(cols.split(",").map(&:strip))
           end
 
-          opts.on("--check-unique COLUMNS", "Check columns for duplicate values (lint command only)") do |cols|
-            if cols == "*"
-              args[:check_unique_columns] = :all
-            else
-              args[:check_unique_columns] = cols.split(",").map(&:strip)
-            end
+          opts.on("--where-in COL:VALUES",
+                  "Keep rows whose value is in the list, e.g. 'city:NYC,LA' (quote values containing commas; repeatable)") do |spec|
+            col, list = spec.split(":", 2)
+            raise Error, "--where-in expects COL:VALUES, e.g. city:NYC,LA" if col.to_s.strip.empty? || list.nil?
+
+            (args[:where_in] ||= []) << [col.strip, Operations::Filter.split_values(list)]
           end
 
-          opts.separator ""
-          opts.separator "I/O Options:"
+          opts.on("--keep-keys-file FILE", "Keep rows whose --key-column value is in FILE (one per line, or one column)") do |path|
+            args[:keep_keys_file] = path
+          end
 
-          opts.on("-o", "--output FILE", "Output file (format detected by extension)") do |file|
-            args[:output] = file
+          opts.on("--drop-keys-file FILE", "Drop rows whose --key-column value is listed in FILE") do |path|
+            args[:drop_keys_file] = path
           end
 
-          opts.on("--in-place", "Modify input file") do
-            args[:in_place] = true
+          opts.on("--key-column COLUMN", "Column matched against --keep-keys-file/--drop-keys-file") do |col|
+            args[:key_column] = col
           end
 
-          opts.on("-d", "--delimiter CHAR", "Input delimiter (auto: comma for CSV, tab for TSV)") do |delim|
-            args[:delimiter] = delim
+          # Column selection
+          opts.on("--trim [COLUMNS]", "Strip surrounding whitespace in String columns (all, or comma-separated)") do |cols|
+            args[:trim] = cols ? cols.split(",").map(&:strip) : :all
           end
 
-          opts.on("--output-delimiter CHAR", "Output delimiter (default: same as input)") do |delim|
-            args[:output_delimiter] = delim
+          opts.on("--lower COLUMNS", "Lowercase values of String columns (comma-separated; changes the data)") do |cols|
+            args[:lower] = cols.split(",").map(&:strip)
           end
 
-          opts.on("--no-header", "Treat input as headerless (use column_1, column_2, etc.)") do
-            args[:no_header] = true
+          opts.on("--upper COLUMNS", "Uppercase values of String columns (comma-separated; changes the data)") do |cols|
+            args[:upper] = cols.split(",").map(&:strip)
           end
 
-          opts.on("--streaming", "Force streaming mode (for large files)") do
-            args[:streaming] = true
+          opts.on("--hash-columns COLUMNS", "Replace values with a hex digest (comma-separated; nulls stay null)") do |cols|
+            args[:hash_columns] = cols.split(",").map(&:strip)
           end
 
-          opts.on("--no-streaming", "Disable streaming mode") do
-            args[:streaming] = false
+          opts.on("--hash-algo ALGO", Operations::Anonymize::HASH_ALGORITHMS.keys,
+                  "Digest for --hash-columns: #{Operations::Anonymize::HASH_ALGORITHMS.keys.join('|')} (default: sha256)") do |algo|
+            args[:hash_algo] = algo
           end
 
-          opts.separator ""
-          opts.separator "Display Options:"
+          opts.on("--hash-salt SALT", "Prefix each value with SALT before hashing") do |salt|
+            args[:hash_salt] = salt
+          end
+
+          opts.on("--redact-columns COLUMNS", "Replace non-null values with #{Operations::Anonymize::MASK} (comma-separated)") do |cols|
+            args[:redact_columns] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--cumsum COL[:ALIAS]", "Append a running total of COL (repeatable; default name COL_cumsum)") do |spec|
+            (args[:cumsum] ||= []) << Operations::Window.parse_spec(spec, :cumsum)
+          end
+
+          opts.on("--diff COL[:ALIAS]", "Append COL minus the previous row's value (repeatable; default name COL_diff)") do |spec|
+            (args[:diff] ||= []) << Operations::Window.parse_spec(spec, :diff)
+          end
+
+          opts.on("--lag COL:N[:ALIAS]", "Append COL from N rows earlier (repeatable; default name COL_lagN)") do |spec|
+            (args[:lag] ||= []) << Operations::Window.parse_spec(spec, :lag)
+          end
+
+          opts.on("--over COLUMNS", "Restart --cumsum/--diff/--lag for each group (comma-separated)") do |cols|
+            args[:over] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--with SQL", "Append 'EXPR AS name' as a column (repeatable); window functions take " \
+                                "OVER (PARTITION BY ... ORDER BY ...)") do |spec|
+            (args[:with] ||= []) << Operations::Window.parse_with(spec)
+          end
+
+          opts.on("--with-row-index [NAME]", "Prepend a row index column (default name: #{DEFAULT_ROW_INDEX_NAME})") do |name|
+            args[:with_row_index] = name || DEFAULT_ROW_INDEX_NAME
+          end
+
+          opts.on("--row-index-offset N", Integer, "First value of --with-row-index (default: 0)") do |n|
+            args[:row_index_offset] = n
+          end
+
+          opts.on("--drop-nulls", "Remove rows with a null in any column") do
+            args[:drop_nulls] = true
+          end
+
+          opts.on("--drop-nulls-on COLUMNS", "Remove rows with a null in any of these columns (comma-separated)") do |cols|
+            args[:drop_nulls_on] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--select-dtypes TYPES", "Keep columns of these types: numeric,string,temporal,bool (comma-separated)") do |types|
+            args[:select_dtypes] = types.split(",").map { |type| type.strip.to_sym }
+          end
+
+          opts.on("--truncate-strings N", Integer, "Shorten String values to N characters, ending in …") do |n|
+            args[:truncate_strings] = n
+          end
+
+          opts.on("--no-ellipsis", "With --truncate-strings, cut values without appending …") do
+            args[:ellipsis] = false
+          end
+
+          opts.on("--max-cell-bytes N", Integer, "Check String cells against N bytes before output (see --max-cell-action)") do |n|
+            args[:max_cell_bytes] = n
+          end
+
+          opts.on("--max-cell-action ACTION", Operations::CELL_BYTE_ACTIONS,
+                  "What --max-cell-bytes does with a longer cell: error|truncate (default: error)") do |action|
+            args[:max_cell_action] = action
+          end
+
+          opts.on("--select COLUMNS", "--only COLUMNS", "Select specific columns (comma-separated; a..b is a range, !col excludes, new=old renames)") do |cols|
+            args[:select] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--limit N", Integer, "Keep at most N rows of the result (after shuffle/head; sample draws from the first N)") do |n|
+            raise Error, "--limit must be non-negative" if n < 0
+            args[:row_limit] = n
+          end
+
+          opts.on("--offset N", Integer, "Skip the first N rows of the result, before --limit (negative: keep the last -N)") do |n|
+            args[:offset] = n
+          end
+
+          opts.on("-n", "--n N", "--rows N", Integer, "Rows for head/tail/sample, or from each end for peek (overrides a positional N)") do |n|
+            raise Error, "--rows must be non-negative" if n < 0
+            args[:rows] = n
+          end
+
+          opts.on("--every INTERVAL", "Resample: bucket width, e.g. 30s, 15m, 1h, 1d, 1w") do |interval|
+            args[:every] = interval
+          end
+
+          opts.on("--agg AGGREGATIONS", "Resample: e.g. \"count(),sum(bytes),max(latency)\" (default: count())") do |spec|
+            args[:agg] = spec
+          end
+
+          opts.on("--by COLUMNS", "Resample: also group by these columns (comma-separated)") do |cols|
+            args[:resample_by] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--fill-gaps", "Resample: add rows for empty buckets (count 0, other aggregations null)") do
+            args[:fill_gaps] = true
+          end
+
+          opts.on("--sample-by COLUMNS", "Sample: stratify by these columns, N rows in total (comma-separated)") do |cols|
+            args[:sample_by] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--balanced", "With --sample-by, draw equally from each group instead of proportionally") do
+            args[:balanced] = true
+          end
+
+          opts.on("--sample-method METHOD", Operations::SAMPLE_METHODS,
+                  "Sample strategy: window|reservoir|full (default: reservoir)") do |method|
+            args[:sample_method] = method
+          end
+
+          opts.on("--seed N", Integer, "Random seed for shuffle and sample (reproducible results)") do |seed|
+            args[:seed] = seed
+          end
+
+          opts.on("--drop COLUMNS", "Drop specific columns (comma-separated)") do |cols|
+            args[:drop] = cols.split(",").map(&:strip)
+          end
+
+          # Deduplication
+          opts.on("--unique", "Remove duplicate rows") do
+            args[:unique] = true
+          end
+
+          opts.on("--unique-on COLUMNS", "Remove duplicates based on columns") do |cols|
+            args[:unique_on] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--[no-]stable", "Keep original row order when deduplicating (default)",
+                  "  --no-stable is faster on large inputs but output order is arbitrary") do |stable|
+            args[:stable] = stable
+          end
+
+          opts.on("--json", "Stats: print one JSON object per column instead of a table") do
+            args[:json] = true
+          end
+
+          opts.on("--fast", "Stats: only count/null_count/min/max, from Parquet footer statistics where present,",
+                  "  otherwise in a single lazy pass (stats command only)") do
+            args[:fast] = true
+          end
+
+          opts.on("--empty-to-null", "Clean: replace empty strings with null (clean command only)") do
+            args[:empty_to_null] = true
+          end
+
+          opts.on("--dedupe", "Clean: remove duplicate rows (clean command only)") do
+            args[:dedupe] = true
+          end
+
+          opts.on("--normalize-headers", "Clean: snake_case column names and make them unique (clean command only)") do
+            args[:normalize_headers] = true
+          end
+
+          opts.on("--approx", "nunique: approximate distinct counts (faster on huge columns)") do
+            args[:approx] = true
+          end
+
+          opts.on("--check-unique COLUMNS", "Check columns for duplicate values (lint command only)") do |cols|
+            if cols == "*"
+              args[:check_unique_columns] = :all
+            else
+              args[:check_unique_columns] = cols.split(",").map(&:strip)
+            end
+          end
+
+          opts.on("--high-null-threshold PERCENT", Float,
+                  "Report columns at least PERCENT% null (lint command only, default: 90)") do |percent|
+            raise Error, "--high-null-threshold must be between 0 and 100" unless (0..100).cover?(percent)
+            args[:high_null_threshold] = percent
+          end
+
+          opts.on("--strict", "Fail on anything questionable: any lint issue, or a --select/--drop/--select-dtypes " \
+                              "entry matching no column") do
+            args[:strict] = true
+          end
+
+          opts.on("--lenient", "Skip --select/--drop names missing from the input with a warning (mixed file batches)") do
+            args[:lenient] = true
+          end
+
+          opts.on("--check-encoding", "Lint: scan raw bytes for invalid UTF-8 before reading") do
+            args[:check_encoding] = true
+          end
+
+          opts.on("--show-nulls", "Lint: print the rows containing nulls (first 100 unless --all)") do
+            args[:show_nulls] = true
+          end
+
+          opts.on("--with-row-numbers", "Lint: prefix rows printed by --show-nulls/--show-duplicates with their file line") do
+            args[:with_row_numbers] = true
+          end
+
+          opts.on("--show-duplicates", "Lint: print the duplicated rows (first 100 unless --all)") do
+            args[:show_duplicates] = true
+          end
+
+          opts.on("--show-outliers", "Lint: flag numeric values outside 1.5×IQR of the quartiles") do
+            args[:show_outliers] = true
+          end
+
+          opts.on("--z N", Float, "Lint: with --show-outliers, flag values more than N standard deviations from the mean") do |z|
+            raise Error, "--z must be positive" unless z > 0
+            args[:outlier_z] = z
+            args[:show_outliers] = true
+          end
+
+          opts.on("--fail-on CHECKS", "Lint: exit with status 1 only for these checks " \
+                                      "(#{Operations::LintReport::CHECKS.values.uniq.join(',')})") do |checks|
+            args[:fail_on] = checks.split(",").map(&:strip)
+          end
+
+          opts.on("--report FORMAT", [:text, :json], "Lint report format: text|json (default: text)") do |format|
+            args[:report] = format
+          end
+
+          opts.on("--all", "Lint: scan every row when checking for mis-typed string columns (default: first 1000)") do
+            args[:all] = true
+          end
+
+          opts.separator ""
+          opts.separator "I/O Options:"
+
+          opts.on("-o", "--output FILE", "Output file (format detected by extension); a directory writes DIR/<input name>") do |file|
+            args[:output] = file
+          end
+
+          opts.on("--tee FILE", "Write the result to FILE and still print it (cat, head, tail, sample)") do |file|
+            args[:tee] = file
+          end
+
+          opts.on("--in-place", "Modify input file") do
+            args[:in_place] = true
+          end
+
+          opts.on("--partition-by COLUMNS", "Write -o DIR as a Hive-style tree, DIR/col=value/part.parquet " \
+                                            "(--output-format picks another format)") do |cols|
+            args[:partition_by] = cols.split(",").map(&:strip)
+          end
+
+          opts.on("--bom", "Start CSV/TSV -o output with a UTF-8 byte order mark, for Excel (not when appending)") do
+            args[:bom] = true
+          end
+
+          opts.on("--header-only", "Write the header/schema with zero rows (templates, append targets)") do
+            args[:header_only] = true
+          end
+
+          opts.on("--max-memory SIZE", "Estimate the input's in-memory size first: over SIZE (e.g. 4G), stream it, " \
+                                       "or refuse steps that need it all in memory") do |size|
+            args[:max_memory] = parse_byte_size(size)
+          end
+
+          opts.on("--limit-bytes SIZE", "Stop writing -o output before it exceeds SIZE (e.g. 10MB; whole rows only)") do |size|
+            args[:limit_bytes] = parse_byte_size(size)
+          end
+
+          opts.on("--null-string STRING", "Write nulls in CSV/TSV output as STRING, e.g. '\\N' for Postgres COPY " \
+                                          "(default: empty field)") do |string|
+            args[:null_string] = string
+          end
+
+          opts.on("--write-schema", "With CSV/TSV -o, also write OUTPUT.schema.json so dtypes survive re-reading") do
+            args[:write_schema] = true
+          end
+
+          opts.on("--append", "Append rows to the -o file (CSV/TSV/JSONL; header only if new)") do
+            args[:append] = true
+          end
+
+          opts.on("--from FORMAT", Formats::SUPPORTED_FORMATS, "convert: input format (same as --input-format)") do |format|
+            args[:input_format] = format
+          end
+
+          opts.on("--to FORMAT", Formats::SUPPORTED_FORMATS, "convert: output format (same as --output-format)") do |format|
+            args[:output_format] = format
+          end
+
+          opts.on("--output-template TEMPLATE", "convert: one output per input, e.g. out/{stem}.parquet " \
+                                                "({stem}, {name}, {dir})") do |template|
+            args[:output_template] = template
+          end
+
+          opts.on("--input-format FORMAT", Formats::SUPPORTED_FORMATS,
+                  "Input format, ignoring the file extension: csv|tsv|parquet|json|jsonl|ipc") do |format|
+            args[:input_format] = format
+          end
+
+          opts.on("--output-format FORMAT", Formats::SUPPORTED_FORMATS,
+                  "-o format, ignoring the file extension: csv|tsv|parquet|json|jsonl|ipc") do |format|
+            args[:output_format] = format
+          end
+
+          opts.on("--encoding ENCODING", Formats::INPUT_ENCODINGS.keys,
+                  "CSV/TSV input encoding: utf8 (default)|utf8-lossy|latin1|windows-1252") do |encoding|
+            args[:encoding] = encoding
+          end
+
+          opts.on("--input-schema SPEC",
+                  "CSV/TSV column types instead of inferring them, e.g. id:i64,name:str,ts:datetime") do |spec|
+            args[:input_schema] = Dtypes.parse_schema(spec)
+          end
+
+          opts.on("--strict-schema", "With --input-schema: fail unless every column is given a type") do
+            args[:strict_schema] = true
+          end
+
+          opts.on("--json-infer-rows N", "JSON lines scanned for columns (default 100; all: late keys still appear)") do |n|
+            raise Error, "--json-infer-rows must be a positive number or all" unless n == "all" || n.match?(/\A[1-9]\d*\z/)
+
+            args[:json_infer_rows] = n == "all" ? :all : n.to_i
+          end
+
+          opts.on("--on-duplicate-columns POLICY", Formats::DUPLICATE_COLUMN_POLICIES,
+                  "Repeated CSV/TSV header names: rename (amount, amount_2; default)|error|keep-first") do |policy|
+            args[:on_duplicate_columns] = policy
+          end
+
+          opts.on("--[no-]parse-dates", "Parse date-like CSV/TSV strings as dates (default: off, values stay strings)") do |parse|
+            args[:parse_dates] = parse
+          end
+
+          opts.on("-d", "--delimiter CHAR", "Input delimiter (auto: comma for CSV, tab for TSV)") do |delim|
+            args[:delimiter] = delim
+          end
+
+          opts.on("--sniff-delimiter", "Guess the input delimiter (, ; tab |) from the first few KB " \
+                                       "(automatic when the header reads as one column)") do
+            args[:sniff_delimiter] = true
+          end
+
+          opts.on("--output-delimiter CHAR", "Output delimiter (default: same as input)") do |delim|
+            args[:output_delimiter] = delim
+          end
+
+          opts.on("--no-header", "Treat input as headerless (use column_1, column_2, etc.)") do
+            args[:no_header] = true
+          end
+
+          opts.on("--streaming", "Force streaming mode (for large files)") do
+            args[:streaming] = true
+          end
+
+          opts.on("--no-streaming", "Disable streaming mode") do
+            args[:streaming] = false
+          end
+
+          opts.on("--external-sort", "Sort with -o through sorted runs spilled to disk (for inputs larger than memory)") do
+            args[:external_sort] = true
+          end
+
+          opts.on("--temp-dir DIR", "Where --external-sort and large shuffles spill (default: system temp dir)") do |dir|
+            raise Error, "--temp-dir #{dir} is not a directory" unless File.directory?(dir)
+
+            args[:temp_dir] = dir
+          end
+
+          opts.on("--chunk-size N", Integer, "Rows per streaming chunk (default: chosen by Polars)") do |n|
+            raise Error, "--chunk-size must be positive" unless n.positive?
+
+            args[:chunk_size] = n
+          end
+
+          opts.on("--explain", "Print the optimized query plan instead of running (cat, head, tail, count, stats)") do
+            args[:explain] = true
+          end
+
+          opts.on("--explain-streaming", "Report whether the command streams end to end, or which steps run in memory") do
+            args[:explain_streaming] = true
+          end
+
+          opts.on("--analyze", "With --explain, also run the query and report time and rows") do
+            args[:analyze] = true
+          end
+
+          opts.on("--if-empty ACTION", [:error, :warn, :ok], "When the result has no rows: error|warn|ok (default: ok)") do |action|
+            args[:if_empty] = action
+          end
+
+          opts.on("--watch", "Re-run the command whenever the input file changes (Ctrl+C to stop)") do
+            args[:watch] = true
+          end
+
+          opts.on("--watch-interval SECONDS", Float, "Polling and debounce interval for --watch (default: 0.5)") do |seconds|
+            raise Error, "--watch-interval must be positive" unless seconds > 0
+            args[:watch_interval] = seconds
+          end
+
+          opts.on("--time", "Print per-phase timings to stderr (phase<TAB>ms)") do
+            args[:time] = true
+          end
+
+          opts.on("--timings", "Like --time, plus rows in/out and peak memory (stderr)") do
+            args[:timings] = true
+          end
+
+          opts.on("--progress", "Show progress on stderr (only when stderr is a TTY)") do
+            args[:progress] = true
+          end
+
+          opts.on("--pipe-format FORMAT", [:csv, :arrow], "Stdout format when piping: csv|arrow (arrow preserves dtypes)") do |fmt|
+            args[:pipe_format] = fmt
+          end
+
+          opts.separator ""
+          opts.separator "Display Options:"
+
+          opts.on("-v", "--verbose", "Show more detail: files, row counts and timings on stderr; count lists dtypes") do
+            args[:verbose] = true
+          end
+
+          opts.on("-q", "--quiet", "Only print data and errors: no warnings, notes or --watch separators") do
+            args[:quiet] = true
+          end
 
           opts.on("--pretty", "Force pretty table output") do
             args[:pretty] = true
@@ -430,6 +1624,10 @@ module TabularTool
             args[:pretty] = false
           end
 
+          opts.on("--columns-as-rows", "Print each row as a field | value block (for wide records)") do
+            args[:columns_as_rows] = true
+          end
+
           opts.separator ""
           opts.separator "Parquet Options:"
 
@@ -437,15 +1635,25 @@ module TabularTool
             args[:compression] = codec
           end
 
+          opts.on("--row-group-size ROWS", Integer, "Rows per Parquet row group (default: Polars' choice)") do |rows|
+            raise Error, "--row-group-size must be positive" unless rows.positive?
+
+            args[:row_group_size] = rows
+          end
+
           opts.separator ""
 
+          opts.on("--config FILE", "Default options file (default: ~/.config/tt/config.toml)") do
+            # Handled by extract_config_path! before parsing; listed here for --help
+          end
+
           opts.on("-h", "--help", "Show help") do
             puts opts, ?\n
             exit
           end
 
-          opts.on("--version", "Show version") do
-            puts TabularTool::VERSION
+          opts.on("--version", "Show version, Polars version and available formats (same as `tt version`)") do
+            puts version_report
             exit
           end
 
@@ -456,58 +1664,337 @@ module TabularTool
         end
       end
 
+      def apply_cleaning(df, options)
+        df, summary = Operations::Clean.call(
+          df,
+          normalize_headers: options[:normalize_headers],
+          trim: options[:clean_trim],
+          empty_to_null: options[:empty_to_null],
+          dedupe: options[:dedupe],
+        )
+
+        labels = {
+          headers: "headers renamed",
+          trimmed: "cells trimmed",
+          nulled: "empty strings set to null",
+          deduped: "duplicate rows removed",
+        }
+        parts = summary.map { |key, count| "#{count} #{labels[key]}" }
+        Log.info "clean: #{parts.empty? ? 'no fixes selected' : parts.join(', ')}"
+        df
+      end
+
+      def apply_key_files(df, options)
+        return df unless options[:keep_keys_file] || options[:drop_keys_file]
+        raise Error, "--keep-keys-file and --drop-keys-file need --key-column" unless options[:key_column]
+
+        column = options[:key_column]
+        # Read and deduplicated once per invocation, however many frames are filtered
+        options[:keys] ||= {}
+        [[options[:keep_keys_file], true], [options[:drop_keys_file], false]].each do |path, keep|
+          next unless path
+
+          keys = options[:keys][path] ||= timer(options).measure("keys") { Operations::Filter.read_keys(path, column: column) }
+          Log.debug "keys: #{keys.length} distinct from #{path}"
+          df = Operations::Filter.by_keys(df, column: column, keys: keys, keep: keep)
+        end
+        df
+      end
+
       def apply_transformations(df, options)
-        df = Operations::Filter.call(df, expression: options[:where]) if options[:where]
+        input = df
+        input_columns = frame_schema(df, options).keys
+        # Trim first so filters compare against the cleaned values
+        df = Operations.trim(df, columns: options[:trim] == :all ? nil : options[:trim]) if options[:trim]
+        df = Operations.change_case(df, columns: options[:lower], to: :lower) if options[:lower]
+        df = Operations.change_case(df, columns: options[:upper], to: :upper) if options[:upper]
+        if options[:where]
+          df = Operations::Filter.call(df, expression: options[:where], ignore_case: options[:filter_ignore_case])
+        end
+        df = Operations::ExprFilter.call(df, expression: options[:expr_filter]) if options[:expr_filter]
+        if options[:where_null] || options[:where_not_null] || options[:where_in]
+          df = Operations::Filter.membership(df, null: options[:where_null], not_null: options[:where_not_null],
+                                                 in_values: options[:where_in])
+        end
+        df = apply_key_files(df, options)
 
-        df = Operations.select(df, columns: options[:select]) if options[:select]
-        df = Operations.drop(df, columns: options[:drop]) if options[:drop]
+        # After filtering, so filters still see the original values
+        if options[:hash_columns]
+          df = Operations::Anonymize.hash_values(df, columns: options[:hash_columns], algorithm: options[:hash_algo] || "sha256",
+                                                     salt: options[:hash_salt])
+        end
+        df = Operations::Anonymize.redact(df, columns: options[:redact_columns]) if options[:redact_columns]
+        if options[:truncate_strings]
+          df = Operations.truncate_strings(df, max_chars: options[:truncate_strings], ellipsis: options[:ellipsis] != false)
+        end
+
+        df = apply_column_selection(df, options)
+        # After selection, so plain --drop-nulls only looks at the kept columns
+        df = Operations.drop_nulls(df) if options[:drop_nulls]
+        df = Operations.drop_nulls(df, columns: options[:drop_nulls_on]) if options[:drop_nulls_on]
 
         # Unique (requires DataFrame, not LazyFrame)
         if options[:unique] || options[:unique_on]
           df = collect_if_lazy(df)
 
+          maintain_order = options[:stable] != false
+
           if options[:unique]
-            df = Operations.unique(df)
+            df = Operations.unique(df, maintain_order: maintain_order)
           elsif options[:unique_on]
-            df = Operations.unique(df, columns: options[:unique_on])
+            df = Operations.unique(df, columns: options[:unique_on], maintain_order: maintain_order)
           end
         end
 
-        if options[:sort_keys] && options[:sort_keys].any?
-          df = Operations::Sort.call(
-            df,
-            keys: options[:sort_keys],
-            reverse: options[:reverse],
-            ignore_case: options[:ignore_case],
-          )
+        # Large -o outputs are shuffled in output_dataframe instead
+        df = Operations.shuffle(df, seed: options[:seed]) if options[:shuffle] && !options[:external_shuffle]
+
+        # --external-sort sorts in output_dataframe instead
+        if options[:sort_keys] && options[:sort_keys].any? && !options[:external_sort_runs]
+          df = sort_rows(df, options[:sort_keys], options)
+        end
+
+        # After sorting, so -k ts --cumsum amount is a running total in time order
+        if options[:cumsum] || options[:diff] || options[:lag]
+          df = Operations::Window.call(df, cumsum: options[:cumsum] || [], diff: options[:diff] || [],
+                                           lag: options[:lag] || [], over: options[:over])
+        end
+        df = Operations::Window.with_columns(df, options[:with]) if options[:with]
+        # Fixed-position consumers rely on the input order surviving; an explicit --select sets its own
+        # An untouched frame is already in order, and asking again would resolve a lazy scan's schema twice
+        df = Operations.restore_column_order(df, input_columns) unless options[:select] || df.equal?(input)
+
+        # Last, so the index follows the filtered and sorted order; head/tail/--limit then
+        # keep each row's logical position rather than renumbering
+        if options[:with_row_index]
+          df = Operations.with_row_index(df, name: options[:with_row_index], offset: options[:row_index_offset] || 0)
+        end
+
+        df
+      end
+
+      # --external-sort streams the merged runs into -o, so it needs a file to read in runs and one to write
+      def check_external_sort(file, command, options)
+        raise Error, "--external-sort needs -k/--key" unless options[:sort_keys]&.any?
+        raise Error, "--external-sort needs -o/--output" unless options[:output]
+        raise Error, "--external-sort needs an input file, not stdin" if file.nil? || file == STDIN_PATH
+        raise Error, "--external-sort only applies to cat and convert" unless [:cat, :convert].include?(command)
+        return unless (key = POST_SORT_OPTIONS.find { |option| options[option] })
+
+        raise Error, "--external-sort cannot be combined with --#{key.to_s.tr('_', '-')}, which needs the sorted rows"
+      end
+
+      def external_sort_applies?(command, options)
+        options[:sort_keys]&.any? && options[:output] && [:cat, :convert].include?(command) &&
+          POST_SORT_OPTIONS.none? { |option| options[option] }
+      end
+
+      def sort_rows(df, keys, options)
+        Operations::Sort.call(
+          df,
+          keys: keys,
+          reverse: options[:reverse],
+          ignore_case: options[:ignore_case],
+          nulls_last: options[:nulls_last],
+        )
+      end
+
+      # --select-dtypes runs after --select, so the two intersect
+      def selection_mode(options)
+        raise Error, "--strict and --lenient cannot be combined" if options[:strict] && options[:lenient]
+
+        if options[:strict]
+          :strict
+        elsif options[:lenient]
+          :lenient
         end
+      end
 
+      def apply_column_selection(df, options)
+        check_ambiguous_columns(options)
+        mode = selection_mode(options)
+        df = Operations.select(df, columns: options[:select], mode: mode) if options[:select]
+        df = Operations.select_dtypes(df, groups: options[:select_dtypes], mode: mode) if options[:select_dtypes]
+        df = Operations.drop(df, columns: options[:drop], mode: mode) if options[:drop]
         df
       end
 
+      # After renaming, "amount" still names the first copy; asking for it by name is almost certainly a mistake
+      def check_ambiguous_columns(options)
+        duplicates = options[:duplicate_columns]
+        return unless duplicates
+
+        { "--select" => options[:select], "--drop" => options[:drop] }.each do |option, tokens|
+          Array(tokens).each do |token|
+            name = token.delete_prefix("!").split("=", 2).last.strip
+            next unless (candidates = duplicates[name])
+
+            raise Error, "#{option} #{name} is ambiguous: the header repeats it. " \
+                         "Choose one of #{candidates.join(', ')} (or --on-duplicate-columns keep-first)"
+          end
+        end
+      end
+
       def output_dataframe(df, file, options, default_pretty: false)
+        if (buckets = options.delete(:external_shuffle))
+          return timer(options).measure("shuffle") do
+            Operations.external_shuffle(df, buckets: buckets, seed: options[:seed],
+                                            temp_dir: options[:temp_dir]) do |shuffled|
+              output_dataframe(shuffled, file, options, default_pretty: default_pretty)
+            end
+          end
+        end
+        if (runs = options.delete(:external_sort_runs))
+          return timer(options).measure("sort") do
+            Operations::Sort.external(df, runs: runs, keys: options[:sort_keys], reverse: options[:reverse],
+                                          ignore_case: options[:ignore_case], nulls_last: options[:nulls_last],
+                                          temp_dir: options[:temp_dir]) do |sorted|
+              output_dataframe(sorted, file, options, default_pretty: default_pretty)
+            end
+          end
+        end
+
+        # --limit applies to the final result, so shuffle --limit N is a uniform random N rows
+        df = offset_and_limit(df, options)
+        # Only the schema is needed, so collecting zero rows is cheap and gives every writer a header
+        return output_empty(df, file, options, default_pretty: default_pretty) if options[:header_only]
+
+        if options[:max_cell_bytes]
+          df = Operations.limit_cell_bytes(df, max_bytes: options[:max_cell_bytes], action: options[:max_cell_action] || :error)
+        end
+        check_if_empty(df, options)
         output_file = options[:in_place] ? file : options[:output]
 
         if output_file
+          bar = progress_bar(options)
+          bar.start("Writing #{File.basename(output_file)}")
           # Formats.write now handles LazyFrames efficiently using sink methods
+          timer(options).measure("write") { write_output(df, output_file, options) }
+          if (bar.enabled? || options[:timings]) && !options[:append] && !options[:limit_bytes]
+            rows = rows_written(df, output_file, options)
+            timer(options).rows_out = rows
+            bar.finish(rows: rows)
+          end
+          nil
+        else
+          # For stdout output, we need to collect the LazyFrame
+          df = timer(options).measure("collect") { collect_if_lazy(df) }
+          timer(options).rows_out = df.height
+          # Written from the collected rows, so the file holds exactly what's printed; its format
+          # comes from the extension, since --output-format describes -o
+          if options[:tee]
+            timer(options).measure("write") { write_output(df, options[:tee], options.except(:output_format)) }
+          end
+          should_pretty = options[:pretty].nil? ? default_pretty && $stdout.tty? : options[:pretty]
+
+          timer(options).measure("format") do
+            if options[:pipe_format] == :arrow
+              # Binary Arrow IPC stream for the next tt in the pipeline; never pretty-printed
+              Formats.write_to_stdout(df, format: :arrow)
+            elsif options[:columns_as_rows]
+              format_columns_as_rows(df)
+            elsif should_pretty
+              df.to_s
+            else
+              # CSV is most universal format for stdout (works for all input types including Parquet)
+              Formats.write_to_stdout(df, format: :csv, delimiter: options[:output_delimiter],
+                                          null_value: options[:null_string])
+            end
+          end
+        end
+      end
+
+      # -o naming a directory (existing, or ending in /) writes <dir>/<input name>
+      # Compression suffixes are dropped because outputs are written uncompressed
+      def resolve_output_path(file, output)
+        return output unless output.end_with?("/") || File.directory?(output)
+        raise Error, "-o directory requires a named input file, not stdin" if file.nil? || file == STDIN_PATH
+
+        FileUtils.mkdir_p(output)
+        File.join(output, File.basename(file).sub(/\.(gz|zst)$/i, ""))
+      end
+
+      # --if-empty error|warn|ok: what to do when the result has zero rows (checked before anything is written)
+      def output_empty(df, file, options, default_pretty:)
+        empty = collect_if_lazy(df.head(0))
+        output_dataframe(empty, file, options.merge(header_only: false, if_empty: :ok), default_pretty: default_pretty)
+      end
+
+      # --offset first, then --limit on what's left. Out-of-range values clamp rather than wrap or raise:
+      # a negative offset past the start keeps every row, and counts beyond MAX_ROWS mean "all"
+      def offset_and_limit(df, options)
+        offset = options[:offset]
+        if offset&.negative?
+          df = df.tail([-offset, MAX_ROWS].min)
+        elsif offset&.positive?
+          df = df.slice([offset, MAX_ROWS].min)
+        end
+        limit = options[:row_limit]
+        limit && limit < MAX_ROWS ? df.head(limit) : df
+      end
+
+      def check_if_empty(df, options)
+        mode = options[:if_empty] || :ok
+        return if mode == :ok
+
+        # A one-row probe is enough for lazy frames, no full count needed
+        empty = df.is_a?(Polars::LazyFrame) ? df.limit(1).collect.height == 0 : df.height == 0
+        return unless empty
+
+        raise Error, "Result has no rows (--if-empty error)" if mode == :error
+        Log.warn "result has no rows"
+      end
+
+      def write_output(df, output_file, options)
+        if options[:partition_by]
+          Operations.require_columns(df, options[:partition_by], option: "--partition-by")
+          paths = Formats.write_partitioned(
+            df,
+            output_file,
+            by: options[:partition_by],
+            format: options[:output_format] || :parquet,
+            delimiter: options[:output_delimiter],
+            compression: options[:compression],
+            row_group_size: options[:row_group_size],
+            bom: options[:bom],
+            null_value: options[:null_string],
+          )
+          Log.debug "write: #{paths.length} partitions under #{output_file}"
+          return
+        end
+
+        if options[:limit_bytes]
+          rows, truncated = Formats.write_limited(
+            df,
+            output_file,
+            max_bytes: options[:limit_bytes],
+            format: options[:output_format],
+            delimiter: options[:output_delimiter],
+            bom: options[:bom],
+            null_value: options[:null_string],
+          )
+          Log.warn "output capped at #{options[:limit_bytes]} bytes: wrote #{rows} rows" if truncated
+        elsif options[:append]
+          Formats.append(df, output_file, format: options[:output_format], delimiter: options[:output_delimiter],
+                                          bom: options[:bom], null_value: options[:null_string])
+        else
           Formats.write(
             df,
             output_file,
+            format: options[:output_format],
             delimiter: options[:output_delimiter],
             compression: options[:compression],
+            row_group_size: options[:row_group_size],
+            bom: options[:bom],
+            null_value: options[:null_string],
           )
-          nil
+        end
+        Formats.write_schema(df, output_file) if options[:write_schema]
+        format = output_format(output_file, options)
+        if format == :parquet && options[:row_group_size]
+          Log.debug "write: #{output_file} (parquet, #{ParquetFooter.row_groups(output_file)} row groups)"
         else
-          # For stdout output, we need to collect the LazyFrame
-          df = collect_if_lazy(df)
-          should_pretty = options[:pretty].nil? ? default_pretty && $stdout.tty? : options[:pretty]
-
-          if should_pretty
-            df.to_s
-          else
-            # CSV is most universal format for stdout (works for all input types including Parquet)
-            Formats.write_to_stdout(df, format: :csv, delimiter: options[:output_delimiter])
-          end
+          Log.debug "write: #{output_file} (#{format})"
         end
       end
 
@@ -531,92 +2018,129 @@ module TabularTool
       rescue Errno::EPIPE
         nil
       rescue Errno::ENOENT
-        warn "Warning: 'less' command not found. Install less for pagination."
+        Log.warn "'less' command not found. Install less for pagination."
         puts content
       ensure
         Polars::Config.set_tbl_rows(original_rows) if original_rows
       end
 
-      def execute_lint(df, options)
+      def execute_lint(df, file, options)
         unique_cols = options[:check_unique_columns]
 
-        result = Operations::Lint.call(
-          df,
-          unique_columns: unique_cols,
-        )
-
-        output = []
-        output << "* Row count: #{result[:row_count]}"
-        output << "* Column count: #{result[:column_count]}"
-        output << ""
+        # The raw header shows names Polars would rename; only meaningful when columns weren't selected
+        header = nil
+        if file && file != STDIN_PATH && !options[:no_header] && !options[:select] && !options[:select_dtypes] &&
+           !options[:drop]
+          header = Formats.read_header(file, delimiter: options[:delimiter])
+        end
 
-        if result[:passed] && result[:warnings].empty?
-          output << "✓ All checks passed:"
-          output << "  - No blank values"
-          output << "  - No duplicate rows"
-          output << "  - No whitespace issues"
-          if unique_cols
-            cols_desc = unique_cols == :all ? "all columns" : unique_cols.join(", ")
-            output << "  - No duplicate values in #{cols_desc}"
-          end
-        else
-          output << "Issues found:"
-          output << ""
+        result = timer(options).measure("lint") do
+          Operations::Lint.call(
+            df,
+            unique_columns: unique_cols,
+            high_null_threshold: options[:high_null_threshold] || Operations::Lint::DEFAULT_HIGH_NULL_THRESHOLD,
+            type_sample_rows: options[:all] ? nil : Operations::Lint::TYPE_SAMPLE_ROWS,
+            header: header,
+            encoding: options[:encoding_result],
+            outliers: options[:show_outliers],
+            z: options[:outlier_z],
+          )
+        end
 
-          result[:errors].each do |error|
-            output << format_lint_issue("✗", error, df)
-            output << ""
-          end
+        report = Operations::LintReport.new(result, unique_columns: unique_cols, encoding_checked: !options[:encoding_result].nil?,
+                                                    outliers_checked: options[:show_outliers])
+        # -o report.json (any case) or --output-format json implies --report json
+        written = output_format(options[:output], options) if options[:output] || options[:output_format]
+        format = options[:report] || (written == :json ? :json : :text)
+        output = format == :json ? report.to_json + "\n" : report.to_text(df)
+        if format != :json
+          output += null_rows_text(df, report, options) if options[:show_nulls]
+          output += duplicate_groups_text(df, report, options) if options[:show_duplicates]
+        end
+        fatal = options[:strict] || options[:fail_on] ? report.fatal_findings(options[:fail_on]) : []
 
-          result[:warnings].each do |warning|
-            output << format_lint_issue("⚠", warning, df)
-            output << ""
-          end
+        if options[:output]
+          File.write(options[:output], output)
+          output = nil
         end
 
-        output.join("\n") + "\n"
+        raise CheckFailed.new(output, "Lint found #{fatal.length} fatal issue(s)") if fatal.any?
+        output
       end
 
-      def execute_stats(df, options)
-        result = Operations::Stats.call(df, columns: options[:select])
-        result[:stats].to_s
+      # --select was already applied by apply_transformations
+      def execute_nunique(df, file, options)
+        counts = timer(options).measure("aggregate") { Operations::Stats.nunique(df, approx: options[:approx]) }
+        result = Polars::DataFrame.new({
+          "column" => counts.map(&:first),
+          "n_unique" => counts.map(&:last),
+        })
+        output_dataframe(result, file, options, default_pretty: true)
       end
 
-      def format_lint_issue(symbol, issue, df)
-        lines = issue[:lines] || []
+      # Row printing options shared by --show-nulls and --show-duplicates
+      # Line numbers are 1-based file lines, so the header shifts data rows down by one
+      def shown_rows_options(options)
+        {
+          limit: options[:all] ? nil : 100,
+          row_numbers: options[:with_row_numbers],
+          line_offset: options[:no_header] ? 1 : 2,
+        }
+      end
 
-        message = case issue[:type]
-        when :blank_values
-          "#{symbol} Column '#{issue[:column]}': #{issue[:count]} blank/null values\n  Lines: #{lines.join(', ')}"
-        when :duplicate_rows
-          "#{symbol} #{issue[:count]} duplicate rows found\n  Lines: #{lines.join(', ')}"
-        when :duplicate_column_values
-          values_str = issue[:values].map { |v| v.nil? ? "(null)" : v.inspect }.join(", ")
-          "#{symbol} Column '#{issue[:column]}': #{issue[:count]} duplicate values\n  Values: #{values_str}\n  Lines: #{lines.join(', ')}"
-        when :whitespace
-          "#{symbol} Column '#{issue[:column]}': trailing/leading whitespace in #{issue[:count]} values\n  Lines: #{lines.join(', ')}"
-        else
-          "#{symbol} #{issue[:type]}: #{issue[:message]}"
-        end
-
-        # Add row data for issues with line numbers (limit to first 5 to avoid overwhelming output)
-        if lines.any?
-          display_lines = lines.take(5)
-          message += "\n"
-          display_lines.each do |line_num|
-            row_index = line_num - 2  # Convert file line number to 0-based row index (accounting for header)
-            if row_index >= 0 && row_index < df.height
-              row = df.slice(row_index, 1)
-              row_data = df.columns.map { |col| "#{col}=#{row[col][0].inspect}" }.join(", ")
-              message += "\n  Row #{line_num}: #{row_data}"
-            end
+      def null_rows_text(df, report, options)
+        return "" if report.findings.none? { |finding| finding.type == :blank_values }
+
+        rows = Operations::Lint.null_rows(df, **shown_rows_options(options))
+        "#{shown_rows_heading('Rows with nulls', rows, options)}\n#{rows.write_csv}\n"
+      end
+
+      def shown_rows_heading(label, rows, options)
+        more = !options[:all] && rows.height == 100 ? ", use --all for more" : ""
+        "#{label} (#{rows.height} rows#{more}):"
+      end
+
+      # Rows behind each duplicate finding, as CSV
+      def duplicate_groups_text(df, report, options)
+        report.findings.filter_map do |finding|
+          keys = case finding.type
+          when :duplicate_rows then df.columns
+          when :duplicate_column_values then [finding.column]
           end
-          if lines.length > 5
-            message += "\n  ... and #{lines.length - 5} more rows"
+          next unless keys
+
+          groups = Operations::Lint.duplicate_groups(df, keys: keys, **shown_rows_options(options))
+          label = finding.type == :duplicate_rows ? "all columns" : finding.column
+          "#{shown_rows_heading("Duplicates on #{label}", groups, options)}\n#{groups.write_csv}\n"
+        end.join
+      end
+
+      # A row per file and column with a leading file column, written (or printed) once at the end
+      def execute_stats_files(inputs, options)
+        output = options.delete(:output)
+        tables = inputs.map { |input| execute(command: :stats, file: input, **options, stats_table: true) }
+        combined = Polars.concat(tables, how: "diagonal_relaxed")
+        output_dataframe(combined, inputs.first, options.merge(output: output), default_pretty: true)
+      end
+
+      def execute_stats(df, file, options)
+        # --select was already applied by apply_transformations
+        result = timer(options).measure("aggregate") do
+          if options[:fast]
+            Operations::Stats.fast(df, schema: frame_schema(df, options), path: footer_stats_path(file, options))
+          else
+            Operations::Stats.call(df)
           end
         end
+        return Operations::Stats.table(result[:stats], file: file) if options[:stats_table]
 
-        message
+        timer(options).measure("format") do
+          next JSON.pretty_generate(Operations::Stats.records(result[:stats])) if options[:json]
+
+          output = result[:stats].to_s
+          output += "\n(--fast: mean/std/median not computed)" if options[:fast]
+          output
+        end
       end
     end
   end
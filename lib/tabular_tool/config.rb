@@ -0,0 +1,87 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Default CLI options from a TOML file (~/.config/tt/config.toml or --config PATH)
+  #
+  # Keys are long option names without the leading dashes:
+  #
+  #   max-col-width = 40
+  #   compression = "zstd"
+  #   pretty = false       # same as --no-pretty
+  #
+  # Only flat `key = value` pairs of strings, numbers and booleans are supported
+  module Config
+    def self.default_path
+      base = ENV["XDG_CONFIG_HOME"]
+      base = File.join(Dir.home, ".config") if base.nil? || base.empty?
+      File.join(base, "tt", "config.toml")
+    rescue ArgumentError
+      # Dir.home raises when HOME is unset
+      nil
+    end
+
+    def self.load(path)
+      parse(File.read(path), path)
+    rescue SystemCallError => e
+      raise Error, "Cannot read config #{path}: #{e.message}"
+    end
+
+    def self.parse(text, path = "config")
+      values = {}
+
+      text.each_line.with_index(1) do |line, lineno|
+        line = strip_comment(line).strip
+        next if line.empty?
+
+        key, value = line.split("=", 2).map(&:strip)
+        if value.nil? || key.empty? || !key.match?(/\A[A-Za-z0-9_-]+\z/)
+          raise Error, "Invalid config #{path}:#{lineno}: expected `key = value`"
+        end
+
+        values[key.tr("_", "-")] = parse_value(value, path, lineno)
+      end
+
+      values
+    end
+
+    # Turns config values into the equivalent command-line flags
+    def self.to_argv(values)
+      values.flat_map do |key, value|
+        case value
+        when true  then ["--#{key}"]
+        when false then ["--no-#{key}"]
+        else ["--#{key}", value.to_s]
+        end
+      end
+    end
+
+    def self.parse_value(value, path, lineno)
+      case value
+      when "true" then true
+      when "false" then false
+      when /\A"(.*)"\z/ then $1.gsub(/\\(["\\])/, '\1')
+      when /\A'(.*)'\z/ then $1
+      when /\A[+-]?\d+\z/ then value.to_i
+      when /\A[+-]?\d+\.\d+\z/ then value.to_f
+      else raise Error, "Invalid config #{path}:#{lineno}: unsupported value #{value}"
+      end
+    end
+    private_class_method :parse_value
+
+    # Drops a trailing # comment, ignoring # inside quoted strings
+    def self.strip_comment(line)
+      quote = nil
+      line.each_char.with_index do |char, i|
+        if quote
+          quote = nil if char == quote
+        elsif char == '"' || char == "'"
+          quote = char
+        elsif char == "#"
+          return line[0...i]
+        end
+      end
+      line
+    end
+    private_class_method :strip_comment
+  end
+end
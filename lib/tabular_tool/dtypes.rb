@@ -0,0 +1,73 @@
+# frozen_string_literal: true
+
+require "polars"
+
+module TabularTool
+  # Helpers for Polars dtypes, which may be classes (Polars::Int64) or instances (Polars::Datetime.new("us"))
+  module Dtypes
+    # Type classes accepted by --select-dtypes
+    GROUPS = {
+      numeric: [Polars::IntegerType, Polars::FloatType, Polars::Decimal],
+      string: [Polars::String, Polars::Categorical],
+      temporal: [Polars::Date, Polars::Datetime, Polars::Time, Polars::Duration],
+      bool: [Polars::Boolean],
+    }.freeze
+
+    # Short names accepted by --input-schema, besides the full names parse reads
+    ALIASES = {
+      "i8" => Polars::Int8, "i16" => Polars::Int16, "i32" => Polars::Int32, "i64" => Polars::Int64,
+      "u8" => Polars::UInt8, "u16" => Polars::UInt16, "u32" => Polars::UInt32, "u64" => Polars::UInt64,
+      "f32" => Polars::Float32, "f64" => Polars::Float64,
+      "str" => Polars::String, "string" => Polars::String, "bool" => Polars::Boolean,
+      "date" => Polars::Date, "datetime" => Polars::Datetime.new("us"), "time" => Polars::Time,
+    }.freeze
+
+    def self.kind?(dtype, klass)
+      dtype.is_a?(Class) ? dtype <= klass : dtype.is_a?(klass)
+    end
+
+    def self.in_group?(dtype, group)
+      GROUPS.fetch(group).any? { |klass| kind?(dtype, klass) }
+    end
+
+    def self.name(dtype)
+      dtype.to_s.delete_prefix("Polars::")
+    end
+
+    # "id:i64,name:str,ts:Datetime(time_unit: \"ms\")" as { "id" => Polars::Int64, ... }, in the given order
+    def self.parse_schema(spec)
+      # Commas inside a type's parentheses don't separate columns
+      spec.scan(/(?:[^,(]|\([^)]*\))+/).to_h do |entry|
+        name, type = entry.strip.match(/\A(.+?)\s*:\s*(\w+(?:\(.*\))?)\z/m)&.captures
+        raise Error, "Invalid --input-schema entry #{entry.strip.inspect}: expected NAME:TYPE" unless name
+
+        dtype = ALIASES[type.downcase] || parse(type)
+        raise Error, "Unknown type #{type} for #{name} in --input-schema (e.g. i64, f64, str, date, datetime)" unless dtype
+
+        [name, dtype]
+      end
+    end
+
+    # Inverse of name for the types a CSV cell can hold; nil for nested or unknown types
+    def self.parse(name)
+      type_name, params = name.match(/\A(\w+)(?:\((.*)\))?\z/m)&.captures
+      return unless type_name && Polars.const_defined?(type_name, false)
+
+      klass = Polars.const_get(type_name, false)
+      return unless klass.is_a?(Class) && klass <= Polars::DataType
+
+      time_unit = params.to_s[/time_unit: "?(\w+)"?/, 1]
+      if klass <= Polars::Datetime
+        klass.new(time_unit || "us", params.to_s[/time_zone: "([^"]+)"/, 1])
+      elsif klass <= Polars::Duration
+        klass.new(time_unit || "us")
+      elsif klass <= Polars::Decimal
+        klass.new(params.to_s[/precision: (\d+)/, 1]&.to_i, params.to_s[/scale: (\d+)/, 1].to_i)
+      elsif [Polars::List, Polars::Array, Polars::Struct, Polars::Enum].any? { |nested| klass <= nested }
+        nil
+      else
+        klass
+      end
+    end
+  end
+end
@@ -1,12 +1,66 @@
 # frozen_string_literal: true
 
+require "fileutils"
+require "json"
 require "polars"
 
 module TabularTool
   module Formats
+    # Formats read and written by this build, in --input-format/--output-format order
+    SUPPORTED_FORMATS = [:csv, :tsv, :parquet, :json, :jsonl, :ipc].freeze
+
+    # Input/output format by file extension; anything else is read as CSV
+    FORMAT_EXTENSIONS = {
+      ".csv" => :csv, ".txt" => :csv, ".tsv" => :tsv, ".parquet" => :parquet, ".pq" => :parquet,
+      ".json" => :json, ".jsonl" => :jsonl, ".ndjson" => :jsonl, ".arrow" => :ipc, ".ipc" => :ipc,
+      ".feather" => :ipc, ".orc" => :orc,
+    }.freeze
+
     # Default streaming threshold: 500 MiB
     STREAMING_THRESHOLD = 500 * 1024 * 1024
 
+    # Rows serialized at a time by write_limited
+    LIMITED_WRITE_BATCH_ROWS = 10_000
+
+    # Arrow IPC stream messages begin with the 0xFFFFFFFF continuation marker
+    ARROW_STREAM_MAGIC = "\xFF\xFF\xFF\xFF".b
+
+    # Sidecar written by --write-schema next to CSV/TSV output, e.g. out.csv.schema.json
+    SCHEMA_SUFFIX = ".schema.json"
+
+    # Written ahead of CSV/TSV output by --bom, so Excel detects UTF-8
+    UTF8_BOM = "\xEF\xBB\xBF".b.freeze
+
+    # Directory name used by Hive (and readers that follow it) for a null partition value
+    HIVE_NULL_PARTITION = "__HIVE_DEFAULT_PARTITION__"
+
+    # File extension of each partition file, by output format
+    PARTITION_EXTENSIONS = { csv: "csv", tsv: "tsv", parquet: "parquet", json: "json", jsonl: "jsonl", ipc: "arrow" }.freeze
+
+    # --encoding values; single-byte encodings are transcoded to UTF-8 ahead of Polars, which only reads UTF-8
+    INPUT_ENCODINGS = {
+      "utf8" => nil,
+      "utf8-lossy" => "utf8-lossy",
+      "latin1" => Encoding::ISO_8859_1,
+      "windows-1252" => Encoding::Windows_1252,
+    }.freeze
+
+    # Bytes transcoded at a time; single-byte encodings can be split anywhere
+    TRANSCODE_CHUNK_BYTES = 1024 * 1024
+
+    # Rough in-memory bytes per file byte, by input format, for --max-memory's estimate
+    # Parquet is compressed and dictionary-encoded, so it grows the most once decoded
+    MEMORY_FACTORS = { csv: 2, tsv: 2, json: 3, jsonl: 2, parquet: 5, ipc: 1 }.freeze
+
+    # Delimiters sniff_delimiter chooses between, in tie-break order
+    SNIFF_DELIMITERS = [",", ";", "\t", "|"].freeze
+
+    # Bytes read from the start of a file to guess its delimiter
+    SNIFF_BYTES = 8 * 1024
+
+    # --on-duplicate-columns values, for repeated names in a CSV/TSV header
+    DUPLICATE_COLUMN_POLICIES = { "rename" => :rename, "error" => :error, "keep-first" => :keep_first }.freeze
+
     class << self
       def should_stream?(path, threshold: STREAMING_THRESHOLD)
         size = File.size(path)
@@ -22,72 +76,235 @@ module TabularTool
         false
       end
 
+      # A pre-flight guess at the file's decoded size; .gz/.zst are assumed to expand ~10x, as in should_stream?
+      def memory_estimate(path, format: nil)
+        format ||= detect_format(path)
+        size = File.size(path) * MEMORY_FACTORS.fetch(format, 2)
+        compressed_file?(path) ? size * 10 : size
+      end
+
+      def arrow_stream?(bytes)
+        bytes.b.start_with?(ARROW_STREAM_MAGIC)
+      end
+
+      # Only the last extension before an optional .gz/.zst counts, so dotted names
+      # like archive.2024.csv.gz work
       def detect_format(path)
-        base_path = path.sub(/\.(gz|zst)$/, "")
-        ext = File.extname(base_path).downcase
-
-        case ext
-        when ".csv", ".txt"
-          :csv
-        when ".tsv"
-          :tsv
-        when ".parquet", ".pq"
-          :parquet
-        when ".json"
-          :json
-        when ".jsonl", ".ndjson"
-          :jsonl
-        else
-          :csv
-        end
+        FORMAT_EXTENSIONS.fetch(format_extension(path), :csv)
+      end
+
+      # False when detect_format would only be falling back to CSV
+      def recognized_format?(path)
+        FORMAT_EXTENSIONS.key?(format_extension(path))
+      end
+
+      def format_extension(path)
+        File.extname(path.sub(/\.(gz|zst)$/i, "")).downcase
       end
 
-      def read(path, format: nil, delimiter: nil, has_header: true, streaming: nil, **options)
+      # parse_dates only applies to CSV/TSV; nil leaves Polars' default (no date parsing)
+      # schema (name => dtype, from --input-schema) forces the listed CSV/TSV column types
+      # infer_rows is how many JSON lines to read for the schema (:all for every line; nil keeps Polars' 100)
+      # progress (a Progress) tracks eager reads: bytes for text formats, row groups for Parquet
+      # temp_files collects the temp files the result reads from, for the caller to close! once done with it
+      def read(path, format: nil, delimiter: nil, has_header: true, streaming: nil, parse_dates: nil, schema: nil,
+               strict_schema: false, infer_rows: nil, progress: nil, temp_files: nil, **options)
         format ||= detect_format(path)
+        if schema && ![:csv, :tsv].include?(format)
+          raise Error, "--input-schema only applies to CSV/TSV input, not #{format}"
+        end
+        raise Error, "--json-infer-rows only applies to JSON lines input, not #{format}" if infer_rows && format != :jsonl
 
         streaming = should_stream?(path) if streaming.nil?
+        if options[:encoding] && ![:csv, :tsv].include?(format)
+          raise Error, "--encoding only applies to CSV/TSV input, not #{format}"
+        end
+
+        # The --write-schema sidecar still belongs to the original path
+        csv_path = [:csv, :tsv].include?(format) ? decoded_path(path, options, temp_files) : path
+
+        if [:csv, :tsv].include?(format)
+          types = if schema
+                    input_schema_options(path, csv_path, schema, strict: strict_schema, format: format,
+                                                                delimiter: delimiter, has_header: has_header)
+                  else
+                    schema_options(path)
+                  end
+        end
 
         case format
         when :csv
-          read_csv(path, delimiter: delimiter || ",", has_header: has_header, streaming: streaming, **options)
+          read_csv(csv_path, delimiter: delimiter || ",", has_header: has_header, streaming: streaming,
+                   progress: progress, **csv_date_options(parse_dates), **types, **options)
         when :tsv
-          read_csv(path, delimiter: delimiter || "\t", has_header: has_header, streaming: streaming, **options)
+          read_csv(csv_path, delimiter: delimiter || "\t", has_header: has_header, streaming: streaming,
+                   progress: progress, **csv_date_options(parse_dates), **types, **options)
         when :parquet
           if streaming
             Polars.scan_parquet(path, **options)
+          elsif progress&.enabled?
+            read_parquet_row_groups(path, progress, **options)
           else
             Polars.read_parquet(path, **options)
           end
         when :json
           # JSON doesn't support streaming in Polars
-          Polars.read_json(path, **options)
+          Polars.read_json(counted_source(path, progress), **options)
         when :jsonl
           if streaming
-            Polars.scan_ndjson(path, **options)
+            Polars.scan_ndjson(path, **ndjson_options(infer_rows), **options)
           else
-            Polars.read_ndjson(path, **options)
+            Polars.read_ndjson(counted_source(path, progress), **ndjson_options(infer_rows), **options)
           end
+        when :ipc
+          if streaming
+            Polars.scan_ipc(path, **options)
+          else
+            Polars.read_ipc(counted_source(path, progress), **options)
+          end
+        when :orc
+          # Recognized explicitly so ORC isn't misread as CSV
+          raise Error, "ORC input is not available: ruby-polars is built without an ORC reader. " \
+                       "Convert the file to Parquet first (e.g. with Spark or pyarrow)."
         else
           raise Error, "Unsupported format: #{format}"
         end
       end
 
-      def write(df, path, format: nil, delimiter: nil, compression: nil, **options)
+      # Column names exactly as written in a CSV/TSV header line, before Polars dedupes or renames them
+      # Returns nil for other formats and compressed files
+      def read_header(path, format: nil, delimiter: nil)
         format ||= detect_format(path)
+        return unless [:csv, :tsv].include?(format) && !compressed_file?(path)
+
+        delimiter ||= format == :tsv ? "\t" : ","
+        File.open(path, 'r') do |file|
+          line = file.each_line.find { |l| !l.strip.empty? }
+          return [] unless line
+
+          line.delete_prefix("\uFEFF").chomp.split(delimiter, -1).map { |name| name.delete_prefix('"').delete_suffix('"') }
+        end
+      end
+
+      # The candidate that splits the header into several fields and agrees with it on the most records
+      # Returns nil when no candidate splits the header, e.g. a genuinely one-column file
+      def sniff_delimiter(path)
+        sample = open_raw(path) { |io| io.read(SNIFF_BYTES) }.to_s
+        complete = sample.bytesize < SNIFF_BYTES
+        sample = sample.force_encoding(Encoding::UTF_8).scrub.delete_prefix("\uFEFF")
+
+        scores = SNIFF_DELIMITERS.map do |delimiter|
+          counts = record_field_counts(sample, delimiter, complete: complete)
+          header = counts.first.to_i
+          header > 1 ? counts.count(header).fdiv(counts.length) : 0
+        end
+        best = scores.each_index.max_by { |i| [scores[i], -i] }
+        SNIFF_DELIMITERS[best] if scores[best] > 0
+      end
+
+      # Header names with later repeats suffixed: amount, amount, amount -> amount, amount_2, amount_3
+      # Suffixes skip names the header already uses, so a renamed column never collides with a real one
+      def dedupe_names(names)
+        taken = names.uniq
+        seen = Hash.new(0)
+        names.map do |name|
+          seen[name] += 1
+          next name if seen[name] == 1
+
+          suffix = seen[name]
+          suffix += 1 while taken.include?("#{name}_#{suffix}")
+          seen[name] = suffix
+          taken << "#{name}_#{suffix}"
+          "#{name}_#{suffix}"
+        end
+      end
+
+      # Hive-style layout: dir/col=value/.../part.<ext>, with the partition columns left out of the files
+      # Returns the paths written, in first-seen partition order
+      def write_partitioned(df, dir, by:, format: :parquet, **options)
+        df = df.collect if df.is_a?(Polars::LazyFrame)
+        extension = PARTITION_EXTENSIONS.fetch(format) { raise Error, "Unsupported format: #{format}" }
+
+        df.partition_by(by, maintain_order: true, include_key: false, as_dict: true).map do |values, part|
+          segments = by.zip(Array(values)).map { |col, value| "#{partition_segment(col)}=#{partition_segment(value)}" }
+          path = File.join(dir, *segments, "part.#{extension}")
+          FileUtils.mkdir_p(File.dirname(path))
+          write(part, path, format: format, **options)
+          path
+        end
+      end
+
+      # Percent-encodes what isn't safe in a path segment, as Hive does; nil and "" become the null marker
+      def partition_segment(value)
+        return HIVE_NULL_PARTITION if value.nil? || value.to_s.empty?
+
+        value.to_s.gsub(%r{[\x00-\x1F\x7F"#%'*/:=?\\{\[\]^]}) { |char| format("%%%02X", char.ord) }
+      end
+
+      # {"columns": [{"name": ..., "dtype": ...}]} with dtypes named as Dtypes.name prints them
+      def schema_json(schema)
+        JSON.pretty_generate({
+          columns: schema.map { |name, dtype| { name: name, dtype: Dtypes.name(dtype) } },
+        })
+      end
+
+      def write_schema(df, path)
+        File.write("#{path}#{SCHEMA_SUFFIX}", "#{schema_json(df.schema)}\n")
+      end
+
+      # Column name => dtype from the sidecar next to path, or nil when there is none
+      def read_schema(path)
+        sidecar = "#{path}#{SCHEMA_SUFFIX}"
+        return unless File.exist?(sidecar)
+
+        columns = JSON.parse(File.read(sidecar)).fetch("columns")
+        columns.to_h { |column| [column.fetch("name"), Dtypes.parse(column.fetch("dtype"))] }.compact
+      rescue JSON::ParserError, KeyError, TypeError => e
+        raise Error, "Invalid schema file #{sidecar}: #{e.message}"
+      end
+
+      # Yields the file's bytes as a binary IO, decompressing .gz/.zst on the fly
+      def open_raw(path, &block)
+        case path
+        when /\.gz$/i
+          require "zlib"
+          Zlib::GzipReader.open(path) { |gz| block.call(gz) }
+        when /\.zst$/i
+          IO.popen(["zstd", "-dc", path], "rb", &block)
+        else
+          File.open(path, "rb", &block)
+        end
+      rescue Errno::ENOENT => e
+        raise Error, "Cannot open #{path}: #{e.message}"
+      end
+
+      def write(df, path, format: nil, delimiter: nil, compression: nil, bom: false, null_value: nil,
+                row_group_size: nil, **options)
+        format ||= detect_format(path)
+        raise Error, "--bom only applies to CSV/TSV output, not #{format}" if bom && ![:csv, :tsv].include?(format)
+        if row_group_size && format != :parquet
+          raise Error, "--row-group-size only applies to Parquet output, not #{format}"
+        end
+        check_null_value(null_value, format)
 
         # For LazyFrames, use streaming sink methods when possible
         if df.is_a?(Polars::LazyFrame)
           case format
           when :csv
-            return df.sink_csv(path, separator: delimiter || ",", **options)
+            return df.sink_csv(path, separator: delimiter || ",", include_bom: bom,
+                                     **null_options(null_value, delimiter || ","), **options)
           when :tsv
-            return df.sink_csv(path, separator: delimiter || "\t", **options)
+            return df.sink_csv(path, separator: delimiter || "\t", include_bom: bom,
+                                     **null_options(null_value, delimiter || "\t"), **options)
           when :parquet
-            return df.sink_parquet(path, compression: compression || "zstd", **options)
+            return df.sink_parquet(path, compression: compression || "zstd", row_group_size: row_group_size, **options)
           when :jsonl
+            # Streams batches straight to disk, so memory stays bounded for huge outputs
             return df.sink_ndjson(path, **options)
+          when :ipc
+            return df.sink_ipc(path, **options)
           when :json
-            # JSON doesn't have a sink method, need to collect
+            # A bracketed JSON array has no sink method, need to collect (use .jsonl for large outputs)
             df = df.collect
           else
             raise Error, "Unsupported format: #{format}"
@@ -97,30 +314,125 @@ module TabularTool
         # For DataFrames or formats without sink methods
         case format
         when :csv
-          write_csv(df, path, delimiter: delimiter || ",", **options)
+          write_csv(df, path, delimiter: delimiter || ",", bom: bom,
+                              **null_options(null_value, delimiter || ","), **options)
         when :tsv
-          write_csv(df, path, delimiter: delimiter || "\t", **options)
+          write_csv(df, path, delimiter: delimiter || "\t", bom: bom,
+                              **null_options(null_value, delimiter || "\t"), **options)
         when :parquet
-          df.write_parquet(path, compression: compression || "zstd", **options)
+          df.write_parquet(path, compression: compression || "zstd", row_group_size: row_group_size, **options)
         when :json
           df.write_json(path, **options)
         when :jsonl
           df.write_ndjson(path, **options)
+        when :ipc
+          df.write_ipc(path, **options)
         else
           raise Error, "Unsupported format: #{format}"
         end
       end
 
-      def write_to_stdout(df, format:, delimiter: nil, **options)
+      # Writes whole rows until the next one would push the file past max_bytes
+      # LazyFrames are read only as far as the cap needs, one batch at a time; returns [rows_written, truncated]
+      def write_limited(df, path, max_bytes:, format: nil, delimiter: nil, bom: false, null_value: nil)
+        format ||= detect_format(path)
+        unless [:csv, :tsv, :jsonl].include?(format)
+          raise Error, "--limit-bytes is not supported for #{format} output (use CSV, TSV or JSONL)"
+        end
+        check_null_value(null_value, format)
+
+        separator = delimiter || (format == :tsv ? "\t" : ",")
+        nulls = null_options(null_value, separator)
+        serialize = lambda do |frame, header|
+          format == :jsonl ? frame.write_ndjson : frame.write_csv(nil, separator: separator, include_header: header, **nulls)
+        end
+
+        raise Error, "--bom only applies to CSV/TSV output, not #{format}" if bom && format == :jsonl
+
+        File.open(path, "w") do |file|
+          bytes = 0
+          if format != :jsonl
+            header = serialize.call(df.lazy.head(0).collect, true)
+            header = UTF8_BOM + header.b if bom
+            raise Error, "--limit-bytes #{max_bytes} is smaller than the header" if header.bytesize > max_bytes
+
+            file.write(header)
+            bytes = header.bytesize
+          end
+
+          rows = 0
+          start = bytes
+          # How many more rows should fit, going by the bytes per row written so far
+          fits = -> { ((max_bytes - bytes) * rows.fdiv(bytes - start)).ceil if rows.positive? && bytes > start }
+          each_batch(df, fits) do |batch|
+            data = serialize.call(batch, false)
+            if bytes + data.bytesize <= max_bytes
+              file.write(data)
+              bytes += data.bytesize
+              rows += batch.height
+              next
+            end
+
+            # Only the batch that crosses the cap is written row by row
+            batch.height.times do |i|
+              row = serialize.call(batch.slice(i, 1), false)
+              break if bytes + row.bytesize > max_bytes
+
+              file.write(row)
+              bytes += row.bytesize
+              rows += 1
+            end
+            return [rows, true]
+          end
+          [rows, false]
+        end
+      end
+
+      # Appends rows to a text output, writing the header (and any BOM) only when the file is new or empty
+      def append(df, path, format: nil, delimiter: nil, bom: false, null_value: nil)
+        format ||= detect_format(path)
+        raise Error, "--bom only applies to CSV/TSV output, not #{format}" if bom && ![:csv, :tsv].include?(format)
+        check_null_value(null_value, format)
+
+        df = df.collect if df.is_a?(Polars::LazyFrame)
+        include_header = !File.exist?(path) || File.zero?(path)
+
+        data = case format
+        when :csv
+          df.write_csv(nil, separator: delimiter || ",", include_header: include_header,
+                            **null_options(null_value, delimiter || ","))
+        when :tsv
+          df.write_csv(nil, separator: delimiter || "\t", include_header: include_header,
+                            **null_options(null_value, delimiter || "\t"))
+        when :jsonl
+          df.write_ndjson
+        when :parquet, :json, :ipc
+          raise Error, "--append is not supported for #{format} output (use CSV, TSV or JSONL)"
+        else
+          raise Error, "Unsupported format: #{format}"
+        end
+
+        data = UTF8_BOM + data.b if bom && include_header
+        File.open(path, "a") { |file| file.write(data) }
+      end
+
+      def write_to_stdout(df, format:, delimiter: nil, null_value: nil, **options)
+        check_null_value(null_value, format)
         case format
         when :csv
-          df.write_csv(nil, separator: delimiter || ",")
+          df.write_csv(nil, separator: delimiter || ",", **null_options(null_value, delimiter || ","))
         when :tsv
-          df.write_csv(nil, separator: delimiter || "\t")
+          df.write_csv(nil, separator: delimiter || "\t", **null_options(null_value, delimiter || "\t"))
         when :json
           df.write_json
         when :jsonl
           df.write_ndjson
+        when :arrow
+          # Arrow IPC stream keeps dtypes intact when piping between tt invocations
+          require 'stringio'
+          sio = StringIO.new(String.new)
+          df.write_ipc_stream(sio)
+          sio.string
         when :parquet
           # For stdout, we need to write to memory buffer
           # This is tricky with Polars, may need to write to temp file
@@ -130,12 +442,14 @@ module TabularTool
         end
       end
 
-      def read_from_io(io, format:, delimiter: nil, has_header: true, **options)
+      def read_from_io(io, format:, delimiter: nil, has_header: true, parse_dates: nil, **options)
         case format
         when :csv
-          read_csv_from_io(io, delimiter: delimiter || ",", has_header: has_header, **options)
+          read_csv_from_io(io, delimiter: delimiter || ",", has_header: has_header, **csv_date_options(parse_dates), **options)
         when :tsv
-          read_csv_from_io(io, delimiter: delimiter || "\t", has_header: has_header, **options)
+          read_csv_from_io(io, delimiter: delimiter || "\t", has_header: has_header, **csv_date_options(parse_dates), **options)
+        when :arrow
+          Polars.read_ipc_stream(io, **options)
         else
           raise Error, "Shell decompression only supports CSV/TSV, got: #{format}"
         end
@@ -143,26 +457,210 @@ module TabularTool
 
       private
 
-      def read_csv(path, delimiter:, has_header: true, streaming: false, **options)
+      # Yields LIMITED_WRITE_BATCH_ROWS rows at a time. A LazyFrame runs as head(k) into a temp
+      # Parquet file with a row group per batch, so each batch reads back only its own row group instead
+      # of re-running the plan up to its offset. k starts at one batch; while the caller keeps taking
+      # rows, the next round is sized by fits (rows the caller expects to still need), and at least
+      # doubles so the input's head is re-read only a few times. Reading stops once the caller stops
+      # (it returns out of the block) or the input runs out.
+      def each_batch(df, fits)
+        unless df.is_a?(Polars::LazyFrame)
+          (0...df.height).step(LIMITED_WRITE_BATCH_ROWS) { |offset| yield df.slice(offset, LIMITED_WRITE_BATCH_ROWS) }
+          return
+        end
+
+        require "tmpdir"
+        Dir.mktmpdir("tt-limited") do |dir|
+          spill = File.join(dir, "rows.parquet")
+          limit = LIMITED_WRITE_BATCH_ROWS
+          done = 0
+          loop do
+            df.head(limit).sink_parquet(spill, row_group_size: LIMITED_WRITE_BATCH_ROWS)
+            total = Polars.scan_parquet(spill).select(Polars.len.alias("rows")).collect["rows"][0]
+            (done...total).step(LIMITED_WRITE_BATCH_ROWS) do |offset|
+              yield Polars.scan_parquet(spill).slice(offset, LIMITED_WRITE_BATCH_ROWS).collect
+            end
+            return if total < limit
+
+            done = total
+            # A tenth more than the estimate, so a slightly wider tail still lands in this round
+            limit = [done + (fits.call.to_i * 1.1).ceil, limit * 2].max
+          end
+        end
+      end
+
+      # Single-byte input is transcoded to a UTF-8 temp file in chunks, so memory stays bounded and
+      # a lazy scan still works; the encoding option is consumed here rather than passed to Polars
+      def decoded_path(path, options, temp_files)
+        source = INPUT_ENCODINGS.fetch(options[:encoding]) do
+          raise Error, "Unknown --encoding #{options[:encoding]} (supported: #{INPUT_ENCODINGS.keys.join(', ')})"
+        end
+        return path unless source.is_a?(Encoding)
+
+        options.delete(:encoding)
+        require "tempfile"
+        file = Tempfile.new(["tt-decoded", ".csv"], binmode: true)
+        open_raw(path) do |io|
+          while (chunk = io.read(TRANSCODE_CHUNK_BYTES))
+            file.write(chunk.force_encoding(source).encode(Encoding::UTF_8, invalid: :replace, undef: :replace))
+          end
+        end
+        file.close
+        # Kept past this method, since a lazy scan reads the file when collected. The caller's
+        # temp_files close it when its run ends; without them it lasts until the same path is decoded again
+        if temp_files
+          temp_files << file
+        else
+          decoded = (@decoded_files ||= {})
+          decoded.delete(path)&.close!
+          decoded[path] = file
+        end
+        file.path
+      end
+
+      # Fields per record, skipping delimiters and newlines inside double quotes; a trailing record
+      # cut off by the sample size is left out
+      def record_field_counts(sample, delimiter, complete:)
+        counts = []
+        fields = 1
+        quoted = false
+        blank = true
+        sample.each_char do |char|
+          if char == '"'
+            # A doubled "" inside a quoted field toggles twice, so it stays quoted
+            quoted = !quoted
+            blank = false
+          elsif quoted
+            next
+          elsif char == delimiter
+            fields += 1
+            blank = false
+          elsif char == "\n"
+            counts << fields unless blank
+            fields = 1
+            blank = true
+          elsif char != "\r"
+            blank = false
+          end
+        end
+        counts << fields if complete && !blank
+        counts
+      end
+
+      def check_null_value(null_value, format)
+        return if null_value.nil? || [:csv, :tsv].include?(format)
+
+        raise Error, "--null-string only applies to CSV/TSV output, not #{format}"
+      end
+
+      # Polars writes null_value verbatim, so one containing the delimiter, a quote or a newline
+      # is quoted here to keep the row's field count intact
+      def null_options(null_value, separator)
+        return {} if null_value.nil?
+
+        null_value = "\"#{null_value.gsub('"', '""')}\"" if null_value.match?(/[#{Regexp.escape(separator)}"\r\n]/)
+        { null_value: null_value }
+      end
+
+      # A UTF-8 BOM left on the first header name (e.g. "\uFEFFid" from Excel) is dropped
+      def without_bom(df)
+        first = df.columns.first
+        return df unless first&.start_with?("\uFEFF")
+
+        df.rename({ first => first.delete_prefix("\uFEFF") })
+      end
+
+      def read_csv(path, delimiter:, has_header: true, streaming: false, progress: nil, **options)
         # Work around Polars bug with leading empty lines by preprocessing
         # Only for uncompressed files (compressed files are handled differently)
         # https://github.com/pola-rs/polars/issues/xxxxx
         if !compressed_file?(path) && has_leading_empty_lines?(path)
           # Read file, skip leading empty lines, then pass to Polars
           File.open(path, 'r') do |file|
-            read_csv_from_io(file, delimiter: delimiter, has_header: has_header, streaming: streaming, **options)
+            read_csv_from_io(progress&.wrap(file) || file, delimiter: delimiter, has_header: has_header,
+                                                           streaming: streaming, **options)
           end
         else
-          if streaming
-            Polars.scan_csv(path, separator: delimiter, has_header: has_header, **options)
-          else
-            Polars.read_csv(path, separator: delimiter, has_header: has_header, **options)
-          end
+          df = if streaming
+                 Polars.scan_csv(path, separator: delimiter, has_header: has_header, **options)
+               else
+                 Polars.read_csv(counted_source(path, progress), separator: delimiter, has_header: has_header, **options)
+               end
+          without_bom(df)
         end
       end
 
-      def write_csv(df, path, delimiter:, **options)
-        df.write_csv(path, separator: delimiter, **options)
+      # Polars reads a path in one opaque call; with the bar shown, the bytes go through it first
+      # (held in memory once more while parsing, which eager reads of this size can afford)
+      def counted_source(path, progress)
+        return path unless progress&.enabled?
+
+        require "stringio"
+        File.open(path, "rb") { |file| StringIO.new(progress.wrap(file).read) }
+      end
+
+      # One row group at a time, advancing the bar by each group's bytes as it's decoded
+      def read_parquet_row_groups(path, progress, **options)
+        groups = ParquetFooter.row_group_sizes(path)
+        if groups.length < 2
+          df = Polars.read_parquet(path, **options)
+          progress.advance(groups.sum { |_, bytes| bytes.to_i })
+          return df
+        end
+
+        offset = 0
+        parts = groups.map do |rows, bytes|
+          part = Polars.scan_parquet(path, **options).slice(offset, rows).collect
+          offset += rows
+          progress.advance(bytes.to_i)
+          part
+        end
+        Polars.concat(parts, rechunk: false)
+      end
+
+      # A --write-schema sidecar restores the dtypes the CSV was written from
+      def schema_options(path)
+        schema = read_schema(path)
+        schema&.any? ? { schema_overrides: schema } : {}
+      end
+
+      # A schema naming every column replaces inference entirely; a partial one overrides the listed
+      # columns (on top of any --write-schema sidecar) and lets Polars infer the rest, unless strict
+      def input_schema_options(path, csv_path, schema, strict:, format:, delimiter:, has_header:)
+        header = has_header ? read_header(csv_path, format: format, delimiter: delimiter) : schema.keys
+        if header
+          unknown = schema.keys - header
+          raise Error, "--input-schema names columns not in #{File.basename(path)}: #{unknown.join(', ')}" if unknown.any?
+
+          missing = header - schema.keys
+          return { schema: header.to_h { |name| [name, schema[name]] } } if missing.empty?
+          raise Error, "--strict-schema: no type given for #{missing.join(', ')}" if strict
+        elsif strict
+          raise Error, "--strict-schema needs an uncompressed file, whose header can be checked"
+        end
+
+        { schema_overrides: (read_schema(path) || {}).merge(schema) }
+      end
+
+      # Keys are unioned over the lines read for inference, and lines missing a key read it as null;
+      # a key that first appears after them is dropped, hence :all
+      def ndjson_options(infer_rows)
+        return {} if infer_rows.nil?
+
+        { infer_schema_length: infer_rows == :all ? nil : infer_rows }
+      end
+
+      def csv_date_options(parse_dates)
+        parse_dates.nil? ? {} : { try_parse_dates: parse_dates }
+      end
+
+      def write_csv(df, path, delimiter:, bom: false, **options)
+        return df.write_csv(path, separator: delimiter, **options) unless bom
+
+        File.open(path, "wb") do |file|
+          file.write(UTF8_BOM)
+          df.write_csv(file, separator: delimiter, **options)
+        end
       end
 
       def read_csv_from_io(io, delimiter:, has_header: true, streaming: false, **options)
@@ -179,7 +677,7 @@ module TabularTool
         require 'stringio'
         cleaned_io = StringIO.new(lines.join)
 
-        Polars.read_csv(cleaned_io, separator: delimiter, has_header: has_header, **options)
+        without_bom(Polars.read_csv(cleaned_io, separator: delimiter, has_header: has_header, **options))
       end
 
       def has_leading_empty_lines?(path)
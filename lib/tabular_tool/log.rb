@@ -0,0 +1,55 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Informational messages, always on stderr so stdout only ever carries data
+  #   quiet   - errors only (the caller prints those)
+  #   normal  - warnings and short notes such as clean's summary
+  #   verbose - plus diagnostics: files read and written, row counts, timings
+  module Log
+    LEVELS = [:quiet, :normal, :verbose].freeze
+
+    class << self
+      def level
+        @level || :normal
+      end
+
+      def level=(level)
+        raise ArgumentError, "Unknown log level: #{level}" unless LEVELS.include?(level)
+
+        @level = level
+      end
+
+      # --quiet beats --verbose when both are given
+      def configure(quiet: false, verbose: false)
+        self.level = quiet ? :quiet : verbose ? :verbose : :normal
+      end
+
+      def quiet?
+        level == :quiet
+      end
+
+      def verbose?
+        level == :verbose
+      end
+
+      def warn(message)
+        write("Warning: #{message}") unless quiet?
+      end
+
+      def info(message)
+        write(message) unless quiet?
+      end
+
+      def debug(message)
+        write(message) if verbose?
+      end
+
+      private
+
+      # Looked up on every call, so redirecting $stderr (as tests do) takes effect
+      def write(message)
+        $stderr.puts(message)
+      end
+    end
+  end
+end
@@ -1,25 +1,214 @@
 # frozen_string_literal: true
 
+require "did_you_mean"
+require "tmpdir"
+
 require_relative "operations/sort"
 require_relative "operations/filter"
+require_relative "operations/expr_filter"
 require_relative "operations/lint"
+require_relative "operations/lint_report"
 require_relative "operations/stats"
+require_relative "operations/clean"
+require_relative "operations/anonymize"
+require_relative "operations/window"
+require_relative "operations/resample"
 
 module TabularTool
   module Operations
-    def self.select(df, columns:)
-      df.select(columns)
+    # Unknown-column errors list every column when there are at most this many
+    LISTED_COLUMNS_MAX = 20
+
+    # Checks names against the frame's schema (no data is read), so a typo fails before a long scan
+    def self.require_columns(df, columns, option:)
+      available = df.columns
+      missing = columns.uniq - available
+      return if missing.empty?
+
+      checker = DidYouMean::SpellChecker.new(dictionary: available)
+      names = missing.map do |name|
+        guess = available.find { |col| col.casecmp?(name) } || checker.correct(name).first
+        guess ? "#{name} (did you mean '#{guess}'?)" : name
+      end
+      message = "Unknown column(s) in #{option}: #{names.join(', ')}"
+      message += ". Columns: #{available.join(', ')}" if available.length <= LISTED_COLUMNS_MAX
+      raise Error, message
+    end
+
+    # Expands selection tokens to column names, one array per token
+    # "^...$" tokens are regexes, as in Polars.col; "a..b" is a through b in column order;
+    # everything else is a literal name
+    # mode: nil      - unknown names fail; a regex may match nothing
+    #       :strict  - anything that matches no column fails
+    #       :lenient - unknown names are skipped with a warning
+    def self.resolve_columns(df, tokens, option:, mode: nil)
+      available = df.columns
+      missing = []
+      resolved = tokens.map do |token|
+        if token.start_with?("^") && token.end_with?("$")
+          matches = available.grep(Regexp.new(token))
+          raise Error, "#{option} pattern #{token} matches no columns (--strict)" if matches.empty? && mode == :strict
+
+          matches
+        elsif available.include?(token)
+          [token]
+        elsif (range = token.match(/\A(.+?)\.\.(.+)\z/))
+          first, last = range.captures.map(&:strip)
+          ends = [first, last].select { |name| available.include?(name) }
+          missing.concat([first, last] - ends)
+          next [] unless ends.length == 2
+
+          from, to = available.index(first), available.index(last)
+          raise Error, "#{option} range #{token} is reversed: #{first} comes after #{last}" if from > to
+
+          available[from..to]
+        else
+          missing << token
+          []
+        end
+      end
+      return resolved if missing.empty?
+
+      require_columns(df, missing, option: option) unless mode == :lenient
+      Log.warn "#{option}: skipping missing column(s) #{missing.join(', ')}"
+      resolved
+    end
+
+    # "!col" tokens select every column except the listed ones
+    # "new=old" tokens select old and rename it to new, like SQL's `old AS new`
+    def self.select(df, columns:, mode: nil)
+      negated, plain = columns.partition { |col| col.start_with?("!") }
+      if negated.empty?
+        renames = plain.map { |token| token.split("=", 2).map(&:strip) }
+        sources = resolve_columns(df, renames.map(&:last), option: "--select", mode: mode)
+        return df.select(renames.zip(sources).flat_map do |(new_name, source), matches|
+          source ? matches.map { |col| Polars.col(col).alias(new_name) } : matches.map { |col| Polars.col(col) }
+        end)
+      end
+
+      raise Error, "Cannot mix excluded (!col) and plain columns in --select" if plain.any?
+
+      excluded = resolve_columns(df, negated.map { |col| col.delete_prefix("!") }, option: "--select", mode: mode).flatten
+      df.select(df.columns - excluded)
+    end
+
+    # Keeps the columns whose dtype falls in any of the groups (see Dtypes::GROUPS), in their current order
+    def self.select_dtypes(df, groups:, mode: nil)
+      unknown = groups - Dtypes::GROUPS.keys
+      raise Error, "Unknown --select-dtypes: #{unknown.join(', ')}. Valid: #{Dtypes::GROUPS.keys.join(', ')}" if unknown.any?
+
+      schema = df.schema
+      if mode == :strict
+        empty = groups.reject { |group| schema.values.any? { |dtype| Dtypes.in_group?(dtype, group) } }
+        raise Error, "--select-dtypes #{empty.join(', ')} matches no columns (--strict)" if empty.any?
+      end
+      df.select(df.columns.select { |col| groups.any? { |group| Dtypes.in_group?(schema[col], group) } })
+    end
+
+    # Puts the surviving input columns back in their input order, with columns added since at the end
+    def self.restore_column_order(df, input_columns)
+      current = df.columns
+      order = (input_columns & current) + (current - input_columns)
+      order == current ? df : df.select(order)
+    end
+
+    # Removes rows with a null in any of the columns (every column when none are given)
+    def self.drop_nulls(df, columns: nil)
+      require_columns(df, columns, option: "--drop-nulls-on") if columns
+
+      df.drop_nulls(subset: columns)
+    end
+
+    # Prepends an ordinal column counting from offset in the frame's current order
+    def self.with_row_index(df, name:, offset: 0)
+      raise Error, "Column #{name} already exists; pass another name to --with-row-index" if df.columns.include?(name)
+      raise Error, "--row-index-offset must not be negative" if offset.negative?
+
+      df.with_row_index(name: name, offset: offset)
     end
 
-    def self.drop(df, columns:)
-      df.drop(columns)
+    # Strips leading/trailing whitespace from String columns (all of them, or the listed ones)
+    # Columns of other types are left untouched
+    def self.trim(df, columns: nil)
+      require_columns(df, columns, option: "--trim") if columns
+      schema = df.schema
+
+      targets = (columns || schema.keys).select { |col| Dtypes.kind?(schema[col], Polars::String) }
+      return df if targets.empty?
+
+      df.with_columns(targets.map { |col| Polars.col(col).str.strip_chars })
     end
 
-    def self.unique(df, columns: nil)
+    ELLIPSIS = "…"
+
+    # Shortens String values longer than max_chars to max_chars characters, the last being an ellipsis
+    # Slicing counts characters, never bytes, so multibyte text stays valid UTF-8
+    def self.truncate_strings(df, max_chars:, ellipsis: true)
+      raise Error, "--truncate-strings must be positive" unless max_chars.positive?
+
+      schema = df.schema
+      targets = schema.keys.select { |col| Dtypes.kind?(schema[col], Polars::String) }
+      return df if targets.empty?
+
+      keep = ellipsis ? max_chars - 1 : max_chars
+      df.with_columns(targets.map do |col|
+        shortened = Polars.col(col).str.slice(0, keep)
+        shortened += Polars.lit(ELLIPSIS) if ellipsis
+        Polars.when(Polars.col(col).str.len_chars > max_chars).then(shortened).otherwise(Polars.col(col)).alias(col)
+      end)
+    end
+
+    CELL_BYTE_ACTIONS = [:error, :truncate].freeze
+
+    # Guards writers against oversized String cells: raise naming the first offending column,
+    # or cut values to max_bytes at a character boundary
+    def self.limit_cell_bytes(df, max_bytes:, action: :error)
+      schema = df.schema
+      targets = schema.keys.select { |col| Dtypes.kind?(schema[col], Polars::String) }
+      return df if targets.empty?
+
+      if action == :truncate
+        cut = ->(value) { value.byteslice(0, max_bytes).scrub("") }
+        return df.with_columns(targets.map do |col|
+          Polars.when(Polars.col(col).str.len_bytes > max_bytes)
+            .then(Polars.col(col).map_elements(cut, return_dtype: Polars::String))
+            .otherwise(Polars.col(col))
+            .alias(col)
+        end)
+      end
+
+      longest = df.lazy.select(targets.map { |col| Polars.col(col).str.len_bytes.max.alias(col) }).collect.row(0, named: true)
+      col, bytes = longest.find { |_, size| size && size > max_bytes }
+      raise Error, "Column #{col} has a #{bytes}-byte value, over --max-cell-bytes #{max_bytes}" if col
+
+      df
+    end
+
+    # Lowercases or uppercases the values of the listed String columns
+    def self.change_case(df, columns:, to:)
+      option = to == :lower ? "--lower" : "--upper"
+      require_columns(df, columns, option: option)
+      schema = df.schema
+
+      non_string = columns.reject { |col| Dtypes.kind?(schema[col], Polars::String) }
+      raise Error, "#{option} only applies to String columns, not: #{non_string.join(', ')}" if non_string.any?
+
+      df.with_columns(columns.map do |col|
+        to == :lower ? Polars.col(col).str.to_lowercase : Polars.col(col).str.to_uppercase
+      end)
+    end
+
+    def self.drop(df, columns:, mode: nil)
+      df.drop(resolve_columns(df, columns, option: "--drop", mode: mode).flatten.uniq)
+    end
+
+    # maintain_order keeps first-seen row order; disabling it lets Polars dedupe in parallel
+    def self.unique(df, columns: nil, maintain_order: true)
       if columns
-        df.unique(subset: columns, maintain_order: true)
+        require_columns(df, columns, option: "--unique-on")
+        df.unique(subset: columns, maintain_order: maintain_order)
       else
-        df.unique(maintain_order: true)
+        df.unique(maintain_order: maintain_order)
       end
     end
 
@@ -29,44 +218,194 @@ module TabularTool
       result.is_a?(Polars::LazyFrame) ? result.collect : result
     end
 
+    # Up to n rows from each group, in the frame's current order (so a prior sort picks which rows)
+    def self.head_per_group(df, n:, by:)
+      df.lazy.filter(Polars.int_range(0, Polars.len).over(by) < n).collect
+    end
+
+    # A streaming count for lazy frames; the CLI memoizes it per frame so one run counts once
+    def self.row_count(df)
+      return df.height if df.is_a?(Polars::DataFrame)
+
+      df.select(Polars.len.alias("count")).collect["count"][0]
+    end
+
+    # For a lazy CSV scan this re-reads the file's head to infer types, so the CLI memoizes it too
+    def self.schema(df)
+      df.schema
+    end
+
+    # First and last n rows plus the total row count, all from the same LazyFrame
+    # The tail is a negative slice, so Parquet isn't read a second time from the start
+    def self.peek(df, n: 5)
+      lf = df.lazy
+      total = row_count(lf)
+      return { total: total, head: lf.collect, tail: nil } if total <= n * 2
+
+      { total: total, head: lf.head(n).collect, tail: lf.slice(-n, n).collect }
+    end
+
     def self.tail(df, n: 10)
-      result = df.tail(n)
-      # If the input is a LazyFrame, tail returns a LazyFrame, so we need to collect it
-      result.is_a?(Polars::LazyFrame) ? result.collect : result
+      return df.tail(n) unless df.is_a?(Polars::LazyFrame)
+
+      # A streaming count, then a positive slice: Polars can skip straight to the last rows
+      # (whole row groups for Parquet) instead of buffering the file for a negative offset
+      total = row_count(df)
+      df.slice([total - n, 0].max, n).collect
     end
 
-    def self.sample(df, n: nil, fraction: nil)
-      # Handle lazy frames efficiently by sampling across the entire dataset
-      if df.is_a?(Polars::LazyFrame)
-        # Get total row count (fast operation)
-        total_rows = df.select(Polars.len.alias("count")).collect["count"][0]
+    SAMPLE_METHODS = [:window, :reservoir, :full].freeze
 
-        # Calculate sample size
-        sample_size = if fraction
-          [(total_rows * fraction).round, total_rows].min
-        elsif n
-          [n, total_rows].min
-        else
-          raise Error, "Must specify either n or fraction for sample"
+    # Random rows by one of three strategies:
+    #   window    - random row positions fetched one slice at a time (with replacement; cheap on lazy frames)
+    #   reservoir - uniform sample without replacement: distinct row positions drawn from the row
+    #               count, then one streaming pass keeps those rows (default)
+    #   full      - collect everything, then shuffle and take
+    # total_rows skips the count when the caller already knows it
+    def self.sample(df, n: nil, fraction: nil, method: :reservoir, seed: nil, total_rows: nil)
+      raise Error, "Must specify either n or fraction for sample" unless n || fraction
+      raise Error, "Unknown sample method: #{method}" unless SAMPLE_METHODS.include?(method)
+
+      total_rows ||= row_count(df)
+      sample_size = fraction ? (total_rows * fraction).round : n
+      sample_size = [sample_size, total_rows].min
+      random = seed ? Random.new(seed) : Random.new
+
+      case method
+      when :window
+        # Generate random indices across the entire range, then fetch rows at them using slice
+        random_indices = (0...sample_size).map { random.rand(total_rows) }.sort
+        return df.lazy.head(0).collect if random_indices.empty?
+
+        samples = random_indices.map do |idx|
+          row = df.slice(idx, 1)
+          row.is_a?(Polars::LazyFrame) ? row.collect : row
         end
+        Polars.concat(samples)
+      when :reservoir
+        indices = distinct_indices(total_rows, sample_size, random)
+        result = df.lazy.with_row_index(name: "__row").filter(Polars.col("__row").is_in(indices)).drop("__row")
+        result.collect
+      when :full
+        df = df.collect if df.is_a?(Polars::LazyFrame)
+        df.sample(n: sample_size, shuffle: true, seed: random.rand(2**32))
+      end
+    end
 
-        # Generate random indices across the entire range
-        random_indices = (0...sample_size).map { rand(total_rows) }.sort
+    # n rows (or a fraction of the rows) drawn from each group; groups smaller than n are kept
+    # whole, and sampled rows keep their original relative order
+    def self.sample_per_group(df, by:, n: nil, fraction: nil, seed: nil)
+      raise Error, "Must specify either n or fraction for sample" unless n || fraction
 
-        # Fetch rows at random indices using slice
-        samples = random_indices.map { |idx| df.slice(idx, 1).collect }
+      position = Polars.int_range(0, Polars.len).over(by)
+      keep = fraction ? position < (Polars.len.over(by) * fraction).round(0) : position < n
+      indexed = df.lazy.with_row_index(name: "__row").collect
+      shuffle(indexed, seed: seed).lazy.filter(keep).sort("__row").drop("__row").collect
+    end
 
-        # Combine all samples into one DataFrame
-        return Polars.concat(samples)
+    # Stratified sample of n rows (or a fraction) in total, split across the groups of `by`
+    # proportionally to their size, or equally with balanced; rows keep their original order
+    def self.sample_stratified(df, by:, n: nil, fraction: nil, balanced: false, seed: nil)
+      raise Error, "Must specify either n or fraction for sample" unless n || fraction
+
+      # One string key per stratum, with nulls as a value of their own
+      stratum = Polars.concat_str(by.map { |col| Polars.col(col).cast(Polars::String).fill_null("\u0000") }, separator: "\u001f")
+      indexed = df.lazy.with_row_index(name: "__row").with_columns(stratum.alias("__stratum")).collect
+
+      sizes = indexed.group_by("__stratum", maintain_order: true).agg(Polars.len.alias("__size"))
+      target = fraction ? (indexed.height * fraction).round : [n, indexed.height].min
+      quotas = allocate_strata(sizes["__size"].to_a, target, balanced)
+      quota = Polars.col("__stratum").replace_strict(sizes["__stratum"].to_a, quotas)
+
+      shuffle(indexed, seed: seed).lazy
+        .filter(Polars.int_range(0, Polars.len).over("__stratum") < quota)
+        .sort("__row")
+        .drop("__row", "__stratum")
+        .collect
+    end
+
+    # Splits target across groups by largest remainder, weighted by size (or equally when balanced)
+    # Groups too small for their share give the surplus back to the rest, so the total stays at target
+    def self.allocate_strata(sizes, target, balanced)
+      quotas = Array.new(sizes.length, 0)
+      remaining = target
+
+      while remaining > 0
+        open = sizes.each_index.select { |i| quotas[i] < sizes[i] }
+        break if open.empty?
+
+        weights = open.map { |i| balanced ? 1.0 : sizes[i].to_f }
+        exact = open.zip(weights).map { |i, weight| [i, remaining * weight / weights.sum] }
+        grant = exact.to_h { |i, share| [i, share.floor] }
+        leftover = remaining - grant.values.sum
+        exact.sort_by { |i, share| [share.floor - share, i] }.first(leftover).each { |i, _| grant[i] += 1 }
+
+        granted = 0
+        grant.each do |i, count|
+          count = [count, sizes[i] - quotas[i]].min
+          quotas[i] += count
+          granted += count
+        end
+        break if granted.zero?
+
+        remaining -= granted
       end
 
-      # For DataFrames, use built-in sample method
-      if fraction
-        df.sample(fraction: fraction)
-      elsif n
-        df.sample(n: [n, df.height].min)
-      else
-        raise Error, "Must specify either n or fraction for sample"
+      quotas
+    end
+    private_class_method :allocate_strata
+
+    # k distinct positions below total_rows, uniformly, by Floyd's algorithm: O(k) draws however
+    # long the input, and only the positions are held, never the rows themselves
+    def self.distinct_indices(total_rows, k, random)
+      chosen = {}
+      ((total_rows - k)...total_rows).each do |j|
+        pick = random.rand(j + 1)
+        chosen[chosen.key?(pick) ? j : pick] = true
+      end
+      chosen.keys.sort
+    end
+    private_class_method :distinct_indices
+
+    # Full random permutation of the rows; needs the whole frame in memory
+    def self.shuffle(df, seed: nil)
+      df = df.collect if df.is_a?(Polars::LazyFrame)
+      random = seed ? Random.new(seed) : Random.new
+      indices = (0...df.height).to_a.shuffle(random: random)
+      df.select(Polars.all.gather(indices))
+    end
+
+    # Two-pass shuffle for inputs larger than memory: the input plan runs once, hashing rows into
+    # buckets and streaming into a temporary Parquet file ordered by bucket, so each bucket is one
+    # contiguous slice of it. Each bucket is read back and shuffled on its own, and the buckets are
+    # concatenated in random order. Yields a LazyFrame over the temp files, which are removed once
+    # the block returns.
+    def self.external_shuffle(df, buckets:, seed: nil, temp_dir: nil)
+      random = seed ? Random.new(seed) : Random.new
+      hash_seed = random.rand(2**32)
+      keyed = df.lazy
+        .with_row_index(name: "__row")
+        .with_columns((Polars.col("__row").hash(hash_seed) % buckets).alias("__bucket"))
+        .drop("__row")
+
+      Dir.mktmpdir("tt-shuffle", temp_dir) do |dir|
+        spill = File.join(dir, "keyed.parquet")
+        # maintain_order keeps each bucket's rows in input order, so a seed reproduces the shuffle
+        keyed.sort("__bucket", maintain_order: true).sink_parquet(spill)
+        counts = Polars.scan_parquet(spill)
+          .select(buckets.times.map { |bucket| (Polars.col("__bucket") == bucket).sum.alias("bucket:#{bucket}") })
+          .collect.row(0)
+        offsets = counts.each_with_object([0]) { |count, starts| starts << starts.last + count }
+
+        paths = counts.each_with_index.map do |count, bucket|
+          path = File.join(dir, "bucket-#{bucket}.parquet")
+          rows = Polars.scan_parquet(spill).slice(offsets[bucket], count).drop("__bucket").collect
+          shuffle(rows, seed: random.rand(2**32)).write_parquet(path)
+          path
+        end
+        File.delete(spill)
+
+        yield Polars.concat(paths.shuffle(random: random).map { |path| Polars.scan_parquet(path) })
       end
     end
   end
@@ -0,0 +1,43 @@
+# frozen_string_literal: true
+
+require "digest"
+
+module TabularTool
+  module Operations
+    # Pseudonymizes columns before an extract is shared
+    # Hashing is deterministic, so hashed keys still join across files written with the same salt
+    module Anonymize
+      HASH_ALGORITHMS = {
+        "sha256" => Digest::SHA256,
+        "sha512" => Digest::SHA512,
+        "sha1" => Digest::SHA1,
+        "md5" => Digest::MD5,
+      }.freeze
+
+      # Replaces every non-null value of a redacted column
+      MASK = "***"
+
+      # Hex digest of salt + the value's text; nulls stay null
+      def self.hash_values(df, columns:, algorithm: "sha256", salt: nil)
+        digest = HASH_ALGORITHMS.fetch(algorithm) do
+          raise Error, "Unknown hash algorithm: #{algorithm}. Valid: #{HASH_ALGORITHMS.keys.join(', ')}"
+        end
+        Operations.require_columns(df, columns, option: "--hash-columns")
+        salt = salt.to_s
+
+        df.with_columns(columns.map do |col|
+          Polars.col(col).cast(Polars::String)
+            .map_elements(->(value) { digest.hexdigest(salt + value) }, return_dtype: Polars::String)
+            .alias(col)
+        end)
+      end
+
+      def self.redact(df, columns:)
+        Operations.require_columns(df, columns, option: "--redact-columns")
+        df.with_columns(columns.map do |col|
+          Polars.when(Polars.col(col).is_not_null).then(Polars.lit(MASK)).otherwise(Polars.lit(nil, dtype: Polars::String)).alias(col)
+        end)
+      end
+    end
+  end
+end
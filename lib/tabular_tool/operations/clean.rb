@@ -0,0 +1,76 @@
+# frozen_string_literal: true
+
+module TabularTool
+  module Operations
+    # Applies lint remediations in a fixed order: headers, trim, empty-to-null, dedupe
+    # Returns the cleaned frame and how many headers/cells/rows each fix touched
+    module Clean
+      def self.call(df, normalize_headers: false, trim: nil, empty_to_null: false, dedupe: false)
+        lf = df.lazy
+        summary = {}
+
+        if normalize_headers
+          columns = lf.columns
+          names = normalized_headers(columns)
+          summary[:headers] = columns.zip(names).count { |old, new| old != new }
+          lf = lf.rename(columns.zip(names).to_h)
+        end
+
+        # Each fix stays a lazy step; its touch count is an expression over the renamed input that
+        # follows the earlier fixes (values), so every count comes from one aggregate
+        source = lf
+        values = lf.columns.to_h { |col| [col, Polars.col(col)] }
+        counts = {}
+
+        if trim
+          columns = string_columns(lf, trim == :all ? nil : trim)
+          counts[:trimmed] = cell_count(columns.map { |col| values[col].str.strip_chars != values[col] })
+          columns.each { |col| values[col] = values[col].str.strip_chars }
+          lf = Operations.trim(lf, columns: columns)
+        end
+
+        if empty_to_null
+          columns = string_columns(lf)
+          counts[:nulled] = cell_count(columns.map { |col| values[col] == "" })
+          columns.each { |col| values[col] = Polars.when(values[col] != "").then(values[col]) }
+          lf = lf.with_columns(columns.map { |col| Polars.when(Polars.col(col) != "").then(Polars.col(col)).alias(col) })
+        end
+
+        if dedupe
+          # unique keeps one row per distinct cleaned row
+          counts[:deduped] = Polars.len - Polars.struct(values.map { |col, value| value.alias(col) }).n_unique
+          lf = Operations.unique(lf)
+        end
+
+        if counts.any?
+          row = source.select(counts.map { |key, expr| expr.alias(key.to_s) }).collect.row(0, named: true)
+          counts.each_key { |key| summary[key] = row[key.to_s] }
+        end
+
+        [df.is_a?(Polars::LazyFrame) ? lf : lf.collect, summary]
+      end
+
+      # snake_case names; blanks become column_N and repeats get _2, _3, ... (skipping names in use)
+      def self.normalized_headers(columns)
+        bases = columns.each_with_index.map do |name, i|
+          base = name.strip.gsub(/([a-z\d])([A-Z])/, '\1_\2').downcase.gsub(/[^a-z0-9]+/, "_").gsub(/\A_+|_+\z/, "")
+          base.empty? ? "column_#{i + 1}" : base
+        end
+        Formats.dedupe_names(bases)
+      end
+
+      def self.string_columns(df, columns = nil)
+        schema = df.schema
+        (columns || schema.keys).select { |col| Dtypes.kind?(schema[col], Polars::String) }
+      end
+
+      # Matching cells summed over the per-column conditions, as one expression
+      def self.cell_count(conditions)
+        return Polars.lit(0) if conditions.empty?
+
+        Polars.sum_horizontal(conditions.map { |condition| condition.sum })
+      end
+      private_class_method :string_columns, :cell_count
+    end
+  end
+end
@@ -1,12 +1,31 @@
 # frozen_string_literal: true
 
+require "date"
+
 module TabularTool
   module Operations
     # Translates our DSL filter syntax to SQL WHERE clause syntax
     # Leverages Polars' battle-tested SQL parser instead of maintaining our own
     class DslToSql
-      def initialize(expression)
+      COMPARISON = /(?<column>\b[A-Za-z_]\w*)\s*(?<op>>=|<=|<>|=|>|<)\s*/
+      DATE_VALUE = /\d{4}-\d{2}-\d{2}(?:[ T]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?)?/
+      DATE_LITERAL = /'(?<date>#{DATE_VALUE})'/
+      # '2024-01-01' <= ts, with the literal first
+      REVERSED_DATE_COMPARISON = /#{DATE_LITERAL}\s*(?<op>>=|<=|<>|=|>|<)\s*(?<column>[A-Za-z_]\w*)\b/
+      DATE_BETWEEN = /(?<column>\b[A-Za-z_]\w*)\s+(?<not>NOT\s+)?BETWEEN\s+'(?<low>#{DATE_VALUE})'\s+AND\s+
+                      '(?<high>#{DATE_VALUE})'/ix
+      RELATIVE_DATE = /(?<keyword>\bnow\b|\btoday\b|\byesterday\b)
+                       (?:\s*(?<sign>[-+])\s*interval\s*'(?<amount>\d+)\s*(?<unit>week|day|hour|minute|second)s?')?/ix
+      INTERVAL_SECONDS = { "week" => 604_800, "day" => 86_400, "hour" => 3600, "minute" => 60, "second" => 1 }.freeze
+      STRING_COMPARISON = /(?<column>\b[A-Za-z_]\w*)\s*(?<op>=|<>|(?:NOT\s+)?LIKE\b)\s*'(?<value>(?:[^']|'')*)'/i
+
+      # schema (column name => dtype) enables date shortcuts against Date/Datetime columns
+      # and, with ignore_case, case-insensitive matching against String columns
+      def initialize(expression, schema: nil, now: Time.now, ignore_case: false)
         @expression = expression
+        @schema = schema
+        @now = now
+        @ignore_case = ignore_case
       end
 
       def translate
@@ -15,12 +34,84 @@ module TabularTool
         sql = translate_quotes(sql)
         sql = translate_method_calls(sql)
         sql = translate_operators(sql)
+        sql = translate_dates(sql) if @schema
+        sql = translate_case(sql) if @schema && @ignore_case
 
         sql
       end
 
       private
 
+      # `city = 'nyc'` becomes `LOWER(city) = 'nyc'` for String columns; =, <> and (NOT) LIKE only
+      def translate_case(sql)
+        sql.gsub(STRING_COMPARISON) do
+          match = ::Regexp.last_match
+          dtype = @schema[match[:column]]
+          next match[0] unless dtype && Dtypes.in_group?(dtype, :string)
+
+          "LOWER(#{match[:column]}) #{match[:op].upcase} '#{match[:value].downcase}'"
+        end
+      end
+
+      # `ts >= '2024-01-01'` and `day > today`, `ts >= now - interval '7 days'` become CASTs, as do
+      # `'2024-01-01' <= ts` and `ts BETWEEN '2024-01-01' AND '2024-02-01'`, but only when the column
+      # is known to be temporal; anything else passes through as SQL
+      def translate_dates(sql)
+        sql = sql.gsub(DATE_BETWEEN) do
+          match = ::Regexp.last_match
+          low = temporal_literal(match[:column], match[:low])
+          next match[0] unless low
+
+          "#{match[:column]} #{match[:not] && 'NOT '}BETWEEN #{low} AND #{temporal_literal(match[:column], match[:high])}"
+        end
+
+        sql = sql.gsub(/#{COMPARISON}(?:#{DATE_LITERAL}|#{RELATIVE_DATE})/o) do
+          match = ::Regexp.last_match
+          value = match[:date] || relative_time(match).strftime("%Y-%m-%d %H:%M:%S")
+          literal = temporal_literal(match[:column], value)
+          literal ? "#{match[:column]} #{match[:op]} #{literal}" : match[0]
+        end
+
+        sql.gsub(REVERSED_DATE_COMPARISON) do
+          match = ::Regexp.last_match
+          literal = temporal_literal(match[:column], match[:date])
+          literal ? "#{literal} #{match[:op]} #{match[:column]}" : match[0]
+        end
+      end
+
+      # The CAST for comparing value with column, or nil when the column isn't Date/Datetime
+      def temporal_literal(column, value)
+        dtype = @schema[column]
+        return unless dtype
+
+        if Dtypes.kind?(dtype, Polars::Date)
+          "CAST('#{value[0, 10]}' AS DATE)"
+        elsif Dtypes.kind?(dtype, Polars::Datetime)
+          "CAST('#{timestamp_literal(value)}' AS TIMESTAMP)"
+        end
+      end
+
+      # Pads to "YYYY-MM-DD HH:MM:SS", which every timestamp cast accepts
+      def timestamp_literal(value)
+        value = value.tr("T", " ")
+        value += " 00:00" if value.length == 10
+        value += ":00" if value.length == 16
+        value
+      end
+
+      def relative_time(match)
+        midnight = Time.new(@now.year, @now.month, @now.day)
+        time = case match[:keyword].downcase
+               when "now" then @now
+               when "today" then midnight
+               when "yesterday" then midnight - INTERVAL_SECONDS["day"]
+               end
+        return time unless match[:sign]
+
+        offset = match[:amount].to_i * INTERVAL_SECONDS.fetch(match[:unit].downcase)
+        match[:sign] == "-" ? time - offset : time + offset
+      end
+
       def translate_quotes(sql)
         # Convert double-quoted string literals to single-quoted (SQL standard)
         # This must be done before operator translation to avoid conflicts with ==
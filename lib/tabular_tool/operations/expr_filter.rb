@@ -0,0 +1,294 @@
+# frozen_string_literal: true
+
+module TabularTool
+  module Operations
+    # Parses a small expression language straight into a Polars expression, for filters
+    # that compare columns with each other or need arithmetic, which --where's SQL makes awkward
+    #
+    #   expr    := or
+    #   or      := and ("||" and)*
+    #   and     := not ("&&" not)*
+    #   not     := "!" not | compare
+    #   compare := sum (("==" | "!=" | ">" | ">=" | "<" | "<=") sum)?
+    #   sum     := product (("+" | "-") product)*
+    #   product := unary (("*" | "/" | "%") unary)*
+    #   unary   := "-" unary | postfix
+    #   postfix := primary ("." METHOD "(" args? ")")*
+    #   primary := NUMBER | STRING | true | false | null | IDENT | col("name")
+    #            | FUNCTION "(" args? ")" | "(" expr ")"
+    #
+    # Bare identifiers are column names; col("...") reaches names with spaces or punctuation.
+    class ExprFilter
+      FUNCTIONS = %w[col abs round coalesce].freeze
+      METHODS = %w[is_null is_not_null abs round len lower upper contains starts_with ends_with].freeze
+
+      TOKEN = /\s*(?:(?<number>\d+(?:\.\d+)?)|(?<string>"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*')|
+                    (?<ident>[A-Za-z_]\w*)|(?<op>==|!=|>=|<=|&&|\|\||[-+*\/%<>!().,]))/x
+
+      Token = Struct.new(:type, :value, :position)
+
+      # Column names referenced by the expression, filled in by parse
+      attr_reader :columns
+
+      def self.call(df, expression:)
+        parser = new(expression)
+        filter = parser.parse
+        Operations.require_columns(df, parser.columns, option: "--expr-filter")
+        df.filter(filter)
+      end
+
+      def initialize(expression)
+        @expression = expression
+        @tokens = tokenize(expression)
+        @index = 0
+        @columns = []
+      end
+
+      def parse
+        expr = parse_or
+        token = peek
+        raise_error("Unexpected #{token.value.inspect}", token) if token
+        expr
+      end
+
+      private
+
+      def tokenize(source)
+        tokens = []
+        position = 0
+        while position < source.length
+          break if source[position..].strip.empty?
+
+          match = TOKEN.match(source, position)
+          if match.nil? || match.begin(0) != position
+            start = position + (source[position..] =~ /\S/).to_i
+            raise Error, "Invalid --expr-filter: unexpected character #{source[start].inspect} at position #{start + 1}"
+          end
+
+          type = [:number, :string, :ident, :op].find { |name| match[name] }
+          tokens << Token.new(type, match[type], match.begin(type))
+          position = match.end(0)
+        end
+        tokens
+      end
+
+      def parse_or
+        expr = parse_and
+        expr |= parse_and while accept("||")
+        expr
+      end
+
+      def parse_and
+        expr = parse_not
+        expr &= parse_not while accept("&&")
+        expr
+      end
+
+      def parse_not
+        return parse_not.not_ if accept("!")
+
+        parse_compare
+      end
+
+      def parse_compare
+        left = parse_sum
+        op = accept("==", "!=", ">=", "<=", ">", "<")
+        return left unless op
+
+        right = parse_sum
+        case op
+        when "==" then left == right
+        when "!=" then left != right
+        when ">=" then left >= right
+        when "<=" then left <= right
+        when ">"  then left > right
+        when "<"  then left < right
+        end
+      end
+
+      def parse_sum
+        expr = parse_product
+        while (op = accept("+", "-"))
+          right = parse_product
+          expr = op == "+" ? expr + right : expr - right
+        end
+        expr
+      end
+
+      def parse_product
+        expr = parse_unary
+        while (op = accept("*", "/", "%"))
+          right = parse_unary
+          expr = case op
+                 when "*" then expr * right
+                 when "/" then expr / right
+                 else expr % right
+                 end
+        end
+        expr
+      end
+
+      def parse_unary
+        return Polars.lit(0) - parse_unary if accept("-")
+
+        parse_postfix
+      end
+
+      def parse_postfix
+        expr = parse_primary
+        while accept(".")
+          token = advance("method name")
+          raise_error("Unsupported method #{token.value.inspect} (supported: #{METHODS.join(', ')})", token) unless
+            token.type == :ident && METHODS.include?(token.value)
+
+          expr = apply_method(expr, token.value, parse_arguments)
+        end
+        expr
+      end
+
+      def parse_primary
+        token = advance("a value")
+
+        case token.type
+        when :number
+          Polars.lit(number(token.value))
+        when :string
+          Polars.lit(unquote(token.value))
+        when :ident
+          case token.value
+          when "true" then Polars.lit(true)
+          when "false" then Polars.lit(false)
+          when "null" then Polars.lit(nil)
+          else
+            return column(token.value) unless peek&.value == "("
+            raise_error("Unsupported function #{token.value.inspect} (supported: #{FUNCTIONS.join(', ')})", token) unless
+              FUNCTIONS.include?(token.value)
+
+            apply_function(token, parse_arguments)
+          end
+        else
+          raise_error("Unexpected #{token.value.inspect}", token) unless token.value == "("
+
+          expr = parse_or
+          expect(")")
+          expr
+        end
+      end
+
+      def parse_arguments
+        expect("(")
+        args = []
+        unless accept(")")
+          args << argument
+          args << argument while accept(",")
+          expect(")")
+        end
+        args
+      end
+
+      # Lone literals stay raw Ruby values so col("name"), contains("x") and round(x, 2) can use them
+      def argument
+        token = peek
+        if [:string, :number].include?(token&.type) && [",", ")"].include?(@tokens[@index + 1]&.value)
+          @index += 1
+          return token.type == :string ? unquote(token.value) : number(token.value)
+        end
+
+        parse_or
+      end
+
+      def apply_function(token, args)
+        case token.value
+        when "col"
+          raise_error("col() takes one quoted column name", token) unless args.length == 1 && args[0].is_a?(String)
+          column(args[0])
+        when "abs"
+          check_arity(token, args, 1)
+          expression(args[0]).abs
+        when "round"
+          check_arity(token, args, 1, 2)
+          raise_error("round() digits must be a whole number", token) unless args[1].nil? || args[1].is_a?(Integer)
+          expression(args[0]).round(args[1] || 0)
+        when "coalesce"
+          raise_error("coalesce() needs at least two arguments", token) if args.length < 2
+          Polars.coalesce(args.map { |arg| expression(arg) })
+        end
+      end
+
+      def apply_method(expr, name, args)
+        case name
+        when "is_null" then expr.is_null
+        when "is_not_null" then expr.is_not_null
+        when "abs" then expr.abs
+        when "round"
+          raise Error, "Invalid --expr-filter: .round() takes a whole number of digits" unless args.all?(Integer)
+          expr.round(args[0] || 0)
+        when "len" then expr.str.len_chars
+        when "lower" then expr.str.to_lowercase
+        when "upper" then expr.str.to_uppercase
+        when "contains" then expr.str.contains(string_argument(name, args), literal: true)
+        when "starts_with" then expr.str.starts_with(string_argument(name, args))
+        when "ends_with" then expr.str.ends_with(string_argument(name, args))
+        end
+      end
+
+      def string_argument(name, args)
+        raise Error, "Invalid --expr-filter: .#{name}() takes one quoted string" unless args.length == 1 && args[0].is_a?(String)
+
+        args[0]
+      end
+
+      def check_arity(token, args, *allowed)
+        return if allowed.include?(args.length)
+
+        raise_error("#{token.value}() takes #{allowed.join(' or ')} argument(s), got #{args.length}", token)
+      end
+
+      def column(name)
+        @columns << name unless @columns.include?(name)
+        Polars.col(name)
+      end
+
+      def expression(arg)
+        arg.is_a?(Polars::Expr) ? arg : Polars.lit(arg)
+      end
+
+      def number(value)
+        value.include?(".") ? value.to_f : value.to_i
+      end
+
+      def unquote(value)
+        value[1..-2].gsub(/\\(.)/, '\1')
+      end
+
+      def peek
+        @tokens[@index]
+      end
+
+      def advance(expected)
+        token = @tokens[@index]
+        raise Error, "Invalid --expr-filter: expected #{expected} at end of expression" unless token
+
+        @index += 1
+        token
+      end
+
+      def accept(*values)
+        token = peek
+        return nil unless token && token.type == :op && values.include?(token.value)
+
+        @index += 1
+        token.value
+      end
+
+      def expect(value)
+        token = advance(value.inspect)
+        raise_error("Expected #{value.inspect} but found #{token.value.inspect}", token) unless token.value == value
+      end
+
+      def raise_error(message, token)
+        raise Error, "Invalid --expr-filter: #{message} at position #{token.position + 1} in #{@expression.inspect}"
+      end
+    end
+  end
+end
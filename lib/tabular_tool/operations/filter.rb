@@ -1,14 +1,131 @@
 # frozen_string_literal: true
 
+require "strscan"
 require_relative "dsl_to_sql"
+require_relative "filter_diagnosis"
 
 module TabularTool
   module Operations
     module Filter
-      def self.call(df, expression:)
-        sql = DslToSql.new(expression).translate
-        df.filter(Polars.sql_expr(sql))
+      # Key files read as plain lists, one value per line with no header
+      KEY_LIST_EXTENSIONS = [".txt", ".lst", ""].freeze
+
+      # Names a --where expression compares or calls methods on; SQL keywords and literals are skipped
+      REFERENCE = /(?<column>\b[A-Za-z_]\w*)(?:\s*(?:==|!=|>=|<=|<>|=|>|<)|\s+(?:IS|LIKE|NOT|IN|BETWEEN)\b|
+                   \.(?:contains|starts_with|ends_with|is_null|is_not_null)\()/ix
+      KEYWORDS = %w[and or not is null true false like in between now today yesterday interval].freeze
+
+      def self.call(df, expression:, ignore_case: false)
+        Operations.require_columns(df, referenced_columns(expression), option: "--where")
+        if (problem = FilterDiagnosis.unquoted_literal(expression, columns: df.columns))
+          raise Error, FilterDiagnosis.message(expression, *problem)
+        end
+
+        sql = DslToSql.new(expression, schema: df.schema, ignore_case: ignore_case).translate
+        begin
+          condition = Polars.sql_expr(sql)
+        rescue Polars::Error => e
+          # Polars' position refers to the translated SQL, so find the mistake in what the user typed
+          problem = FilterDiagnosis.find(expression)
+          raise Error, FilterDiagnosis.message(expression, *problem) if problem
+
+          raise Error, "Invalid --where: #{e.message.lines.first.strip}\n  #{expression}"
+        end
+        df.filter(condition)
+      end
+
+      def self.referenced_columns(expression)
+        unquoted = expression.gsub(/'(?:[^']|'')*'|"[^"]*"/, "''")
+        unquoted.scan(REFERENCE).flatten.reject { |name| KEYWORDS.include?(name.downcase) }.uniq
+      end
+
+      # --where-null, --where-not-null and --where-in, ANDed together
+      # in_values pairs a column with raw strings, which are parsed to the column's dtype
+      def self.membership(df, null: nil, not_null: nil, in_values: nil)
+        schema = df.schema
+        check = lambda do |col, flag|
+          Operations.require_columns(df, [col], option: flag)
+          Polars.col(col)
+        end
+
+        conditions = null.to_a.map { |col| check.(col, "--where-null").is_null }
+        conditions += not_null.to_a.map { |col| check.(col, "--where-not-null").is_not_null }
+        in_values.to_a.each do |col, values|
+          conditions << check.(col, "--where-in").is_in(typed_values(col, values, schema[col]))
+        end
+        conditions.empty? ? df : df.filter(conditions.reduce(:&))
+      end
+
+      # "NYC, LA,'Washington, D.C.'" => ["NYC", "LA", "Washington, D.C."]
+      def self.split_values(list)
+        scanner = StringScanner.new(list)
+        values = []
+        loop do
+          scanner.skip(/\s*/)
+          values << if scanner.scan(/"((?:[^"\\]|\\.)*)"|'((?:[^'\\]|\\.)*)'/)
+                      (scanner[1] || scanner[2]).gsub(/\\(.)/, '\1')
+                    else
+                      scanner.scan(/[^,]*/).strip
+                    end
+          scanner.skip(/\s*/)
+          break if scanner.eos?
+          raise Error, "Invalid --where-in value list: expected ',' at position #{scanner.pos + 1} in #{list.inspect}" unless
+            scanner.skip(/,/)
+        end
+        values
+      end
+
+      # Keeps (or, with keep: false, drops) rows whose column value is in keys
+      # An is_in filter rather than a join, so a lazy scan stays lazy and streams
+      def self.by_keys(df, column:, keys:, keep: true)
+        Operations.require_columns(df, [column], option: "--key-column")
+        schema = df.schema
+
+        keys = cast_keys(keys, schema[column], column)
+        matches = Polars.col(column).is_in(keys)
+        # A null key is never in the list, so dropping keys leaves those rows alone
+        df.filter(keep ? matches : matches.not_ | Polars.col(column).is_null)
+      end
+
+      # Distinct keys from a text file (one per line) or a single-column CSV/TSV/Parquet/JSON file
+      def self.read_keys(path, column: nil)
+        raise Error, "Key file not found: #{path}" unless File.exist?(path)
+
+        if KEY_LIST_EXTENSIONS.include?(File.extname(path.sub(/\.(gz|zst)$/i, "")).downcase)
+          lines = Formats.open_raw(path) { |io| io.each_line.map { |line| line.force_encoding(Encoding::UTF_8).strip } }
+          return Polars::Series.new("key", lines.reject(&:empty?).uniq, dtype: Polars::String)
+        end
+
+        keys = Formats.read(path, streaming: false)
+        keys = keys.select(column) if column && keys.columns.length > 1 && keys.columns.include?(column)
+        raise Error, "Key file #{path} must have a single column (found #{keys.columns.join(', ')})" unless keys.width == 1
+
+        keys.to_series.drop_nulls.unique(maintain_order: true)
+      end
+
+      def self.cast_keys(keys, dtype, column)
+        return keys if Dtypes.name(keys.dtype) == Dtypes.name(dtype)
+
+        keys.cast(Polars::String).cast(dtype, strict: true)
+      rescue Polars::Error
+        raise Error, "Keys don't all parse as #{Dtypes.name(dtype)}, the dtype of #{column}"
+      end
+      private_class_method :cast_keys
+
+      def self.typed_values(col, values, dtype)
+        if Dtypes.kind?(dtype, Polars::Boolean)
+          values.map do |value|
+            next value.downcase == "true" if ["true", "false"].include?(value.downcase)
+
+            raise Error, "--where-in value #{value.inspect} for #{col} is not a Boolean"
+          end
+        else
+          Polars::Series.new(col, values, dtype: Polars::String).cast(dtype, strict: true)
+        end
+      rescue Polars::Error
+        raise Error, "--where-in values for #{col} don't all parse as #{Dtypes.name(dtype)}: #{values.join(', ')}"
       end
+      private_class_method :typed_values
     end
   end
 end
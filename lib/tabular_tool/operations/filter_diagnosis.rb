@@ -0,0 +1,106 @@
+# frozen_string_literal: true
+
+module TabularTool
+  module Operations
+    # Finds where a --where expression goes wrong, for an error that points at it:
+    #
+    #   Invalid --where: two comparison operators in a row
+    #     age > > 30
+    #           ^
+    #     hint: compare one column with one value, e.g. age > 30
+    module FilterDiagnosis
+      TOKEN = /(?<string>'(?:[^']|'')*'|"[^"]*")|(?<open_string>['"])|(?<number>\d+(?:\.\d+)?)|
+               (?<ident>[A-Za-z_]\w*)|(?<op>===|=>|=<|==|!=|>=|<=|<>|&&|\|\||[=<>!()+\-*\/%.,])|(?<other>\S)/x
+      COMPARISONS = ["==", "!=", ">=", "<=", "<>", "=", ">", "<"].freeze
+      CONNECTIVES = ["&&", "||", "and", "or"].freeze
+      # Bare words that are values in their own right, not unquoted strings
+      VALUE_WORDS = %w[null true false now today yesterday interval not].freeze
+
+      Token = Struct.new(:type, :value, :position)
+
+      # A bare word compared against a column that doesn't exist, which SQL would read as a column name
+      # Checked before running the filter, since it otherwise fails late with a missing-column error
+      def self.unquoted_literal(expression, columns:)
+        tokens = tokenize(expression)
+        (tokens + [nil]).each_cons(3) do |op, word, following|
+          next unless op.type == :op && COMPARISONS.include?(op.value) && word.type == :ident
+          next if columns.include?(word.value) || VALUE_WORDS.include?(word.value.downcase)
+          # Function calls and method calls aren't literals
+          next if [".", "("].include?(following&.value)
+
+          return [word.position, "unquoted string #{word.value}", "quote string values: #{quoted(expression, word)}"]
+        end
+        nil
+      end
+
+      # [position, problem, hint] for the first mistake found, or nil
+      def self.find(expression)
+        tokens = tokenize(expression)
+        depth = []
+
+        tokens.each_with_index do |token, i|
+          following = tokens[i + 1]
+          case token.type
+          when :open_string
+            return [token.position, "unterminated string",
+                    "close it with a matching #{token.value}; strings use 'single' or \"double\" quotes"]
+          when :other
+            return [token.position, "unexpected character #{token.value.inspect}", nil]
+          when :op
+            case token.value
+            when "===" then return [token.position, "=== is not an operator", "use == (or =) to compare"]
+            when "=>" then return [token.position, "=> is not an operator", "did you mean >="]
+            when "=<" then return [token.position, "=< is not an operator", "did you mean <="]
+            when "(" then depth << token.position
+            when ")"
+              return [token.position, "unmatched )", "remove it or add the missing ("] if depth.pop.nil?
+            end
+
+            next unless COMPARISONS.include?(token.value)
+
+            if token.value == "=" && following&.value == "="
+              return [following.position, "= = is not an operator", "use == (or =) to compare"]
+            end
+            if following.nil? || following.value == ")" || CONNECTIVES.include?(following.value.downcase)
+              return [token.position + token.value.length, "missing value after #{token.value}", nil]
+            end
+            if following.type == :op && COMPARISONS.include?(following.value)
+              return [following.position, "two comparison operators in a row",
+                      "compare one column with one value, e.g. age > 30"]
+            end
+          end
+        end
+
+        return [depth.last, "unclosed (", "add the missing )"] if depth.any?
+
+        nil
+      end
+
+      def self.message(expression, position, problem, hint)
+        lines = ["Invalid --where: #{problem}", "  #{expression}", "  #{' ' * position}^"]
+        lines << "  hint: #{hint}" if hint
+        lines.join("\n")
+      end
+
+      def self.tokenize(expression)
+        tokens = []
+        expression.scan(TOKEN) do
+          match = ::Regexp.last_match
+          type = [:string, :open_string, :number, :ident, :op, :other].find { |name| match[name] }
+          tokens << Token.new(type, match[type], match.begin(type))
+          # Everything after an unterminated quote belongs to the broken string
+          break if type == :open_string
+        end
+        tokens
+      end
+
+      # The rest of the comparison's right-hand side up to the next connective or paren, quoted
+      def self.quoted(expression, word)
+        rest = expression[word.position..]
+        value = rest[/\A.*?(?=\s*(?:&&|\|\||\)|\b(?:and|or)\b|\z))/i]
+        "'#{value}'"
+      end
+      private_class_method :tokenize, :quoted
+    end
+  end
+end
@@ -3,13 +3,66 @@
 module TabularTool
   module Operations
     module Lint
-      def self.call(df, unique_columns: nil)
+      # ASCII control characters other than tab, newline and carriage return
+      CONTROL_CHARACTERS = '[\x00-\x08\x0B\x0C\x0E-\x1F\x7F]'
+
+      # Percentage of nulls at which a column is reported as mostly null
+      DEFAULT_HIGH_NULL_THRESHOLD = 90.0
+
+      # Rows inspected per string column when looking for mis-typed values (nil scans everything)
+      TYPE_SAMPLE_ROWS = 1000
+
+      # Fraction of values that must parse for a string column to count as mis-typed
+      MISTYPED_THRESHOLD = 0.95
+
+      # Tukey's fences: values beyond this many interquartile ranges outside the quartiles are outliers
+      IQR_MULTIPLIER = 1.5
+
+      # Parsers tried in order; each returns null where the value doesn't parse
+      STRING_TYPE_PARSERS = {
+        integer: ->(expr) { expr.cast(Polars::Int64, strict: false) },
+        float: ->(expr) { expr.cast(Polars::Float64, strict: false) },
+        date: ->(expr) { expr.str.to_date(strict: false) },
+        boolean: ->(expr) { Polars.when(expr.str.to_lowercase.is_in(["true", "false"])).then(true) },
+      }.freeze
+
+      # Per-row conditions behind the profile counts whose findings list line numbers
+      LINE_CHECKS = {
+        nulls: ->(column) { column.is_null },
+        whitespace: lambda do |column|
+          text = column.cast(Polars::String)
+          text.is_not_null & (text.str.strip_chars != text)
+        end,
+        empty_strings: ->(column) { column == "" },
+        control_characters: ->(column) { column.str.contains(CONTROL_CHARACTERS) },
+      }.freeze
+
+      def self.call(df, unique_columns: nil, high_null_threshold: DEFAULT_HIGH_NULL_THRESHOLD,
+                    type_sample_rows: TYPE_SAMPLE_ROWS, header: nil, encoding: nil, outliers: false, z: nil)
         errors = []
         warnings = []
 
-        check_blank_values(df, warnings)
+        if encoding && encoding[:count] > 0
+          errors << {
+            type: :invalid_utf8,
+            count: encoding[:count],
+            lines: [],
+            violations: encoding[:violations],
+          }
+        end
+
+        check_header(header || df.columns, warnings)
+
+        profile = column_profile(df, sample_rows: type_sample_rows)
+        lines = finding_lines(df, profile)
+        check_blank_values(df, profile, lines, warnings)
         check_duplicate_rows(df, errors)
-        check_whitespace(df, warnings)
+        check_whitespace(df, profile, lines, warnings)
+        check_string_hygiene(df, profile, lines, warnings)
+        check_mistyped_strings(df, profile, warnings, sample_rows: type_sample_rows)
+        column_warnings = check_column_usefulness(df, profile, high_null_threshold)
+        warnings.concat(column_warnings)
+        check_outliers(df, warnings, z: z) if outliers
 
         if unique_columns
           if unique_columns == :all
@@ -29,26 +82,161 @@ module TabularTool
           column_count: df.width,
           errors: errors,
           warnings: warnings,
+          suggested_drop: column_warnings.map { |w| w[:column] },
         }
       end
 
+      # Scans raw bytes for invalid UTF-8 before any reader sees them
+      # Counts every bad line but records the line and file byte offset of only the first `limit`
+      def self.encoding_violations(io, limit: 5)
+        count = 0
+        violations = []
+        offset = 0
+
+        io.each_line.with_index(1) do |line, lineno|
+          text = line.dup.force_encoding(Encoding::UTF_8)
+          unless text.valid_encoding?
+            count += 1
+            if violations.length < limit
+              position = 0
+              text.each_char do |char|
+                break unless char.valid_encoding?
+
+                position += char.bytesize
+              end
+              violations << { line: lineno, byte_offset: offset + position }
+            end
+          end
+          offset += line.bytesize
+        end
+
+        { count: count, violations: violations }
+      end
+
+      # Every row whose key appears more than once (originals included), grouped and sorted by key
+      # row_numbers adds a leading "line" column: the file line number, given the first row is on line_offset
+      def self.duplicate_groups(df, keys:, limit: nil, row_numbers: false, line_offset: 2)
+        groups = df.lazy.with_row_index(name: "line", offset: line_offset)
+          .filter(Polars.len.over(keys) > 1)
+          .sort(keys + ["line"], nulls_last: true)
+        finish_rows(groups, limit: limit, row_numbers: row_numbers)
+      end
+
+      # Rows with a null in any column, in file order
+      def self.null_rows(df, limit: nil, row_numbers: false, line_offset: 2)
+        rows = df.lazy.with_row_index(name: "line", offset: line_offset)
+          .filter(Polars.any_horizontal(df.columns.map { |col| Polars.col(col).is_null }))
+        finish_rows(rows, limit: limit, row_numbers: row_numbers)
+      end
+
+      def self.finish_rows(rows, limit:, row_numbers:)
+        rows = rows.head(limit) if limit
+        rows = rows.drop("line") unless row_numbers
+        rows.collect
+      end
+
       private
 
-      def self.check_blank_values(df, warnings)
-        df.columns.each do |col|
-          null_indices = df.with_row_index.filter(Polars.col(col).is_null).select(["index"])["index"].to_a
-
-          if null_indices.any?
-            warnings << {
-              type: :blank_values,
-              column: col,
-              count: null_indices.length,
-              lines: null_indices.map { |i| i + 2 },  # +2 because index is 0-based and we need to account for header line
-            }
+      # Duplicate, empty, padded and case-colliding column names
+      # `header` is the raw header when available, since Polars renames duplicates on read
+      def self.check_header(header, warnings)
+        positions = header.each_with_index.map { |name, i| [name, i + 1] }
+
+        positions.select { |name, _| name.strip.empty? }.each do |_, position|
+          warnings << { type: :empty_column_name, positions: [position], lines: [1] }
+        end
+
+        positions.select { |name, _| !name.strip.empty? && name != name.strip }.each do |name, position|
+          warnings << { type: :column_name_whitespace, column: name, positions: [position], lines: [1] }
+        end
+
+        named = positions.reject { |name, _| name.strip.empty? }
+        named.group_by { |name, _| name.strip }.each do |name, group|
+          next if group.length < 2
+
+          warnings << { type: :duplicate_column_names, column: name, positions: group.map(&:last), lines: [1] }
+        end
+
+        named.group_by { |name, _| name.strip.downcase }.each_value do |group|
+          variants = group.map { |name, _| name.strip }.uniq
+          next if variants.length < 2
+
+          warnings << { type: :column_name_case, values: variants, positions: group.map(&:last), lines: [1] }
+        end
+      end
+
+      # Counts behind the per-column checks, from one aggregation over every column rather than a
+      # pass per column and check; keys are "<check>:<column position>". Case variants show up as
+      # fewer distinct values once lowercased, and the mis-typed check counts how many of each String
+      # column's first sample_rows non-empty values every parser accepts
+      def self.column_profile(df, sample_rows: TYPE_SAMPLE_ROWS)
+        aggs = df.schema.each_with_index.flat_map { |(col, dtype), i| profile_exprs(col, dtype, i, sample_rows) }
+        return {} if aggs.empty?
+
+        begin
+          df.lazy.select(aggs).collect.row(0, named: true)
+        rescue Polars::Error
+          # One expression at a time, so a column type that breaks a check only loses that check
+          aggs.each_with_object({}) do |agg, profile|
+            profile.merge!(df.lazy.select(agg).collect.row(0, named: true))
+          rescue Polars::Error
+            next
           end
         end
       end
 
+      def self.profile_exprs(col, dtype, i, sample_rows)
+        column = Polars.col(col)
+        exprs = [
+          column.null_count.alias("nulls:#{i}"),
+          column.drop_nulls.n_unique.alias("distinct:#{i}"),
+          column.drop_nulls.first.alias("first:#{i}"),
+        ]
+        return exprs unless Dtypes.in_group?(dtype, :string)
+
+        exprs << LINE_CHECKS[:whitespace].call(column).sum.alias("whitespace:#{i}")
+        return exprs unless Dtypes.kind?(dtype, Polars::String)
+
+        sample = typed_sample(column, sample_rows)
+        exprs + [
+          LINE_CHECKS[:empty_strings].call(column).sum.alias("empty_strings:#{i}"),
+          LINE_CHECKS[:control_characters].call(column).sum.alias("control_characters:#{i}"),
+          column.drop_nulls.str.to_lowercase.n_unique.alias("lowercase_distinct:#{i}"),
+          sample.len.alias("sampled:#{i}"),
+        ] + STRING_TYPE_PARSERS.map { |type, parser| parser.call(sample).is_not_null.sum.alias("parsed_#{type}:#{i}") }
+      end
+
+      # The values the mis-typed check looks at: stripped, non-empty, the first sample_rows of them
+      def self.typed_sample(column, sample_rows)
+        value = column.str.strip_chars
+        value = value.filter(value.is_not_null & (value != ""))
+        sample_rows ? value.head(sample_rows) : value
+      end
+
+      # Line numbers behind each nonzero LINE_CHECKS count, in one more pass over only those columns,
+      # so a wide, sparse file never holds row indices for columns with nothing to report
+      # (+2: 0-based indices, plus the header line)
+      def self.finding_lines(df, profile)
+        aggs = df.columns.each_with_index.flat_map do |col, i|
+          LINE_CHECKS.filter_map do |check, condition|
+            key = "#{check}:#{i}"
+            condition.call(Polars.col(col)).arg_true.implode.alias(key) if profile[key].to_i > 0
+          end
+        end
+        return {} if aggs.empty?
+
+        df.lazy.select(aggs).collect.row(0, named: true).transform_values { |indices| indices.map { |i| i + 2 } }
+      end
+
+      def self.check_blank_values(df, profile, lines, warnings)
+        df.columns.each_with_index do |col, i|
+          count = profile["nulls:#{i}"].to_i
+          next if count.zero?
+
+          warnings << { type: :blank_values, column: col, count: count, lines: lines["nulls:#{i}"] }
+        end
+      end
+
       def self.check_duplicate_rows(df, errors)
         original_height = df.height
         df_with_index = df.with_row_index
@@ -80,26 +268,146 @@ module TabularTool
         end
       end
 
-      def self.check_whitespace(df, warnings)
-        df.columns.each do |col|
-          begin
-            df_indexed = df.with_row_index
+      def self.check_whitespace(df, profile, lines, warnings)
+        df.columns.each_with_index do |col, i|
+          count = profile["whitespace:#{i}"].to_i
+          next if count.zero?
+
+          warnings << { type: :whitespace, column: col, count: count, lines: lines["whitespace:#{i}"] }
+        end
+      end
+
+      # Empty strings (distinct from null), control characters, and values that differ only by case
+      # The profile says which columns have case variants; only those get a group_by to list them
+      def self.check_string_hygiene(df, profile, lines, warnings)
+        df.schema.each_with_index do |(col, dtype), i|
+          next unless Dtypes.kind?(dtype, Polars::String)
+
+          [:empty_strings, :control_characters].each do |type|
+            count = profile["#{type}:#{i}"].to_i
+            next if count.zero?
+
+            warnings << { type: type, column: col, count: count, lines: lines["#{type}:#{i}"] }
+          end
+
+          distinct = profile["distinct:#{i}"]
+          lowercase = profile["lowercase_distinct:#{i}"]
+          next unless distinct && lowercase && lowercase < distinct
 
-            ws_rows = df_indexed.filter(
-              Polars.col(col).is_not_null &
-              (Polars.col(col).cast(Polars::String).str.strip_chars != Polars.col(col).cast(Polars::String)),
+          variants = df.lazy.with_row_index.select(["index", col])
+            .filter(Polars.col(col).is_not_null)
+            .group_by(Polars.col(col).str.to_lowercase.alias("__key"))
+            .agg(
+              Polars.col(col).unique.sort.alias("values"),
+              Polars.col("index").alias("indices"),
             )
+            .filter(Polars.col("values").list.len.gt(1))
+            .collect
 
-            if ws_rows.height > 0
-              lines = ws_rows["index"].to_a.map { |i| i + 2 }  # +2 to account for 0-based index and header line
-              warnings << {
-                type: :whitespace,
-                column: col,
-                count: lines.length,
-                lines: lines,
-              }
-            end
-          rescue Polars::Error
+          warnings << {
+            type: :case_variants,
+            column: col,
+            count: variants.height,
+            lines: variants["indices"].to_a.flatten.map { |i| i + 2 }.sort,
+            values: variants["values"].to_a,
+          }
+        end
+      end
+
+      # String columns whose values almost all parse as numbers, dates or booleans
+      # The profile has the parse counts; only a flagged column is read again, for its offending rows
+      def self.check_mistyped_strings(df, profile, warnings, sample_rows:)
+        df.schema.each_with_index do |(col, dtype), i|
+          next unless Dtypes.kind?(dtype, Polars::String)
+
+          sampled = profile["sampled:#{i}"].to_i
+          next if sampled.zero?
+
+          type = STRING_TYPE_PARSERS.keys.find { |t| profile["parsed_#{t}:#{i}"].to_i >= sampled * MISTYPED_THRESHOLD }
+          next unless type
+
+          values = df.lazy.with_row_index.select(["index", Polars.col(col).str.strip_chars.alias("value")])
+            .filter(Polars.col("value").is_not_null & (Polars.col("value") != ""))
+          values = values.head(sample_rows) if sample_rows
+          offenders = values.filter(STRING_TYPE_PARSERS[type].call(Polars.col("value")).is_null).collect
+
+          warnings << {
+            type: :mistyped_strings,
+            column: col,
+            inferred: type,
+            percent: (profile["parsed_#{type}:#{i}"] * 100.0 / sampled).round(1),
+            sampled: sampled,
+            count: offenders.height,
+            lines: offenders["index"].to_a.map { |i| i + 2 },  # +2 to account for 0-based index and header line
+            values: offenders["value"].to_a.first(5),
+          }
+        end
+      end
+
+      # Numeric values outside 1.5×IQR fences, or more than z standard deviations from the mean
+      # One aggregation computes every column's bounds, then one filter per column finds the offending rows
+      def self.check_outliers(df, warnings, z: nil)
+        columns = df.schema.select { |_, dtype| Dtypes.in_group?(dtype, :numeric) }.keys
+        return if columns.empty? || df.height == 0
+
+        aggs = columns.each_with_index.flat_map do |col, i|
+          value = Polars.col(col).cast(Polars::Float64)
+          if z
+            [value.mean.alias("mean:#{i}"), value.std.alias("std:#{i}")]
+          else
+            [value.quantile(0.25).alias("q1:#{i}"), value.quantile(0.75).alias("q3:#{i}")]
+          end
+        end
+        stats = df.lazy.select(aggs).collect.row(0, named: true)
+
+        columns.each_with_index do |col, i|
+          if z
+            mean = stats["mean:#{i}"]
+            std = stats["std:#{i}"]
+            next if mean.nil? || std.nil?
+
+            lower = mean - z * std
+            upper = mean + z * std
+          else
+            q1 = stats["q1:#{i}"]
+            q3 = stats["q3:#{i}"]
+            next if q1.nil? || q3.nil?
+
+            lower = q1 - IQR_MULTIPLIER * (q3 - q1)
+            upper = q3 + IQR_MULTIPLIER * (q3 - q1)
+          end
+          value = Polars.col(col).cast(Polars::Float64)
+          indices = df.lazy.with_row_index.filter((value < lower) | (value > upper)).select("index").collect["index"].to_a
+          next if indices.empty?
+
+          warnings << {
+            type: :outliers,
+            column: col,
+            count: indices.length,
+            lines: indices.map { |idx| idx + 2 },  # +2 to account for 0-based index and header line
+            lower: lower,
+            upper: upper,
+            method: z ? "#{z}σ" : "#{IQR_MULTIPLIER}×IQR",
+          }
+        end
+      end
+
+      # All-null, constant and mostly-null columns, from the profile's counts
+      def self.check_column_usefulness(df, profile, high_null_threshold)
+        return [] if df.height == 0 || df.width == 0
+
+        df.columns.each_with_index.filter_map do |col, i|
+          next unless profile.key?("nulls:#{i}")
+
+          nulls = profile["nulls:#{i}"]
+          percent = nulls * 100.0 / df.height
+
+          if nulls == df.height
+            { type: :all_null, column: col, count: nulls }
+          elsif profile["distinct:#{i}"] == 1 && nulls == 0 && df.height > 1
+            { type: :constant, column: col, count: df.height, value: profile["first:#{i}"] }
+          elsif percent >= high_null_threshold
+            { type: :high_nulls, column: col, count: nulls, percent: percent.round(1) }
           end
         end
       end
@@ -0,0 +1,225 @@
+# frozen_string_literal: true
+
+require "json"
+
+module TabularTool
+  module Operations
+    # Lint findings in a structured form, rendered as human text or JSON
+    class LintReport
+      Finding = Struct.new(:check, :type, :column, :severity, :count, :message, :details, :lines, :bounds,
+                           keyword_init: true) do
+        def to_h
+          hash = {
+            check: check,
+            type: type.to_s,
+            column: column,
+            severity: severity.to_s,
+            count: count,
+            message: message,
+            lines: lines,
+          }
+          hash[:bounds] = bounds if bounds
+          hash
+        end
+      end
+
+      # Check names accepted by --fail-on, keyed by issue type
+      CHECKS = {
+        blank_values: "nulls",
+        all_null: "nulls",
+        high_nulls: "nulls",
+        duplicate_rows: "duplicates",
+        duplicate_column_values: "duplicates",
+        whitespace: "whitespace",
+        empty_strings: "strings",
+        control_characters: "strings",
+        case_variants: "strings",
+        constant: "constant",
+        mistyped_strings: "types",
+        invalid_utf8: "encoding",
+        outliers: "outliers",
+        empty_column_name: "header",
+        column_name_whitespace: "header",
+        duplicate_column_names: "header",
+        column_name_case: "header",
+      }.freeze
+
+      attr_reader :row_count, :column_count, :findings
+
+      # encoding_checked: --check-encoding ran, so a clean result is reported explicitly
+      # outliers_checked: --show-outliers ran, so "no outliers" is listed among passed checks
+      def initialize(result, unique_columns: nil, encoding_checked: false, outliers_checked: false)
+        @encoding_checked = encoding_checked
+        @outliers_checked = outliers_checked
+        @row_count = result[:row_count]
+        @column_count = result[:column_count]
+        @unique_columns = unique_columns
+        @suggested_drop = result[:suggested_drop] || []
+        @findings = result[:errors].map { |issue| finding(issue, :error) } +
+                    result[:warnings].map { |issue| finding(issue, :warning) }
+      end
+
+      def passed?
+        findings.empty?
+      end
+
+      # Findings that should fail the run: all of them, or only those in the given checks
+      def fatal_findings(fail_on = nil)
+        return findings unless fail_on
+
+        unknown = fail_on - CHECKS.values.uniq
+        raise Error, "Unknown --fail-on check(s): #{unknown.join(', ')}. Valid: #{CHECKS.values.uniq.join(', ')}" if unknown.any?
+
+        findings.select { |f| fail_on.include?(f.check) }
+      end
+
+      def to_json(*)
+        JSON.pretty_generate({
+          row_count: row_count,
+          column_count: column_count,
+          passed: passed?,
+          findings: findings.map(&:to_h),
+        })
+      end
+
+      # Human-readable report; df supplies row excerpts for findings with line numbers
+      def to_text(df = nil)
+        output = []
+        output << "* Row count: #{row_count}"
+        output << "* Column count: #{column_count}"
+        output << ""
+        if @encoding_checked && findings.none? { |f| f.type == :invalid_utf8 }
+          output << "✓ valid UTF-8"
+          output << ""
+        end
+
+        if passed?
+          output << "✓ All checks passed:"
+          output << "  - No blank values"
+          output << "  - No duplicate rows"
+          output << "  - No whitespace issues"
+          output << "  - No empty strings, control characters or case variants"
+          output << "  - No all-null, constant or mostly-null columns"
+          output << "  - No duplicate, empty or padded column names"
+          output << "  - No numeric outliers" if @outliers_checked
+          if @unique_columns
+            cols_desc = @unique_columns == :all ? "all columns" : @unique_columns.join(", ")
+            output << "  - No duplicate values in #{cols_desc}"
+          end
+        else
+          output << "Issues found:"
+          output << ""
+
+          findings.each do |f|
+            output << format_finding(f, df)
+            output << ""
+          end
+
+          if @suggested_drop.any?
+            output << "Suggested: --drop #{@suggested_drop.join(',')}"
+            output << ""
+          end
+        end
+
+        output.join("\n") + "\n"
+      end
+
+      private
+
+      def finding(issue, severity)
+        lines = issue[:lines] || []
+        message, details = describe(issue, lines)
+
+        Finding.new(
+          check: CHECKS.fetch(issue[:type], issue[:type].to_s),
+          type: issue[:type],
+          column: issue[:column],
+          severity: severity,
+          count: issue[:count] || issue[:positions]&.length,
+          message: message,
+          details: details,
+          lines: lines,
+          bounds: issue[:type] == :outliers ? { lower: issue[:lower], upper: issue[:upper], method: issue[:method] } : nil,
+        )
+      end
+
+      # One-line message plus indented detail lines for the text report
+      def describe(issue, lines)
+        lines_detail = "Lines: #{lines.join(', ')}"
+
+        case issue[:type]
+        when :blank_values
+          ["Column '#{issue[:column]}': #{issue[:count]} blank/null values", [lines_detail]]
+        when :duplicate_rows
+          ["#{issue[:count]} duplicate rows found", [lines_detail]]
+        when :duplicate_column_values
+          values_str = issue[:values].map { |v| v.nil? ? "(null)" : v.inspect }.join(", ")
+          ["Column '#{issue[:column]}': #{issue[:count]} duplicate values", ["Values: #{values_str}", lines_detail]]
+        when :whitespace
+          ["Column '#{issue[:column]}': trailing/leading whitespace in #{issue[:count]} values", [lines_detail]]
+        when :all_null
+          ["Column '#{issue[:column]}': all #{issue[:count]} values are null", []]
+        when :constant
+          ["Column '#{issue[:column]}': constant value #{issue[:value].inspect} in all #{issue[:count]} rows", []]
+        when :high_nulls
+          ["Column '#{issue[:column]}': #{issue[:percent]}% null (#{issue[:count]} values)", []]
+        when :empty_column_name
+          ["Header: empty column name at position #{issue[:positions].join(', ')}", []]
+        when :column_name_whitespace
+          ["Header: column name #{issue[:column].inspect} has leading/trailing spaces (position #{issue[:positions].join(', ')})", []]
+        when :duplicate_column_names
+          ["Header: duplicate column name #{issue[:column].inspect} at positions #{issue[:positions].join(', ')}", []]
+        when :column_name_case
+          ["Header: column names differ only by case: #{issue[:values].map(&:inspect).join(' / ')} " \
+           "(positions #{issue[:positions].join(', ')})", []]
+        when :mistyped_strings
+          message = "Column '#{issue[:column]}': probably #{issue[:inferred]} stored as string " \
+                    "(#{issue[:percent]}% of #{issue[:sampled]} values parse)"
+          details = issue[:values].any? ? ["Non-conforming: #{issue[:values].map(&:inspect).join(', ')}", lines_detail] : []
+          [message, details]
+        when :outliers
+          ["Column '#{issue[:column]}': #{issue[:count]} outliers outside [#{issue[:lower].round(4)}, " \
+           "#{issue[:upper].round(4)}] (#{issue[:method]})", [lines_detail]]
+        when :invalid_utf8
+          where = issue[:violations].map { |v| "line #{v[:line]} (byte #{v[:byte_offset]})" }
+          ["Invalid UTF-8 on #{issue[:count]} lines; needs transcoding", ["First at: #{where.join(', ')}"]]
+        when :empty_strings
+          ["Column '#{issue[:column]}': #{issue[:count]} empty strings (not null)", [lines_detail]]
+        when :control_characters
+          ["Column '#{issue[:column]}': control characters in #{issue[:count]} values", [lines_detail]]
+        when :case_variants
+          values_str = issue[:values].map { |group| group.map(&:inspect).join(" / ") }.join(", ")
+          ["Column '#{issue[:column]}': #{issue[:count]} values with inconsistent casing", ["Values: #{values_str}", lines_detail]]
+        else
+          ["#{issue[:type]}: #{issue[:message]}", []]
+        end
+      end
+
+      def format_finding(finding, df)
+        symbol = finding.severity == :error ? "✗" : "⚠"
+        message = "#{symbol} #{finding.message}"
+        finding.details.each { |detail| message += "\n  #{detail}" }
+
+        # Add row data for issues with line numbers (limit to first 5 to avoid overwhelming output)
+        lines = finding.lines
+        if df && lines.any?
+          display_lines = lines.take(5)
+          message += "\n"
+          display_lines.each do |line_num|
+            row_index = line_num - 2  # Convert file line number to 0-based row index (accounting for header)
+            if row_index >= 0 && row_index < df.height
+              row = df.slice(row_index, 1)
+              row_data = df.columns.map { |col| "#{col}=#{row[col][0].inspect}" }.join(", ")
+              message += "\n  Row #{line_num}: #{row_data}"
+            end
+          end
+          if lines.length > 5
+            message += "\n  ... and #{lines.length - 5} more rows"
+          end
+        end
+
+        message
+      end
+    end
+  end
+end
@@ -0,0 +1,68 @@
+# frozen_string_literal: true
+
+module TabularTool
+  module Operations
+    # Aggregates rows into fixed time buckets: the timestamp column is truncated to `every`
+    # ("1h", "15m", "1d", ...) and the aggregations run per bucket (and per `by` group)
+    module Resample
+      AGGREGATIONS = %w[count sum mean min max median n_unique first last].freeze
+
+      # "count(),sum(bytes)" => [Polars.len.alias("count"), Polars.col("bytes").sum.alias("sum_bytes")]
+      def self.parse_aggregations(spec)
+        calls = spec.scan(/\s*(\w+)\(\s*([^)]*?)\s*\)\s*(?:,|\z)/)
+        raise Error, "Invalid --agg: #{spec} (e.g. \"count(),sum(bytes)\")" if calls.empty?
+
+        calls.map do |name, column|
+          raise Error, "Unknown aggregation #{name}() in --agg. Valid: #{AGGREGATIONS.join(', ')}" unless AGGREGATIONS.include?(name)
+
+          if name == "count"
+            column.empty? ? Polars.len.alias("count") : Polars.col(column).count.alias("count_#{column}")
+          else
+            raise Error, "#{name}() in --agg needs a column" if column.empty?
+
+            Polars.col(column).public_send(name).alias("#{name}_#{column}")
+          end
+        end
+      end
+
+      # fill_gaps adds a row for every empty bucket between the first and last one seen;
+      # counts are 0 there and the other aggregations null
+      def self.call(df, column:, every:, aggregations:, by: [], fill_gaps: false)
+        lf = df.lazy
+        dtype = lf.schema[column]
+        raise Error, "Unknown column in resample: #{column}" unless dtype
+        unless Dtypes.kind?(dtype, Polars::Date) || Dtypes.kind?(dtype, Polars::Datetime)
+          raise Error, "resample needs a Date or Datetime column, but #{column} is #{Dtypes.name(dtype)}. " \
+                       "For CSV input try --parse-dates"
+        end
+
+        keys = [column] + by
+        result = lf.with_columns(Polars.col(column).dt.truncate(every))
+          .group_by(keys)
+          .agg(aggregations)
+          .sort(keys)
+          .collect
+        return result if !fill_gaps || result.height.zero?
+
+        buckets = bucket_range(result[column].min, result[column].max, every, dtype).to_frame(column)
+        if by.any?
+          buckets = buckets.join(result.select(by).unique(maintain_order: true), how: "cross")
+        end
+
+        counts = result.columns.select { |col| col == "count" || col.start_with?("count_", "n_unique_") }
+        buckets.join(result, on: keys, how: "left")
+          .with_columns(counts.map { |col| Polars.col(col).fill_null(0) })
+          .sort(keys)
+      end
+
+      def self.bucket_range(first, last, every, dtype)
+        if Dtypes.kind?(dtype, Polars::Datetime)
+          Polars.datetime_range(first, last, every, time_unit: dtype.time_unit, time_zone: dtype.time_zone, eager: true)
+        else
+          Polars.date_range(first, last, every, eager: true)
+        end
+      end
+      private_class_method :bucket_range
+    end
+  end
+end
@@ -1,38 +1,124 @@
 # frozen_string_literal: true
 
+require "tmpdir"
+
 module TabularTool
   module Operations
     module Sort
-      def self.call(df, keys:, reverse: false, ignore_case: false)
-        keys = Array(keys)
-
-        if ignore_case
-          df_with_lower = df.clone
-
-          keys.each do |key|
-            begin
-              df_with_lower = df_with_lower.with_columns(
-                Polars.col(key).cast(Polars::String).str.to_lowercase.alias("__#{key}_lower__"),
-              )
-            rescue Polars::Error
-            end
-          end
+      # A ":asc" or ":desc" suffix sets one key's direction; unsuffixed keys follow reverse
+      KEY_DIRECTION = /:(asc|desc)\z/i
+
+      def self.call(df, keys:, reverse: false, ignore_case: false, nulls_last: nil)
+        keys, descending = parse_keys(keys, reverse)
+        Operations.require_columns(df, keys, option: "-k/--key")
+
+        df.sort(sort_exprs(df.schema, keys, ignore_case), **sort_options(descending, nulls_last))
+      end
+
+      # Sort for inputs larger than memory. The input plan runs once, streaming into a temporary
+      # Parquet file; sorted runs are cut from that file and spilled alongside it, then merged range
+      # by range: splitters sampled from the runs cut the first key into ranges of about one run's
+      # size. A run is sorted on that key, so each range is one contiguous slice of it, found from a
+      # single count pass over the key; a range gathers its slice from every run and is sorted on its
+      # own, and the sorted ranges are yielded in order as one LazyFrame. The temp files are removed
+      # once the block returns.
+      def self.external(df, runs:, keys:, reverse: false, ignore_case: false, nulls_last: nil, temp_dir: nil)
+        keys, descending = parse_keys(keys, reverse)
+        lf = df.lazy
+        Operations.require_columns(lf, keys, option: "-k/--key")
+        exprs = sort_exprs(lf.schema, keys, ignore_case)
+        options = sort_options(descending, nulls_last)
+
+        Dir.mktmpdir("tt-sort", temp_dir) do |dir|
+          input_path = File.join(dir, "input.parquet")
+          lf.sink_parquet(input_path)
+          input = Polars.scan_parquet(input_path)
+          # Answered from the footer, without reading the rows
+          total = input.select(Polars.len.alias("rows")).collect["rows"][0]
+          return yield(input.sort(exprs, **options)) if total.zero?
 
-          sort_keys = keys.map do |key|
-            df_with_lower.columns.include?("__#{key}_lower__") ? "__#{key}_lower__" : key
+          run_rows = (total.to_f / runs).ceil
+          run_paths = (0...total).step(run_rows).each_with_index.map do |offset, i|
+            path = File.join(dir, "run-#{i}.parquet")
+            input.slice(offset, run_rows).sort(exprs, **options).sink_parquet(path)
+            path
           end
+          File.delete(input_path)
 
-          sorted = reverse ? df_with_lower.sort(sort_keys, descending: true) : df_with_lower.sort(sort_keys)
-          temp_cols = df_with_lower.columns.select { |c| c.start_with?("__") && c.end_with?("_lower__") }
-          sorted.drop(temp_cols)
-        else
-          if reverse
-            df.sort(keys, descending: true)
-          else
-            df.sort(keys)
+          ranges = merge_ranges(run_paths, exprs.first, run_rows, descending.first, nulls_last)
+          slices = run_paths.map { |path| range_slices(path, ranges) }
+          range_paths = ranges.each_index.map do |i|
+            path = File.join(dir, "range-#{i}.parquet")
+            parts = run_paths.zip(slices).map { |run, bounds| Polars.scan_parquet(run).slice(*bounds[i]) }
+            Polars.concat(parts).sort(exprs, **options).sink_parquet(path)
+            path
           end
+
+          yield Polars.concat(range_paths.map { |path| Polars.scan_parquet(path) })
         end
       end
+
+      def self.parse_keys(keys, reverse)
+        Array(keys).map do |key|
+          direction = key[KEY_DIRECTION, 1]
+          direction ? [key.sub(KEY_DIRECTION, ""), direction.casecmp?("desc")] : [key, reverse ? true : false]
+        end.transpose
+      end
+
+      # Only string keys are lowercased, and only inside the sort expression, so the output keeps its casing
+      def self.sort_exprs(schema, keys, ignore_case)
+        return keys.map { |key| Polars.col(key) } unless ignore_case
+
+        string_keys = keys.select { |key| Dtypes.in_group?(schema[key], :string) }
+        if string_keys.empty?
+          Log.warn "-i/--ignore-case has no effect: #{keys.join(', ')} " \
+                   "#{keys.length == 1 ? 'is not a string column' : 'are not string columns'}"
+        end
+
+        keys.map do |key|
+          string_keys.include?(key) ? Polars.col(key).cast(Polars::String).str.to_lowercase : Polars.col(key)
+        end
+      end
+
+      def self.sort_options(descending, nulls_last)
+        # nil keeps Polars' default null placement (first)
+        options = { descending: descending }
+        options[:nulls_last] = nulls_last unless nulls_last.nil?
+        options
+      end
+
+      # Filters on the first key that split the runs' rows into consecutive ranges, in output order.
+      # Each range is (previous splitter, splitter], so rows tied on the first key share a range
+      # and the later keys still order them. Nulls get a range of their own at the start or end.
+      def self.merge_ranges(run_paths, key, run_rows, descending, nulls_last)
+        count = run_paths.length
+        step = [run_rows / count, 1].max
+        samples = run_paths.map do |path|
+          Polars.scan_parquet(path).select(key.alias("key")).drop_nulls.gather_every(step).collect["key"]
+        end
+        sorted = Polars.concat(samples).sort.to_a
+        splitters = sorted.each_slice([sorted.length / count, 1].max).map(&:last)[0...-1].uniq
+
+        ranges = [nil, *splitters, nil].each_cons(2).map do |low, high|
+          condition = key.is_not_null
+          condition &= key > low unless low.nil?
+          condition &= key <= high unless high.nil?
+          condition
+        end
+        ranges.reverse! if descending
+        nulls_last ? ranges + [key.is_null] : [key.is_null] + ranges
+      end
+
+      # [offset, length] of each range within a sorted run, from one pass counting the key's
+      # rows per range; the ranges are in the run's own order, so the slices are consecutive
+      def self.range_slices(path, ranges)
+        counts = Polars.scan_parquet(path)
+          .select(ranges.each_with_index.map { |condition, i| condition.sum.alias("range:#{i}") })
+          .collect.row(0)
+        offsets = counts.each_with_object([0]) { |count, starts| starts << starts.last + count }
+        offsets.zip(counts).first(counts.length)
+      end
+      private_class_method :parse_keys, :sort_exprs, :sort_options, :merge_ranges, :range_slices
     end
   end
 end
@@ -1,8 +1,14 @@
 # frozen_string_literal: true
 
+require "date"
+
 module TabularTool
   module Operations
     module Stats
+      # Placeholder for statistics the fast path doesn't compute
+      UNAVAILABLE = "n/a"
+      FAST_STATISTICS = ["count", "null_count", "mean", "std", "min", "max", "median"].freeze
+
       def self.call(df, columns: nil)
         df = df.select(columns) if columns
 
@@ -15,9 +21,128 @@ module TabularTool
         }
       end
 
+      # One hash per column from a describe-shaped frame, for JSON output
+      # Percentile rows ("25%") become p-keys ("p25"); unavailable fast-path values become nil
+      def self.records(stats_df)
+        # The label column comes first; describe's name for it differs across Polars versions
+        keys = stats_df[stats_df.columns.first].to_a.map { |stat| stat.end_with?("%") ? "p#{stat.delete_suffix('%')}" : stat }
+
+        stats_df.columns.drop(1).map do |col|
+          values = stats_df[col].to_a.map { |value| value == UNAVAILABLE ? nil : value }
+          { "column" => col }.merge(keys.zip(values).to_h)
+        end
+      end
+
+      # records as a frame with the source file first, for writing; values are strings since
+      # describe mixes numbers with min/max of String columns
+      def self.table(stats_df, file:)
+        rows = records(stats_df).map do |record|
+          { "file" => file }.merge(record.transform_values { |value| value&.to_s })
+        end
+        Polars::DataFrame.new(rows)
+      end
+
       def self.count(df)
         df.height
       end
+
+      # Distinct values per column from one aggregation over all columns
+      # approx uses HyperLogLog, much cheaper on huge high-cardinality columns
+      def self.nunique(df, approx: false)
+        aggs = df.columns.map do |col|
+          expr = approx ? Polars.col(col).approx_n_unique : Polars.col(col).n_unique
+          expr.alias(col)
+        end
+        row = df.lazy.select(aggs).collect.row(0, named: true)
+
+        df.columns.map { |col| [col, row[col]] }
+      end
+
+      # Footer min/max bytes (Parquet plain encoding) as the values Polars returns, for dtypes whose
+      # encoding maps directly onto one; timestamps, decimals, categoricals and the like are computed.
+      # Booleans decode to 0/1 so row groups compare, and become true/false afterwards
+      FOOTER_DECODERS = {
+        Polars::Int8 => "l<", Polars::Int16 => "l<", Polars::Int32 => "l<", Polars::Int64 => "q<",
+        Polars::UInt8 => "L<", Polars::UInt16 => "L<", Polars::UInt32 => "L<", Polars::UInt64 => "Q<",
+        Polars::Float32 => "e", Polars::Float64 => "E",
+      }.transform_values { |format| ->(bytes) { bytes.unpack1(format) } }.merge(
+        Polars::Boolean => ->(bytes) { bytes.getbyte(0) },
+        Polars::String => ->(bytes) { bytes.dup.force_encoding(Encoding::UTF_8) },
+        Polars::Date => ->(bytes) { Date.new(1970, 1, 1) + bytes.unpack1("l<") },
+      ).freeze
+
+      # count/null_count/min/max for every column
+      # path is the Parquet file behind an untransformed frame: columns whose footer statistics cover
+      # every row group are answered from it, without reading any data, and only the rest go through
+      # a single projected lazy pass. Mean, std and median need a full aggregation and are reported
+      # as unavailable. schema is the frame's already-resolved schema, when the caller has it
+      def self.fast(df, columns: nil, schema: nil, path: nil)
+        lf = df.lazy
+        if columns
+          lf = lf.select(columns)
+          schema = nil
+        end
+        schema ||= lf.schema
+        names = schema.keys
+
+        footer_rows, footer = path ? footer_statistics(path, schema) : [nil, {}]
+        aggs = footer_rows ? [] : [Polars.len.alias("__rows")]
+        names.each_with_index do |col, i|
+          next if footer.key?(col)
+
+          aggs << Polars.col(col).null_count.alias("null_count:#{i}")
+          # Nested columns have no ordering; fall back to unavailable for them
+          next if Dtypes.kind?(schema[col], Polars::NestedType)
+
+          aggs << Polars.col(col).min.alias("min:#{i}")
+          aggs << Polars.col(col).max.alias("max:#{i}")
+        end
+
+        row = aggs.empty? ? {} : lf.select(aggs).collect.row(0, named: true)
+        rows = footer_rows || row["__rows"]
+        footer.each do |col, (nulls, min, max)|
+          i = names.index(col)
+          row.merge!("null_count:#{i}" => nulls, "min:#{i}" => min, "max:#{i}" => max)
+        end
+
+        stats = { "statistic" => FAST_STATISTICS }
+        names.each_with_index do |col, i|
+          nulls = row["null_count:#{i}"]
+          min = row.key?("min:#{i}") ? row["min:#{i}"]&.to_s : UNAVAILABLE
+          max = row.key?("max:#{i}") ? row["max:#{i}"]&.to_s : UNAVAILABLE
+          stats[col] = [(rows - nulls).to_s, nulls.to_s, UNAVAILABLE, UNAVAILABLE, min, max, UNAVAILABLE]
+        end
+
+        {
+          row_count: rows,
+          column_count: names.length,
+          stats: Polars::DataFrame.new(stats),
+        }
+      end
+
+      # [row count, { column => [null_count, min, max] }] for the columns the footer fully covers
+      def self.footer_statistics(path, schema)
+        rows, columns = ParquetFooter.statistics(path)
+        covered = schema.each_with_object({}) do |(col, dtype), found|
+          stats = footer_column(columns[col], dtype)
+          found[col] = stats if stats
+        end
+        [rows, covered]
+      end
+
+      # nil unless every row group has a null count, and min/max unless all of its values are null
+      def self.footer_column(groups, dtype)
+        decode = FOOTER_DECODERS.find { |kind, _| Dtypes.kind?(dtype, kind) }&.last
+        return unless decode && groups&.any? && groups.all? { |group| group&.fetch(:null_count) }
+
+        valued = groups.reject { |group| group[:null_count] == group[:num_values] }
+        return unless valued.all? { |group| group[:min] && group[:max] }
+
+        min, max = [:min, :max].map { |bound| valued.map { |group| decode.call(group[bound]) }.public_send(bound) }
+        min, max = [min, max].map { |value| value && value == 1 } if Dtypes.kind?(dtype, Polars::Boolean)
+        [groups.sum { |group| group[:null_count] }, min, max]
+      end
+      private_class_method :footer_statistics, :footer_column
     end
   end
 end
@@ -0,0 +1,175 @@
+# frozen_string_literal: true
+
+module TabularTool
+  module Operations
+    # Appends running totals, first differences and lagged copies of columns
+    # Computed in the frame's current order, so sort first for time-series results
+    #
+    # --with adds SQL expressions as columns, including window functions:
+    #
+    #   sum(amount) OVER (PARTITION BY city) AS city_total
+    #   row_number() OVER (PARTITION BY city ORDER BY ts) AS n
+    #
+    # Polars' SQL layer has no window frames, so OVER takes only PARTITION BY and ORDER BY.
+    # Without ORDER BY an aggregate covers the whole partition; with it, sum/count/min/max/avg
+    # run from the partition's first row to the current one, row by row (ties aren't grouped
+    # the way SQL's default RANGE frame would). ROWS/RANGE/GROUPS clauses are rejected.
+    module Window
+      WITH_SPEC = /\A(?<expr>.+?)\s+AS\s+(?<name>"[^"]+"|\w+)\s*\z/im
+      OVER_CLAUSE = /\A(?<call>.+?)\s+OVER\s*\((?<window>.*)\)\s*\z/im
+      WINDOW_SPEC = /\A\s*(?:PARTITION\s+BY\s+(?<partition>.+?))?\s*(?:ORDER\s+BY\s+(?<order>.+?))?\s*\z/im
+      FUNCTION_CALL = /\A(?<function>\w+)\s*\((?<args>.*)\)\z/m
+
+      # Aggregates that become running aggregates under ORDER BY
+      RUNNING = %w[sum count min max avg].freeze
+      RANKING = %w[row_number rank dense_rank].freeze
+      OFFSETS = %w[lag lead].freeze
+
+      # Restores input order after an ORDER BY window
+      ORDER_INDEX = "__with_order__"
+
+      # "col[:alias]" for cumsum/diff, "col:N[:alias]" for lag
+      def self.parse_spec(spec, kind)
+        parts = spec.split(":")
+        column = parts.shift
+        raise Error, "--#{kind} needs a column name" if column.nil? || column.empty?
+
+        if kind == :lag
+          periods = parts.shift
+          raise Error, "--lag #{spec}: expected col:N[:alias]" unless periods&.match?(/\A\d+\z/)
+
+          { column: column, periods: periods.to_i, alias: parts.first || "#{column}_lag#{periods}" }
+        else
+          { column: column, alias: parts.first || "#{column}_#{kind}" }
+        end
+      end
+
+      # over: group columns whose windows restart for every group
+      def self.call(df, cumsum: [], diff: [], lag: [], over: nil)
+        { "--cumsum" => cumsum, "--diff" => diff, "--lag" => lag }.each do |option, specs|
+          Operations.require_columns(df, specs.map { |spec| spec[:column] }, option: option) if specs.any?
+        end
+        Operations.require_columns(df, over, option: "--over") if over
+
+        exprs = cumsum.map { |spec| Polars.col(spec[:column]).cum_sum.alias(spec[:alias]) } +
+                diff.map { |spec| Polars.col(spec[:column]).diff.alias(spec[:alias]) } +
+                lag.map { |spec| Polars.col(spec[:column]).shift(spec[:periods]).alias(spec[:alias]) }
+        return df if exprs.empty?
+
+        exprs = exprs.map { |expr| expr.over(over) } if over
+        df.with_columns(exprs)
+      end
+
+      # "EXPR AS name", with EXPR optionally ending in OVER (...)
+      def self.parse_with(spec)
+        match = WITH_SPEC.match(spec.strip)
+        raise Error, "--with #{spec}: expected EXPR AS name" unless match
+
+        parsed = { spec: spec, name: match[:name].delete('"'), sql: match[:expr].strip }
+        over = OVER_CLAUSE.match(parsed[:sql])
+        return parsed unless over
+
+        window = over[:window]
+        if window.match?(/\b(?:ROWS|RANGE|GROUPS)\b/i)
+          raise Error, "--with #{spec}: window frames (ROWS/RANGE/GROUPS) aren't supported; " \
+                       "ORDER BY alone gives a running aggregate from the start of the partition"
+        end
+        clause = WINDOW_SPEC.match(window)
+        raise Error, "--with #{spec}: OVER takes PARTITION BY and/or ORDER BY" unless clause
+
+        order = clause[:order].to_s.split(",").map do |key|
+          column, direction, *rest = key.split
+          if rest.any? || (direction && !direction.match?(/\A(asc|desc)\z/i))
+            raise Error, "--with #{spec}: bad ORDER BY key #{key.strip} (expected COLUMN [ASC|DESC])"
+          end
+
+          { column: column, descending: direction.to_s.casecmp?("desc") }
+        end
+        partition = clause[:partition].to_s.split(",").map(&:strip)
+        parsed.merge(sql: over[:call].strip, partition_by: partition, order_by: order)
+      end
+
+      # Each spec sees the columns added before it
+      def self.with_columns(df, specs)
+        specs.reduce(df) { |frame, spec| add_column(frame, spec) }
+      end
+
+      def self.add_column(df, spec)
+        order = spec[:order_by].to_a
+        Operations.require_columns(df, spec[:partition_by].to_a + order.map { |key| key[:column] }, option: "--with")
+        expr = window_expr(spec).alias(spec[:name])
+        return df.with_columns(expr) if order.empty?
+
+        # Evaluated in ORDER BY order, then sorted back so rows keep their input order
+        df.with_row_index(name: ORDER_INDEX)
+          .sort(order.map { |key| key[:column] }, descending: order.map { |key| key[:descending] }, maintain_order: true)
+          .with_columns(expr)
+          .sort(ORDER_INDEX)
+          .drop(ORDER_INDEX)
+      end
+
+      def self.window_expr(spec)
+        return sql(spec[:sql], spec) unless spec.key?(:partition_by)
+
+        call = FUNCTION_CALL.match(spec[:sql])
+        raise Error, "--with #{spec[:spec]}: OVER needs a function call, e.g. sum(x) OVER (...)" unless call
+
+        function = call[:function].downcase
+        args = call[:args].strip
+        expr = if RANKING.include?(function)
+                 ranking_expr(function, spec)
+               elsif OFFSETS.include?(function)
+                 offset_expr(function, args, spec)
+               elsif spec[:order_by].any?
+                 running_expr(function, args, spec)
+               else
+                 sql(spec[:sql], spec)
+               end
+        spec[:partition_by].any? ? expr.over(spec[:partition_by]) : expr
+      end
+
+      # The frame is already in ORDER BY order, so ranks follow from where the keys change
+      def self.ranking_expr(function, spec)
+        position = Polars.int_range(0, Polars.len) + 1
+        return position if function == "row_number"
+        raise Error, "--with #{spec[:spec]}: #{function}() needs ORDER BY" if spec[:order_by].empty?
+
+        keys = spec[:order_by].map { |key| Polars.col(key[:column]) }
+        changed = keys.map { |key| (key != key.shift(1)).fill_null(true) }.reduce(:|)
+        return changed.cast(Polars::UInt32).cum_sum if function == "dense_rank"
+
+        Polars.when(changed).then(position).otherwise(Polars.lit(nil)).fill_null(strategy: "forward")
+      end
+
+      # lag(x[, n]) / lead(x[, n]) in ORDER BY order (or input order without one)
+      def self.offset_expr(function, args, spec)
+        value, periods = args.match(/\A(.+?)(?:\s*,\s*(\d+))?\z/m).captures
+        periods = (periods || 1).to_i
+        sql(value, spec).shift(function == "lag" ? periods : -periods)
+      end
+
+      def self.running_expr(function, args, spec)
+        unless RUNNING.include?(function)
+          raise Error, "--with #{spec[:spec]}: with ORDER BY, OVER supports #{(RUNNING + RANKING + OFFSETS).join(', ')}"
+        end
+        return Polars.int_range(0, Polars.len) + 1 if function == "count" && args == "*"
+
+        value = sql(args, spec)
+        case function
+        when "sum" then value.cum_sum
+        when "count" then value.is_not_null.cast(Polars::UInt32).cum_sum
+        when "min" then value.cum_min
+        when "max" then value.cum_max
+        when "avg" then value.cum_sum / value.is_not_null.cast(Polars::UInt32).cum_sum
+        end
+      end
+
+      def self.sql(expression, spec)
+        Polars.sql_expr(expression)
+      rescue Polars::Error => e
+        raise Error, "Invalid --with #{spec[:spec]}: #{e.message.lines.first.strip}"
+      end
+      private_class_method :add_column, :window_expr, :ranking_expr, :offset_expr, :running_expr, :sql
+    end
+  end
+end
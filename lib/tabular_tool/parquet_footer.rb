@@ -0,0 +1,279 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Reads just enough of a Parquet footer (Thrift compact encoding) to count its row groups and
+  # collect column chunk statistics, neither of which Polars exposes
+  class ParquetFooter
+    MAGIC = "PAR1"
+    # FileMetaData: 3 is num_rows, 4 is list<RowGroup>
+    NUM_ROWS_FIELD = 3
+    ROW_GROUPS_FIELD = 4
+    # RowGroup: 1 list<ColumnChunk>, 2 total_byte_size, 3 num_rows, 6 total_compressed_size
+    COLUMNS_FIELD = 1
+    TOTAL_BYTE_SIZE_FIELD = 2
+    GROUP_ROWS_FIELD = 3
+    COMPRESSED_SIZE_FIELD = 6
+    # ColumnChunk 3: ColumnMetaData
+    META_DATA_FIELD = 3
+    # ColumnMetaData: 3 path_in_schema, 5 num_values, 12 Statistics
+    PATH_FIELD = 3
+    NUM_VALUES_FIELD = 5
+    STATISTICS_FIELD = 12
+    # Statistics: 3 null_count, 5/6 max_value/min_value, 7/8 whether they're exact
+    # (fields 1/2, the deprecated min/max, have an undefined sort order and are ignored)
+    NULL_COUNT_FIELD = 3
+    MAX_VALUE_FIELD = 5
+    MIN_VALUE_FIELD = 6
+    MAX_EXACT_FIELD = 7
+    MIN_EXACT_FIELD = 8
+
+    # Compact protocol type ids
+    BOOLEAN_TRUE = 1
+    BOOLEAN_FALSE = 2
+    BYTE = 3
+    I16 = 4
+    I32 = 5
+    I64 = 6
+    DOUBLE = 7
+    BINARY = 8
+    LIST = 9
+    SET = 10
+    MAP = 11
+    STRUCT = 12
+
+    def self.row_groups(path)
+      read(path).row_group_count
+    end
+
+    # [row count, { column => one entry per row group }] for the file's top-level leaf columns;
+    # an entry is { num_values:, null_count:, min:, max: } with min/max as the raw plain-encoded
+    # bytes, and nil for a row group without statistics. Fields a writer left out (or marked
+    # inexact) are nil, so callers can fall back to reading the column.
+    def self.statistics(path)
+      read(path).column_statistics
+    end
+
+    # [rows, bytes] per row group, bytes being its compressed size when the writer recorded one
+    def self.row_group_sizes(path)
+      read(path).row_group_sizes
+    end
+
+    def self.read(path)
+      File.open(path, "rb") do |file|
+        raise Error, "#{path} is too small to be a Parquet file" if file.size < 12
+
+        file.seek(-8, IO::SEEK_END)
+        length, magic = file.read(8).unpack("Va4")
+        raise Error, "#{path} is not a Parquet file" unless magic == MAGIC && length <= file.size - 12
+
+        file.seek(-8 - length, IO::SEEK_END)
+        new(file.read(length))
+      end
+    end
+
+    def initialize(bytes)
+      @bytes = bytes
+      @position = 0
+    end
+
+    def row_group_count
+      each_field do |field_id, type|
+        return collection_header.first if field_id == ROW_GROUPS_FIELD && type == LIST
+
+        skip(type)
+      end
+      0
+    end
+
+    def column_statistics
+      rows, groups = file_meta_data
+      columns = Hash.new { |hash, name| hash[name] = [] }
+      groups.each do |group|
+        # Nested columns span several leaves with longer paths; only flat columns map to one name
+        group[:chunks].each { |path, stats| columns[path.first] << stats if path.length == 1 }
+      end
+      [rows, columns.to_h]
+    end
+
+    def row_group_sizes
+      file_meta_data.last.map { |group| [group[:rows], group[:compressed_bytes] || group[:bytes]] }
+    end
+
+    private
+
+    # Yields each field id and type of the struct at the current position; the block reads or skips the value
+    def each_field
+      field_id = 0
+      loop do
+        header = byte
+        return if header.zero?
+
+        type = header & 0x0F
+        delta = header >> 4
+        field_id = delta.zero? ? zigzag(varint) : field_id + delta
+        yield field_id, type
+      end
+    end
+
+    def list
+      size, = collection_header
+      Array.new(size) { yield }
+    end
+
+    # [row count, row groups]
+    def file_meta_data
+      rows = nil
+      groups = []
+      each_field do |field_id, type|
+        if field_id == NUM_ROWS_FIELD && type == I64
+          rows = zigzag(varint)
+        elsif field_id == ROW_GROUPS_FIELD && type == LIST
+          groups = list { row_group }
+        else
+          skip(type)
+        end
+      end
+      [rows, groups]
+    end
+
+    # chunks holds [path, statistics] per column chunk
+    def row_group
+      group = { chunks: [], rows: nil, bytes: nil, compressed_bytes: nil }
+      each_field do |field_id, type|
+        if field_id == COLUMNS_FIELD && type == LIST
+          group[:chunks] = list { column_chunk }
+        elsif field_id == TOTAL_BYTE_SIZE_FIELD && type == I64
+          group[:bytes] = zigzag(varint)
+        elsif field_id == GROUP_ROWS_FIELD && type == I64
+          group[:rows] = zigzag(varint)
+        elsif field_id == COMPRESSED_SIZE_FIELD && type == I64
+          group[:compressed_bytes] = zigzag(varint)
+        else
+          skip(type)
+        end
+      end
+      group
+    end
+
+    def column_chunk
+      chunk = [[], nil]
+      each_field do |field_id, type|
+        field_id == META_DATA_FIELD && type == STRUCT ? chunk = column_meta_data : skip(type)
+      end
+      chunk
+    end
+
+    def column_meta_data
+      path = []
+      num_values = nil
+      stats = nil
+      each_field do |field_id, type|
+        if field_id == PATH_FIELD && type == LIST
+          path = list { binary.force_encoding(Encoding::UTF_8) }
+        elsif field_id == NUM_VALUES_FIELD && type == I64
+          num_values = zigzag(varint)
+        elsif field_id == STATISTICS_FIELD && type == STRUCT
+          stats = column_stats
+        else
+          skip(type)
+        end
+      end
+      [path, stats&.merge(num_values: num_values)]
+    end
+
+    def column_stats
+      stats = { null_count: nil, min: nil, max: nil }
+      exact = { min: true, max: true }
+      each_field do |field_id, type|
+        case field_id
+        when NULL_COUNT_FIELD then type == I64 ? stats[:null_count] = zigzag(varint) : skip(type)
+        when MAX_VALUE_FIELD then type == BINARY ? stats[:max] = binary : skip(type)
+        when MIN_VALUE_FIELD then type == BINARY ? stats[:min] = binary : skip(type)
+        when MAX_EXACT_FIELD then exact[:max] = type == BOOLEAN_TRUE
+        when MIN_EXACT_FIELD then exact[:min] = type == BOOLEAN_TRUE
+        else skip(type)
+        end
+      end
+      stats[:min] = nil unless exact[:min]
+      stats[:max] = nil unless exact[:max]
+      stats
+    end
+
+    # Booleans live in the field header, except inside lists, sets and maps where each takes a byte
+    def skip(type, in_collection: false)
+      case type
+      when BOOLEAN_TRUE, BOOLEAN_FALSE then @position += 1 if in_collection
+      when BYTE then @position += 1
+      when I16, I32, I64 then varint
+      when DOUBLE then @position += 8
+      when BINARY then @position += varint
+      when LIST, SET
+        size, element = collection_header
+        size.times { skip(element, in_collection: true) }
+      when MAP
+        size = varint
+        return if size.zero?
+
+        types = byte
+        size.times do
+          skip(types >> 4, in_collection: true)
+          skip(types & 0x0F, in_collection: true)
+        end
+      when STRUCT then skip_struct
+      else
+        raise Error, "Unreadable Parquet footer: unknown field type #{type}"
+      end
+    end
+
+    def skip_struct
+      loop do
+        header = byte
+        return if header.zero?
+
+        zigzag(varint) if (header >> 4).zero?
+        skip(header & 0x0F)
+      end
+    end
+
+    # [size, element type]; sizes of 15 or more follow as a varint
+    def collection_header
+      header = byte
+      size = header >> 4
+      size = varint if size == 15
+      [size, header & 0x0F]
+    end
+
+    def byte
+      value = @bytes.getbyte(@position)
+      raise Error, "Unreadable Parquet footer: truncated" if value.nil?
+
+      @position += 1
+      value
+    end
+
+    def binary
+      length = varint
+      value = @bytes.byteslice(@position, length)
+      raise Error, "Unreadable Parquet footer: truncated" if value.nil? || value.bytesize < length
+
+      @position += length
+      value
+    end
+
+    def varint
+      value = 0
+      shift = 0
+      loop do
+        current = byte
+        value |= (current & 0x7F) << shift
+        return value if (current & 0x80).zero?
+
+        shift += 7
+      end
+    end
+
+    def zigzag(value)
+      (value >> 1) ^ -(value & 1)
+    end
+  end
+end
@@ -0,0 +1,160 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Progress reporting on stderr for long-running reads and writes
+  # Never writes to stdout, so piped data stays clean
+  class Progress
+    # Minimum seconds between redraws
+    REFRESH_INTERVAL = 0.2
+
+    # Wraps an IO and reports bytes consumed to a Progress
+    class CountingIO
+      # A read of everything goes in chunks of this many bytes, so the bar moves while it streams in
+      READ_CHUNK_BYTES = 1024 * 1024
+
+      def initialize(io, progress)
+        @io = io
+        @progress = progress
+      end
+
+      def each_line(*args)
+        return enum_for(:each_line, *args) unless block_given?
+
+        @io.each_line(*args) do |line|
+          @progress.advance(line.bytesize)
+          yield line
+        end
+      end
+
+      def gets(*args)
+        line = @io.gets(*args)
+        @progress.advance(line.bytesize) if line
+        line
+      end
+
+      def read(length = nil, *args)
+        return read_all if length.nil? && args.empty?
+
+        data = @io.read(length, *args)
+        @progress.advance(data.bytesize) if data
+        data
+      end
+
+      def method_missing(name, *args, &block)
+        @io.respond_to?(name) ? @io.public_send(name, *args, &block) : super
+      end
+
+      def respond_to_missing?(name, include_private = false)
+        @io.respond_to?(name, include_private) || super
+      end
+
+      private
+
+      def read_all
+        data = String.new(encoding: Encoding::BINARY)
+        while (chunk = @io.read(READ_CHUNK_BYTES))
+          @progress.advance(chunk.bytesize)
+          data << chunk
+        end
+        data
+      end
+    end
+
+    attr_reader :bytes
+
+    def initialize(enabled: false, io: $stderr)
+      @io = io
+      # Suppressed automatically when stderr isn't a terminal
+      @enabled = enabled && io.tty?
+      @bytes = 0
+    end
+
+    def enabled?
+      @enabled
+    end
+
+    def start(label, total: nil)
+      @label = label
+      @total = total
+      @bytes = 0
+      @started_at = now
+      @last_render = nil
+      render
+    end
+
+    def wrap(io)
+      enabled? ? CountingIO.new(io, self) : io
+    end
+
+    def advance(count)
+      @bytes += count
+      render if due?
+    end
+
+    def finish(rows: nil)
+      return unless enabled? && @label
+
+      elapsed = now - @started_at
+      summary = "#{@label}: done in #{format_duration(elapsed)}"
+      if rows
+        rate = elapsed > 0 ? (rows / elapsed).round : rows
+        summary = "#{@label}: #{rows} rows in #{format_duration(elapsed)} (#{rate} rows/s)"
+      end
+
+      @io.print("\r\e[K#{summary}\n")
+      @label = nil
+    end
+
+    private
+
+    def due?
+      enabled? && (@last_render.nil? || now - @last_render >= REFRESH_INTERVAL)
+    end
+
+    def render
+      return unless enabled?
+
+      @last_render = now
+      elapsed = @last_render - @started_at
+      parts = [@label]
+
+      if @bytes > 0
+        rate = elapsed > 0 ? @bytes / elapsed : 0
+        if @total && @total > 0
+          percent = [(@bytes * 100.0 / @total).round, 100].min
+          parts << "#{format_bytes(@bytes)}/#{format_bytes(@total)} (#{percent}%)"
+          parts << "ETA #{format_duration((@total - @bytes) / rate)}" if rate > 0 && @bytes < @total
+        else
+          parts << format_bytes(@bytes)
+        end
+        parts << "#{format_bytes(rate)}/s"
+      end
+
+      parts << format_duration(elapsed)
+      @io.print("\r\e[K#{parts.join('  ')}")
+    end
+
+    def format_bytes(count)
+      self.class.format_bytes(count)
+    end
+
+    def self.format_bytes(count)
+      units = ["B", "KiB", "MiB", "GiB", "TiB"]
+      value = count.to_f
+      unit = units.shift
+      while value >= 1024 && units.any?
+        value /= 1024
+        unit = units.shift
+      end
+      unit == "B" ? "#{value.round} B" : format("%.1f %s", value, unit)
+    end
+
+    def format_duration(seconds)
+      seconds < 60 ? format("%.1fs", seconds) : format("%dm%02ds", seconds / 60, seconds % 60)
+    end
+
+    def now
+      Process.clock_gettime(Process::CLOCK_MONOTONIC)
+    end
+  end
+end
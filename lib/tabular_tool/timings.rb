@@ -0,0 +1,66 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Wall-clock time spent in each phase of a command, reported by --time and --timings
+  class Timings
+    attr_accessor :rows_in, :rows_out
+
+    def initialize
+      @phases = {}
+      # Time spent in nested phases, per measure call in progress
+      @nested = []
+    end
+
+    # A phase measured inside another (a write inside an external sort) counts only toward itself;
+    # the outer phase keeps its own time, so the phases still add up to the wall-clock total
+    def measure(phase)
+      @nested.push(0)
+      started = now
+      yield
+    ensure
+      elapsed = (now - started) * 1000
+      inner = @nested.pop
+      @nested[-1] += elapsed if @nested.any?
+      record(phase, elapsed - inner)
+    end
+
+    def record(phase, ms)
+      @phases[phase] = (@phases[phase] || 0) + ms
+    end
+
+    def phases
+      @phases.dup
+    end
+
+    # One "phase<TAB>ms" line per phase, in execution order, plus the total
+    def report
+      lines = @phases.map { |phase, ms| "#{phase}\t#{ms.round(1)}" }
+      lines << "total\t#{@phases.values.sum.round(1)}"
+      lines.join("\n")
+    end
+
+    # The --time report plus row counts and peak memory; unknown values print as "-"
+    def summary
+      [
+        report,
+        "rows_in\t#{rows_in || '-'}",
+        "rows_out\t#{rows_out || '-'}",
+        "peak_rss_kb\t#{peak_rss_kb || '-'}",
+      ].join("\n")
+    end
+
+    # Best-effort: high-water mark from /proc on Linux, unavailable elsewhere
+    def peak_rss_kb
+      status = File.read("/proc/self/status")
+      status[/^VmHWM:\s+(\d+) kB/, 1]&.to_i
+    rescue SystemCallError
+      nil
+    end
+
+    private
+
+    def now
+      Process.clock_gettime(Process::CLOCK_MONOTONIC)
+    end
+  end
+end
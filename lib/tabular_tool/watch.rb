@@ -0,0 +1,75 @@
+# frozen_string_literal: true
+
+module TabularTool
+  # Re-runs a block whenever a file changes, for --watch
+  # Polls the file's mtime and size, which works the same on every platform and filesystem
+  class Watch
+    DEFAULT_INTERVAL = 0.5
+
+    # Separators go to stderr, so piped stdout stays a clean sequence of results
+    # sleeper waits out each poll interval; tests pass one that returns at once
+    def initialize(path, interval: nil, io: $stderr, sleeper: Kernel.method(:sleep))
+      raise Error, "--watch needs an input file, not stdin" if path.nil? || path == CLI::STDIN_PATH
+
+      @path = path
+      @interval = interval || DEFAULT_INTERVAL
+      @io = io
+      @sleeper = sleeper
+    end
+
+    # Runs the block now and after every change until interrupted (or max_runs is reached)
+    # Returns the number of runs
+    def run(max_runs: nil)
+      runs = 0
+      last_seen = nil
+
+      loop do
+        current = signature
+        if current.nil? || current == last_seen
+          @sleeper.call(@interval)
+          next
+        end
+
+        # Debounce: wait for the writer to finish before re-running
+        @sleeper.call(@interval)
+        next unless signature == current
+
+        last_seen = current
+        runs += 1
+        separate(runs)
+        begin
+          yield
+        rescue CheckFailed => e
+          # The report is data, so it goes to stdout like any other result
+          puts e.output if e.output
+        rescue Error, Polars::Error => e
+          # A half-written file shouldn't end the session; report and keep watching
+          warn "Error: #{e.message}"
+        end
+        return runs if max_runs && runs >= max_runs
+      end
+    rescue Interrupt
+      runs
+    end
+
+    private
+
+    def signature
+      stat = File.stat(@path)
+      [stat.mtime, stat.size]
+    rescue Errno::ENOENT
+      nil
+    end
+
+    def separate(runs)
+      return if Log.quiet?
+
+      if @io.tty?
+        @io.print "\e[H\e[2J"
+      elsif runs > 1
+        @io.puts
+      end
+      @io.puts "==> #{@path} (run #{runs}, #{Time.now.strftime('%H:%M:%S')}) <=="
+    end
+  end
+end
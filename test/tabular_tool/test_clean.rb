@@ -0,0 +1,66 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "tmpdir"
+
+class TestClean < Minitest::Test
+  def setup
+    @fixtures_path = File.join(__dir__, "..", "fixtures")
+    @dirty = File.join(@fixtures_path, "dirty.csv")
+  end
+
+  def test_normalized_headers
+    names = TabularTool::Operations::Clean.normalized_headers([" First Name", "City", "city", "", "orderID"])
+
+    assert_equal ["first_name", "city", "city_2", "column_4", "order_id"], names
+    # A suffix never lands on a name the header already has
+    assert_equal ["a", "a_3", "a_2"], TabularTool::Operations::Clean.normalized_headers(["a", "a", "a_2"])
+  end
+
+  def test_clean_reports_touch_counts
+    df = TabularTool::Formats.read(@dirty)
+    cleaned, summary = TabularTool::Operations::Clean.call(
+      df,
+      normalize_headers: true,
+      trim: :all,
+      empty_to_null: true,
+      dedupe: true,
+    )
+
+    assert_equal({ headers: 3, trimmed: 3, nulled: 1, deduped: 1 }, summary)
+    assert_equal ["first_name", "city", "city_2"], cleaned.columns
+    assert_equal ["NYC", nil, "Boston"], cleaned["city"].to_a
+
+    lazy, lazy_summary = TabularTool::Operations::Clean.call(df.lazy, normalize_headers: true, trim: :all,
+                                                                       empty_to_null: true, dedupe: true)
+    assert_kind_of Polars::LazyFrame, lazy
+    assert_equal summary, lazy_summary
+    assert_equal cleaned.rows, lazy.collect.rows
+  end
+
+  def test_cleaned_output_lints_clean
+    Dir.mktmpdir do |dir|
+      out = File.join(dir, "clean.csv")
+      _, err = capture_io do
+        TabularTool::CLI.execute(
+          command: :clean,
+          file: @dirty,
+          output: out,
+          normalize_headers: true,
+          trim: :all,
+          empty_to_null: true,
+          dedupe: true,
+        )
+      end
+      assert_match(/clean: 3 headers renamed, 3 cells trimmed, 1 empty strings set to null, 1 duplicate rows removed/, err)
+
+      fixed = [:whitespace, :empty_strings, :duplicate_rows, :column_name_whitespace, :column_name_case]
+      before = TabularTool::Operations::Lint.call(TabularTool::Formats.read(@dirty), header: TabularTool::Formats.read_header(@dirty))
+      after = TabularTool::Operations::Lint.call(TabularTool::Formats.read(out), header: TabularTool::Formats.read_header(out))
+
+      assert before[:warnings].any? { |w| fixed.include?(w[:type]) }
+      assert after[:warnings].none? { |w| fixed.include?(w[:type]) }
+      assert after[:errors].none? { |e| e[:type] == :duplicate_rows }
+    end
+  end
+end
@@ -2,6 +2,8 @@
 
 require "test_helper"
 require "tempfile"
+require "tmpdir"
+require "json"
 
 class TestCLI < Minitest::Test
   def setup
@@ -49,6 +51,18 @@ class TestCLI < Minitest::Test
     assert_equal 20, args[:limit]
   end
 
+  def test_parse_stdin_dash_as_file
+    args = TabularTool::CLI.parse_args(["head", "5", "-"])
+    assert_equal :head, args[:command]
+    assert_equal 5, args[:limit]
+    assert_equal "-", args[:file]
+  end
+
+  def test_parse_pipe_format
+    args = TabularTool::CLI.parse_args(["--pipe-format", "arrow", "file.parquet"])
+    assert_equal :arrow, args[:pipe_format]
+  end
+
   def test_parse_tail_command
     args = TabularTool::CLI.parse_args(["tail", "file.csv"])
     assert_equal :tail, args[:command]
@@ -372,6 +386,32 @@ class TestCLI < Minitest::Test
     assert_equal 4, lines.length, "Should have header + 3 rows"
   end
 
+  def test_head_parquet_uses_lazy_scan
+    # head on Parquet should never eagerly read the whole file
+    Polars.stub(:read_parquet, ->(*) { flunk "head should scan Parquet lazily" }) do
+      result = TabularTool::CLI.execute(
+        command: :head,
+        file: File.join(@fixtures_path, "basic.parquet"),
+        limit: 2,
+        select: ["name"],
+        pretty: false,
+      )
+
+      assert_equal "name\nAlice\nBob", result.strip
+    end
+  end
+
+  def test_parquet_head_fast_path_conditions
+    file = File.join(@fixtures_path, "basic.parquet")
+
+    assert TabularTool::CLI.send(:parquet_head_fast_path?, file, :head, { select: ["name"] })
+    refute TabularTool::CLI.send(:parquet_head_fast_path?, file, :head, { sort_keys: ["age"] })
+    refute TabularTool::CLI.send(:parquet_head_fast_path?, file, :head, { where: "age > 30" })
+    refute TabularTool::CLI.send(:parquet_head_fast_path?, file, :head, { streaming: false })
+    refute TabularTool::CLI.send(:parquet_head_fast_path?, file, :tail, {})
+    refute TabularTool::CLI.send(:parquet_head_fast_path?, File.join(@fixtures_path, "basic.csv"), :head, {})
+  end
+
   # Test transformation combinations
   def test_filter_and_sort_combination
     result = TabularTool::CLI.execute(
@@ -401,6 +441,90 @@ class TestCLI < Minitest::Test
     assert_equal 3, lines.length, "Should have header + 2 unique values (active, inactive)"
   end
 
+  def test_column_order_survives_drop_unique_and_sort
+    Dir.mktmpdir do |dir|
+      ["csv", "parquet"].each do |format|
+        input = File.join(@fixtures_path, "basic.#{format}")
+        output = File.join(dir, "out.#{format}")
+        TabularTool::CLI.execute(command: :cat, file: input, output: output, drop: ["city"],
+                                 unique_on: ["status"], sort_keys: ["score"], reverse: true)
+
+        assert_equal ["name", "age", "score", "status"], TabularTool::Formats.read(output).columns, format
+      end
+    end
+  end
+
+  def test_column_order_appends_derived_columns
+    result = TabularTool::CLI.execute(
+      command: :cat,
+      file: File.join(@fixtures_path, "basic.csv"),
+      drop: ["name"],
+      unique_on: ["city"],
+      sort_keys: ["age"],
+      cumsum: [TabularTool::Operations::Window.parse_spec("score", :cumsum)],
+      pretty: false,
+    )
+
+    assert_equal "age,city,score,status,score_cumsum", result.split("\n").first
+  end
+
+  def test_sample_selects_and_sorts_the_sampled_rows
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "s.parquet")
+      args = TabularTool::CLI.parse_args(["sample", "5", "--select", "name,score", "-k", "score", "--sample-method", "full",
+                                          "--seed", "7", File.join(@fixtures_path, "basic.csv"), "-o", output])
+      TabularTool::CLI.execute(**args)
+
+      sampled = TabularTool::Formats.read(output)
+      assert_equal ["name", "score"], sampled.columns
+      assert_equal 5, sampled.height
+      assert_equal sampled["score"].to_a.sort, sampled["score"].to_a
+    end
+  end
+
+  def test_sample_draws_from_rows_left_by_offset_and_limit
+    $stdout.stub :tty?, false do
+      file = File.join(@fixtures_path, "basic.csv")
+      names = lambda do |args|
+        result = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["sample"] + args + ["--seed", "3", file]))
+        result.lines.drop(1).map { |line| line.split(",").first }
+      end
+
+      # The fraction is of the 4-row population, not the whole file
+      sampled = names.call(["0.5", "--limit", "4"])
+      assert_equal 2, sampled.length
+      assert_empty sampled - ["Alice", "Bob", "Charlie", "Diana"]
+
+      sampled = names.call(["0.5", "--offset", "-4"])
+      assert_equal 2, sampled.length
+      assert_empty sampled - ["Grace", "Henry", "Ivy", "Jack"]
+
+      # Asking for more rows than the population returns all of it
+      assert_equal ["Alice", "Bob", "Charlie"], names.call(["10", "--limit", "3"])
+    end
+  end
+
+  def test_sample_sorts_like_cat
+    Tempfile.create(["names", ".csv"]) do |f|
+      f.write("name,n\nbob,1\nAlice,2\n,3\ncarol,4\nDAVE,5\nalice,6\n")
+      f.flush
+
+      $stdout.stub :tty?, false do
+        sort_flags = ["-k", "name", "-i", "-r", "--nulls", "last"]
+        cat = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(sort_flags + [f.path]))
+        # The whole population, shuffled first, so only the sort decides the order
+        sampled = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["sample", "6", "--sample-method", "full",
+                                                                          "--seed", "5"] + sort_flags + [f.path]))
+
+        # "Alice" and "alice" tie under -i, so compare case-folded names
+        names = ->(output) { output.lines.map { |line| line.split(",").first.to_s.downcase } }
+        assert_equal names.call(cat), names.call(sampled)
+        assert_equal "DAVE", sampled.lines[1].split(",").first
+        assert_equal ",3", sampled.lines.last.chomp
+      end
+    end
+  end
+
   # Test sample command variations
   def test_sample_compressed_file
     result = TabularTool::CLI.execute(
@@ -503,6 +627,788 @@ class TestCLI < Minitest::Test
     assert_equal "7", result, "Should count 7 active rows"
   end
 
+  def test_if_empty_error_raises_before_writing
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "out.csv")
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(
+          command: :cat,
+          file: File.join(@fixtures_path, "basic.csv"),
+          where: "age > 100",
+          output: path,
+          if_empty: :error,
+        )
+      end
+
+      assert_match(/no rows/, error.message)
+      refute File.exist?(path)
+    end
+  end
+
+  def test_if_empty_warn_prints_to_stderr
+    $stdout.stub :tty?, false do
+      _, err = capture_io do
+        TabularTool::CLI.execute(
+          command: :head,
+          file: File.join(@fixtures_path, "basic.parquet"),
+          where: "age > 100",
+          if_empty: :warn,
+        )
+      end
+
+      assert_match(/result has no rows/, err)
+    end
+  end
+
+  def test_quiet_suppresses_warnings
+    $stdout.stub :tty?, false do
+      _, err = capture_io do
+        TabularTool::CLI.execute(
+          command: :head,
+          file: File.join(@fixtures_path, "basic.parquet"),
+          where: "age > 100",
+          if_empty: :warn,
+          quiet: true,
+        )
+      end
+
+      assert_empty err
+    end
+  ensure
+    TabularTool::Log.level = :normal
+  end
+
+  def test_verbose_diagnostics_go_to_stderr_only
+    $stdout.stub :tty?, false do
+      result = nil
+      out, err = capture_io do
+        result = TabularTool::CLI.execute(command: :head, file: File.join(@fixtures_path, "basic.csv"), limit: 2, verbose: true)
+      end
+
+      assert_empty out
+      assert_equal 3, result.lines.length
+      refute_match(/read:/, result)
+      assert_match(/read: .*basic\.csv \(csv, \d+ bytes/, err)
+      assert_match(/^total\t/, err)
+    end
+  ensure
+    TabularTool::Log.level = :normal
+  end
+
+  def test_parse_args_quiet
+    assert TabularTool::CLI.parse_args(["head", "-q", "data.csv"])[:quiet]
+    assert TabularTool::CLI.parse_args(["head", "--quiet", "data.csv"])[:quiet]
+    assert TabularTool::Log.quiet?
+  ensure
+    TabularTool::Log.level = :normal
+  end
+
+  def test_header_only_writes_schema_without_rows
+    Dir.mktmpdir do |dir|
+      csv = File.join(dir, "template.csv")
+      parquet = File.join(dir, "template.parquet")
+
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), header_only: true, output: csv)
+      assert_equal "name,age,city,score,status\n", File.read(csv)
+
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.parquet"), header_only: true,
+                               output: parquet, if_empty: :error)
+      empty = Polars.read_parquet(parquet)
+      source = Polars.read_parquet(File.join(@fixtures_path, "basic.parquet"))
+      assert_equal 0, empty.height
+      assert_equal source.columns, empty.columns
+      assert_equal source.dtypes.map(&:to_s), empty.dtypes.map(&:to_s)
+    end
+
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :head, file: File.join(@fixtures_path, "basic.csv"), header_only: true)
+      assert_equal "name,age,city,score,status\n", result
+    end
+  end
+
+  def test_unknown_columns_suggest_close_matches
+    file = File.join(@fixtures_path, "basic.csv")
+    cases = {
+      { select: ["nmae"] } => /--select: nmae \(did you mean 'name'\?\)/,
+      { drop: ["citty"] } => /--drop: citty \(did you mean 'city'\?\)/,
+      { sort_keys: ["scroe:desc"] } => %r{-k/--key: scroe \(did you mean 'score'\?\)},
+      { unique_on: ["Status"] } => /--unique-on: Status \(did you mean 'status'\?\)/,
+      { where: "citty == 'Chicago' && age > 3" } => /--where: citty \(did you mean 'city'\?\)/,
+      { expr_filter: "agee > 30" } => /--expr-filter: agee \(did you mean 'age'\?\)/,
+    }
+
+    cases.each do |options, message|
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :cat, file: file, streaming: true, **options)
+      end
+      assert_match(message, error.message)
+      assert_match(/Columns: name, age, city, score, status\z/, error.message)
+    end
+  end
+
+  def test_duplicate_header_names_follow_policy
+    file = File.join(@fixtures_path, "duplicate_headers.csv")
+
+    $stdout.stub :tty?, false do
+      result = nil
+      _, err = capture_io { result = TabularTool::CLI.execute(command: :cat, file: file) }
+      assert_equal "id,amount,name,amount_2", result.lines.first.chomp
+      assert_match(/Warning: renamed duplicate column\(s\): amount -> amount_2/, err)
+
+      result = nil
+      capture_io { result = TabularTool::CLI.execute(command: :cat, file: file, on_duplicate_columns: :keep_first) }
+      assert_equal ["id,amount,name", "1,10,a", "2,20,b"], result.lines.map(&:chomp)
+    end
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: file, on_duplicate_columns: :error)
+    end
+    assert_match(/Duplicate column name\(s\) in header: amount/, error.message)
+  end
+
+  def test_selecting_a_renamed_duplicate_lists_candidates
+    file = File.join(@fixtures_path, "duplicate_headers.csv")
+
+    error = assert_raises(TabularTool::Error) do
+      capture_io { TabularTool::CLI.execute(command: :cat, file: file, select: ["id", "amount"]) }
+    end
+    assert_match(/--select amount is ambiguous.*Choose one of amount, amount_2/, error.message)
+
+    $stdout.stub :tty?, false do
+      result = nil
+      capture_io { result = TabularTool::CLI.execute(command: :cat, file: file, select: ["amount_2"]) }
+      assert_equal ["amount_2", "11", "21"], result.lines.map(&:chomp)
+    end
+  end
+
+  def test_if_empty_ignores_non_empty_results
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :head, file: File.join(@fixtures_path, "basic.csv"), if_empty: :error)
+
+      assert result.start_with?("name,")
+    end
+  end
+
+  def test_parse_shuffle_with_seed_and_limit
+    args = TabularTool::CLI.parse_args(["shuffle", "--seed", "42", "--limit", "3", "data.csv"])
+
+    assert_equal :shuffle, args[:command]
+    assert_equal 42, args[:seed]
+    assert_equal 3, args[:row_limit]
+  end
+
+  def test_shuffle_with_limit_takes_random_rows_after_shuffling
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(
+        command: :shuffle,
+        file: File.join(@fixtures_path, "basic.csv"),
+        seed: 42,
+        row_limit: 3,
+      )
+
+      lines = result.split("\n")
+      assert_equal 4, lines.length, "Header + 3 rows"
+      expected = TabularTool::Operations.shuffle(TabularTool::Formats.read(File.join(@fixtures_path, "basic.csv")), seed: 42)
+      assert_equal expected["name"].to_a.first(3), lines.drop(1).map { |line| line.split(",").first }
+    end
+  end
+
+  def test_offset_and_limit_edge_cases
+    $stdout.stub :tty?, false do
+      file = File.join(@fixtures_path, "basic.csv")
+      names = lambda do |args|
+        result = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(args + [file]))
+        result.lines.drop(1).map { |line| line.split(",").first }
+      end
+
+      # A negative offset past the start clamps to the first row
+      assert_equal 10, names.call(["--offset", "-1000000"]).length
+      assert_equal ["Ivy", "Jack"], names.call(["--offset", "-2"])
+      assert_equal ["Diana", "Eve"], names.call(["--offset", "3", "--limit", "2"])
+      assert_empty names.call(["--offset", (2**70).to_s])
+      # A limit beyond any frame's size means all rows
+      assert_equal 10, names.call(["--limit", (2**70).to_s]).length
+    end
+  end
+
+  def test_limit_zero_writes_header_only
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), row_limit: 0)
+
+      assert_equal "name,age,city,score,status\n", result
+    end
+  end
+
+  def test_cat_shuffle_matches_shuffle_command
+    $stdout.stub :tty?, false do
+      file = File.join(@fixtures_path, "basic.csv")
+      args = TabularTool::CLI.parse_args(["--shuffle", "--seed", "5", file])
+      shuffled = TabularTool::CLI.execute(**args)
+
+      assert args[:shuffle]
+      assert_equal TabularTool::CLI.execute(command: :shuffle, file: file, seed: 5), shuffled
+      assert_equal File.read(file).lines.map(&:chomp).sort, shuffled.lines.map(&:chomp).sort
+      refute_equal File.read(file).lines.map(&:chomp), shuffled.lines.map(&:chomp)
+    end
+  end
+
+  def test_shuffle_with_key_is_rejected
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), shuffle: true, sort_keys: ["age"])
+    end
+    assert_match(/--shuffle cannot be combined with --key/, error.message)
+  end
+
+  def test_head_per_group_with_descending_key
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(["head", "2", "--per-group", "status", "-k", "age:desc",
+                                          File.join(@fixtures_path, "basic.csv")])
+      result = TabularTool::CLI.execute(**args)
+
+      assert_equal ["status"], args[:per_group]
+      assert_equal ["Charlie", "Jack", "Eve", "Henry"], result.lines.drop(1).map { |line| line.split(",").first }
+    end
+  end
+
+  def test_hash_columns_apply_after_filtering
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(["--where", "name == 'Alice'", "--hash-columns", "name", "--hash-salt", "pepper",
+                                          "--redact-columns", "city", "--select", "name,city",
+                                          File.join(@fixtures_path, "basic.csv")])
+      result = TabularTool::CLI.execute(**args)
+
+      assert_equal "name,city\n#{Digest::SHA256.hexdigest('pepperAlice')},***\n", result
+    end
+  end
+
+  def test_chunk_size_sets_polars_streaming_config
+    args = TabularTool::CLI.parse_args(["--chunk-size", "50000", "--streaming", File.join(@fixtures_path, "basic.csv")])
+    assert_equal 50_000, args[:chunk_size]
+    assert_raises(TabularTool::Error) { TabularTool::CLI.parse_args(["--chunk-size", "0", "data.csv"]) }
+
+    previous = ENV["POLARS_STREAMING_CHUNK_SIZE"]
+    begin
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(**args)
+        assert_equal 11, result.lines.length
+      end
+      assert_equal "50000", ENV["POLARS_STREAMING_CHUNK_SIZE"]
+    ensure
+      ENV["POLARS_STREAMING_CHUNK_SIZE"] = previous
+    end
+  end
+
+  def test_truncate_strings_reaches_output_file
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.csv")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), output: output,
+                               truncate_strings: 4, select: ["city"])
+
+      assert_equal ["New…", "Los…", "Chi…"], File.read(output).lines.drop(1).first(3).map(&:chomp)
+    end
+  end
+
+  def test_select_dtypes_intersects_with_select
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(["head", "1", "--select", "score,name,age", "--select-dtypes", "numeric",
+                                          File.join(@fixtures_path, "basic.csv")])
+      result = TabularTool::CLI.execute(**args)
+
+      assert_equal [:numeric], args[:select_dtypes]
+      assert_equal "score,age\n85.5,30\n", result
+    end
+  end
+
+  def test_with_row_index_follows_sorted_order_and_offset
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(["-k", "age", "--with-row-index", "--row-index-offset", "100",
+                                          "--select", "name", File.join(@fixtures_path, "basic.csv")])
+      assert_equal "row_nr", args[:with_row_index]
+
+      lines = TabularTool::CLI.execute(**args).lines.map(&:chomp)
+      assert_equal "row_nr,name", lines.first
+      assert_equal "100,Bob", lines[1]
+      assert_equal (100..109).to_a, lines.drop(1).map { |line| line.split(",").first.to_i }
+    end
+  end
+
+  def test_with_row_index_keeps_logical_position_through_tail
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :tail, file: File.join(@fixtures_path, "basic.csv"), limit: 2,
+                                        with_row_index: "idx", select: ["name"])
+
+      assert_equal "idx,name\n8,Ivy\n9,Jack\n", result
+    end
+  end
+
+  def test_with_row_index_survives_parquet_sink
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.parquet")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), output: output,
+                               with_row_index: "row_nr", streaming: true)
+
+      written = Polars.read_parquet(output)
+      assert_equal "row_nr", written.columns.first
+      assert_equal (0..9).to_a, written["row_nr"].to_a
+    end
+  end
+
+  def test_parse_with_row_index_does_not_swallow_file
+    basic = File.join(@fixtures_path, "basic.csv")
+    args = TabularTool::CLI.parse_args(["--with-row-index", basic])
+
+    assert_equal basic, args[:file]
+    assert_equal "row_nr", args[:with_row_index]
+  end
+
+  def test_with_row_index_name_without_file_is_not_the_input
+    args = TabularTool::CLI.parse_args(["--with-row-index", "idx"])
+    assert_equal "idx", args[:with_row_index]
+    assert_nil args[:file]
+
+    error = assert_raises(TabularTool::Error) { TabularTool::CLI.execute(**args) }
+    assert_match(/No input file specified/, error.message)
+  end
+
+  def test_cumsum_runs_after_sort
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(["head", "3", "-k", "age", "--select", "name,age", "--cumsum", "age:total",
+                                          File.join(@fixtures_path, "basic.csv")])
+      result = TabularTool::CLI.execute(**args)
+
+      assert_equal "name,age,total\nBob,25,25\nIvy,26,51\nFrank,27,78\n", result
+    end
+  end
+
+  def test_parse_sample_by_balanced
+    args = TabularTool::CLI.parse_args(["sample", "100", "--sample-by", "class,region", "--balanced", "data.csv"])
+
+    assert_equal ["class", "region"], args[:sample_by]
+    assert args[:balanced]
+    assert_equal 100, args[:sample_n]
+  end
+
+  def test_drop_nulls_considers_selected_columns
+    file = File.join(@fixtures_path, "lint_test.csv")
+
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["--drop-nulls-on", "city", file]))
+      assert_equal 9, result.lines.length - 1
+      refute_match(/^Eve,/, result)
+
+      result = TabularTool::CLI.execute(command: :cat, file: file, drop_nulls: true)
+      assert_equal 8, result.lines.length - 1
+
+      # email (null for Charlie) isn't selected, so only Eve's missing city counts
+      result = TabularTool::CLI.execute(command: :cat, file: file, select: ["name", "city"], drop_nulls: true)
+      assert_equal 9, result.lines.length - 1
+    end
+  end
+
+  def test_parse_trim
+    basic = File.join(@fixtures_path, "basic.csv")
+    assert_equal :all, TabularTool::CLI.parse_args(["--trim", basic])[:trim]
+    assert_equal basic, TabularTool::CLI.parse_args(["--trim", basic])[:file]
+    assert_equal ["city", "name"], TabularTool::CLI.parse_args(["--trim", "city,name", "data.csv"])[:trim]
+  end
+
+  def test_trim_column_without_file_is_not_the_input
+    args = TabularTool::CLI.parse_args(["--trim", "city"])
+    assert_equal ["city"], args[:trim]
+    assert_nil args[:file]
+
+    error = assert_raises(TabularTool::Error) { TabularTool::CLI.execute(**args) }
+    assert_match(/No input file specified/, error.message)
+  end
+
+  def test_trim_runs_before_where
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(
+        command: :cat,
+        file: File.join(@fixtures_path, "lint_test.csv"),
+        trim: :all,
+        where: "age == '29'",
+      )
+
+      assert_equal 2, result.split("\n").length, "Header + the row whose age was '29 '"
+      assert_includes result, "Grace"
+    end
+  end
+
+  def test_lower_persists_to_output_file
+    Dir.mktmpdir do |dir|
+      out = File.join(dir, "out.parquet")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), lower: ["name"], output: out)
+
+      assert_equal "alice", TabularTool::Formats.read(out)["name"][0]
+    end
+  end
+
+  def test_sample_verbose_reports_method
+    $stdout.stub :tty?, false do
+      _, err = capture_io do
+        TabularTool::CLI.execute(
+          command: :sample,
+          file: File.join(@fixtures_path, "basic.csv"),
+          sample_n: 2,
+          sample_method: :full,
+          verbose: true,
+        )
+      end
+
+      assert_match(/sample: method=full/, err)
+    end
+  end
+
+  def test_columns_as_rows_prints_a_block_per_row
+    result = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["head", "2", "--columns-as-rows",
+                                                                     File.join(@fixtures_path, "basic.parquet")]))
+
+    assert_equal <<~TEXT.chomp, result
+      -[ RECORD 1 ]-
+      name   | Alice
+      age    | 30
+      city   | New York
+      score  | 85.5
+      status | active
+      -[ RECORD 2 ]-
+      name   | Bob
+      age    | 25
+      city   | Los Angeles
+      score  | 92.0
+      status | active
+    TEXT
+  end
+
+  def test_columns_as_rows_rejects_output_file
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), columns_as_rows: true,
+                               output: "out.csv")
+    end
+    assert_match(/--columns-as-rows only applies to terminal output/, error.message)
+  end
+
+  def test_tee_writes_the_printed_rows
+    Dir.mktmpdir do |dir|
+      parquet = File.join(@fixtures_path, "basic.parquet")
+      [["head", "3"], ["tail", "2"], ["sample", "4", "--seed", "7"], ["cat", "-k", "age"]].each do |command|
+        tee = File.join(dir, "#{command.first}.csv")
+        result = $stdout.stub :tty?, false do
+          TabularTool::CLI.execute(**TabularTool::CLI.parse_args([*command, "--tee", tee, parquet]))
+        end
+
+        assert_equal File.read(tee).chomp, result.chomp
+      end
+      assert_equal ["Alice", "Bob", "Charlie"], TabularTool::Formats.read(File.join(dir, "head.csv"))["name"].to_a
+    end
+  end
+
+  def test_tee_rejects_output_file
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), tee: "a.csv", output: "b.csv")
+    end
+    assert_match(/use -o alone/, error.message)
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :stats, file: File.join(@fixtures_path, "basic.csv"), tee: "a.csv")
+    end
+    assert_match(/--tee only applies to/, error.message)
+  end
+
+  def test_peek_elides_middle_rows
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "big.parquet")
+      Polars::DataFrame.new({ "id" => (1..1000).to_a, "label" => (1..1000).map { |i| "row#{i}" } }).write_parquet(path)
+
+      result = TabularTool::CLI.execute(command: :peek, file: path, rows: 3)
+      lines = result.split("\n")
+
+      assert_equal "1000 rows × 2 cols", lines[0]
+      assert_match(/\Aid\s+label\z/, lines[1])
+      assert_match(/\AInt64\s+String\z/, lines[2])
+      assert_match(/\A1\s+row1\z/, lines[3])
+      assert_match(/\A…\s+…\z/, lines[6])
+      assert_match(/\A1000\s+row1000\z/, lines.last)
+      refute_includes result, "row500"
+    end
+  end
+
+  def test_peek_honors_select_and_where
+    result = TabularTool::CLI.execute(
+      command: :peek,
+      file: File.join(@fixtures_path, "basic.csv"),
+      select: ["name"],
+      where: "age > 30",
+    )
+    lines = result.split("\n")
+
+    assert_equal "4 rows × 1 cols", lines[0]
+    refute_includes result, "…"
+    assert_equal ["Charlie", "Eve", "Henry", "Jack"], lines[3..].map(&:strip)
+  end
+
+  def test_parse_limit_bytes
+    assert_equal 10 * 1024 * 1024, TabularTool::CLI.parse_args(["--limit-bytes", "10MB", "a.csv"])[:limit_bytes]
+    assert_equal 512 * 1024, TabularTool::CLI.parse_args(["--limit-bytes", "512K", "a.csv"])[:limit_bytes]
+    assert_equal 1500, TabularTool::CLI.parse_args(["--limit-bytes", "1500", "a.csv"])[:limit_bytes]
+    assert_raises(TabularTool::Error) { TabularTool::CLI.parse_args(["--limit-bytes", "lots", "a.csv"]) }
+  end
+
+  def test_max_memory_streams_or_refuses_in_memory_steps
+    file = File.join(@fixtures_path, "basic.csv")
+    assert_equal 4 * 1024**3, TabularTool::CLI.parse_args(["--max-memory", "4G", "a.csv"])[:max_memory]
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: file, sort_keys: ["age"], output: "unused.parquet", max_memory: 100)
+    end
+    assert_match(/basic\.csv needs an estimated .* over --max-memory 100 B, and these steps/, error.message)
+    assert_match(%r{^  transform: -k/--key sorts}, error.message)
+
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.parquet")
+      _, err = capture_io do
+        TabularTool::CLI.execute(command: :cat, file: file, where: "age > 30", output: output, max_memory: 100)
+      end
+
+      assert_match(/; streaming it$/, err)
+      assert_equal 4, Polars.read_parquet(output).height
+    end
+
+    # Within budget, nothing changes
+    _, err = capture_io { TabularTool::CLI.execute(command: :count, file: file, max_memory: 1024**3) }
+    assert_empty err
+  end
+
+  def test_tail_fast_path_conditions
+    csv = File.join(@fixtures_path, "basic.csv")
+
+    assert TabularTool::CLI.send(:tail_fast_path?, csv, :tail, { where: "age > 30" })
+    assert TabularTool::CLI.send(:tail_fast_path?, File.join(@fixtures_path, "basic.parquet"), :tail, {})
+    refute TabularTool::CLI.send(:tail_fast_path?, csv, :tail, { sort_keys: ["age"] })
+    refute TabularTool::CLI.send(:tail_fast_path?, csv, :tail, { streaming: false })
+    refute TabularTool::CLI.send(:tail_fast_path?, csv, :head, {})
+    refute TabularTool::CLI.send(:tail_fast_path?, File.join(@fixtures_path, "basic.csv.gz"), :tail, {})
+  end
+
+  def test_jsonl_input_is_scanned_lazily
+    jsonl = File.join(@fixtures_path, "basic.jsonl")
+
+    assert TabularTool::CLI.send(:lazy_jsonl?, jsonl, {})
+    refute TabularTool::CLI.send(:lazy_jsonl?, jsonl, { in_place: true })
+    refute TabularTool::CLI.send(:lazy_jsonl?, File.join(@fixtures_path, "basic.json"), {})
+
+    $stdout.stub :tty?, false do
+      csv = TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["head", "3", "--where", "age > 26",
+                                                                    File.join(@fixtures_path, "basic.csv")]))
+      assert_equal csv, TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["head", "3", "--where", "age > 26", jsonl]))
+      assert_equal "10", TabularTool::CLI.execute(command: :count, file: jsonl)
+    end
+  end
+
+  def test_tail_of_large_generated_csv
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "large.csv")
+      File.open(path, "w") do |f|
+        f.puts "id,value"
+        100_000.times { |i| f.puts "#{i},v#{i}" }
+      end
+
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(command: :tail, file: path, limit: 3)
+
+        assert_equal ["id,value", "99997,v99997", "99998,v99998", "99999,v99999"], result.split("\n")
+      end
+    end
+  end
+
+  def test_parse_rows_flag
+    assert_equal 3, TabularTool::CLI.parse_args(["head", "-n", "3", "data.csv"])[:limit]
+    assert_equal 7, TabularTool::CLI.parse_args(["tail", "--rows", "7", "data.csv"])[:limit]
+
+    args = TabularTool::CLI.parse_args(["sample", "-n", "4", "data.csv"])
+    assert_equal 4, args[:sample_n]
+    assert_nil args[:sample_fraction]
+  end
+
+  def test_parse_rows_flag_overrides_positional
+    args = nil
+    _, err = capture_io do
+      args = TabularTool::CLI.parse_args(["head", "10", "-n", "2", "data.csv"])
+    end
+
+    assert_equal 2, args[:limit]
+    assert_match(/-n 2 overrides positional count 10/, err)
+  end
+
+  def test_parse_numeric_filename_is_treated_as_file
+    Dir.mktmpdir do |dir|
+      Dir.chdir(dir) do
+        File.write("2024", "id\n1\n")
+        args = nil
+        _, err = capture_io do
+          args = TabularTool::CLI.parse_args(["head", "2024"])
+        end
+
+        assert_equal "2024", args[:file]
+        assert_equal 10, args[:limit]
+        assert_match(/treating '2024' as a file/, err)
+      end
+    end
+  end
+
+  def test_parse_n_flag_with_numeric_filename
+    Dir.mktmpdir do |dir|
+      Dir.chdir(dir) do
+        File.write("2024", "id\n1\n")
+        args = nil
+        capture_io do
+          args = TabularTool::CLI.parse_args(["tail", "--n", "3", "2024"])
+        end
+
+        assert_equal "2024", args[:file]
+        assert_equal 3, args[:limit]
+      end
+    end
+  end
+
+  def test_parse_positional_count_still_works
+    args = TabularTool::CLI.parse_args(["head", "10", "data.csv"])
+
+    assert_equal 10, args[:limit]
+    assert_equal "data.csv", args[:file]
+  end
+
+  def test_input_format_overrides_missing_extension
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "export")
+      File.write(path, "id\tname\n1\tAlice\n2\tBob\n")
+
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(command: :cat, file: path, input_format: :tsv)
+        assert_equal "id,name\n1,Alice\n2,Bob", result.strip
+      end
+    end
+  end
+
+  def test_input_format_for_compressed_file_without_inner_extension
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "data.gz")
+      FileUtils.cp(File.join(@fixtures_path, "basic.tsv.gz"), path)
+
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(command: :head, file: path, input_format: :tsv, limit: 2)
+        assert_equal "name,age,city,score,status", result.split("\n").first
+      end
+    end
+  end
+
+  def test_archive_with_dotted_name_reads_as_csv
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "archive.2024.csv.gz")
+      FileUtils.cp(File.join(@fixtures_path, "basic.csv.gz"), path)
+
+      assert_equal "10", TabularTool::CLI.execute(command: :count, file: path)
+    end
+  end
+
+  def test_parse_parse_dates_flags
+    assert_nil TabularTool::CLI.parse_args(["data.csv"])[:parse_dates]
+    assert_equal true, TabularTool::CLI.parse_args(["--parse-dates", "data.csv"])[:parse_dates]
+    assert_equal false, TabularTool::CLI.parse_args(["--no-parse-dates", "data.csv"])[:parse_dates]
+  end
+
+  def test_parse_if_empty
+    args = TabularTool::CLI.parse_args(["--if-empty", "warn", "data.csv"])
+    assert_equal :warn, args[:if_empty]
+  end
+
+  def test_output_directory_writes_input_basename
+    Dir.mktmpdir do |dir|
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv.gz"), output: dir)
+
+      path = File.join(dir, "basic.csv")
+      assert File.exist?(path)
+      assert_equal 10, TabularTool::Formats.read(path).height
+    end
+  end
+
+  def test_output_trailing_slash_creates_directory
+    Dir.mktmpdir do |dir|
+      out = File.join(dir, "converted") + "/"
+      TabularTool::CLI.execute(command: :head, file: File.join(@fixtures_path, "basic.parquet"), output: out, limit: 3)
+
+      assert_equal 3, TabularTool::Formats.read(File.join(dir, "converted", "basic.parquet")).height
+    end
+  end
+
+  def test_count_verbose_lists_columns_and_dtypes
+    result = TabularTool::CLI.execute(
+      command: :count,
+      file: File.join(@fixtures_path, "basic.csv"),
+      verbose: true,
+    )
+
+    lines = result.split("\n")
+    assert_equal "10", lines.first
+    assert_equal "  name: String", lines[1]
+    assert_equal "  age: Int64", lines[2]
+    assert_equal 6, lines.length
+  end
+
+  def test_count_verbose_layout_matches_across_formats
+    outputs = ["basic.csv", "basic.parquet", "basic.jsonl"].map do |name|
+      TabularTool::CLI.execute(command: :count, file: File.join(@fixtures_path, name), verbose: true)
+    end
+
+    assert_equal 1, outputs.uniq.length, "Expected identical output, got: #{outputs.inspect}"
+  end
+
+  def test_count_without_verbose_is_single_line
+    result = TabularTool::CLI.execute(command: :count, file: File.join(@fixtures_path, "basic.parquet"))
+
+    assert_equal "10", result
+  end
+
+  def test_count_output_writes_a_table
+    Dir.mktmpdir do |dir|
+      input = File.join(@fixtures_path, "basic.parquet")
+      csv = File.join(dir, "counts.csv")
+
+      assert_nil TabularTool::CLI.execute(command: :count, file: input, output: csv)
+      assert_equal "rows,cols,path\n10,5,#{input}\n", File.read(csv)
+
+      parquet = File.join(dir, "counts.parquet")
+      TabularTool::CLI.execute(command: :count, file: File.join(@fixtures_path, "basic.csv"), where: "age > 30", output: parquet)
+      expected = [{ "rows" => 4, "cols" => 5, "path" => File.join(@fixtures_path, "basic.csv") }]
+      assert_equal expected, Polars.read_parquet(parquet).rows(named: true)
+    end
+  end
+
+  def test_lint_output_json_by_extension
+    Dir.mktmpdir do |dir|
+      json = File.join(dir, "report.json")
+      text = File.join(dir, "report.txt")
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), output: json)
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), output: text)
+
+      assert_equal true, JSON.parse(File.read(json))["passed"]
+      assert_includes File.read(text), "✓ All checks passed"
+    end
+  end
+
+  def test_lint_output_json_by_format
+    Dir.mktmpdir do |dir|
+      upper = File.join(dir, "REPORT.JSON")
+      bare = File.join(dir, "report")
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), output: upper)
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), output: bare,
+                               output_format: :json)
+
+      assert_equal true, JSON.parse(File.read(upper))["passed"]
+      assert_equal true, JSON.parse(File.read(bare))["passed"]
+    end
+  end
+
   # Test lint command
   def test_lint_compressed_file
     result = TabularTool::CLI.execute(
@@ -668,6 +1574,11 @@ class TestCLI < Minitest::Test
     assert args[:unique]
   end
 
+  def test_parse_no_stable_flag
+    args = TabularTool::CLI.parse_args(["--unique", "--no-stable", "file.csv"])
+    assert_equal false, args[:stable]
+  end
+
   def test_parse_unique_on_columns
     args = TabularTool::CLI.parse_args(["--unique-on", "email,user_id", "file.csv"])
     assert_equal ["email", "user_id"], args[:unique_on]
@@ -803,6 +1714,24 @@ class TestCLI < Minitest::Test
     assert result.include?("age") || result.include?("column")
   end
 
+  def test_stats_on_several_files_writes_one_tagged_table
+    basic = File.join(@fixtures_path, "basic.csv")
+    other = File.join(@fixtures_path, "basic.parquet")
+
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "stats.csv")
+      args = TabularTool::CLI.parse_args(["stats", basic, other, "-o", output])
+      assert_equal [basic, other], args[:stats_inputs]
+      TabularTool::CLI.execute(**args)
+
+      table = Polars.read_csv(output, infer_schema_length: 0)
+      assert_equal ["file", "column"], table.columns.first(2)
+      assert_equal [basic] * 5 + [other] * 5, table["file"].to_a
+      assert_equal ["name", "age", "city", "score", "status"] * 2, table["column"].to_a
+      assert_equal [10.0, 10.0], table.filter(Polars.col("column") == "age")["count"].to_a.map(&:to_f)
+    end
+  end
+
   def test_execute_count_command
     result = TabularTool::CLI.execute(
       command: :count,
@@ -976,4 +1905,236 @@ class TestCLI < Minitest::Test
     assert lines[0].include?(","), "Header should be CSV format"
     assert lines[1].include?(","), "Data should be CSV format"
   end
+
+  def test_convert_csv_to_parquet_and_parquet_to_jsonl
+    Dir.mktmpdir do |dir|
+      parquet = File.join(dir, "basic.parquet")
+      summary = TabularTool::CLI.execute(command: :convert, file: File.join(@fixtures_path, "basic.csv"), output: parquet)
+
+      assert_match(%r{basic\.csv \([\d.]+ \w*B\) -> .*basic\.parquet \([\d.]+ \w*B\): 10 rows, ratio [\d.]+x\z}, summary)
+      assert_equal 10, Polars.read_parquet(parquet).height
+
+      jsonl = File.join(dir, "adults.jsonl")
+      summary = TabularTool::CLI.execute(command: :convert, file: parquet, output: jsonl, where: "age > 30")
+
+      assert_match(/: 4 rows/, summary)
+      assert_equal 4, File.readlines(jsonl).length
+    end
+  end
+
+  def test_convert_fails_when_output_row_count_differs
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.parquet")
+      error = TabularTool::CLI.stub(:output_row_count, 7) do
+        assert_raises(TabularTool::Error) do
+          TabularTool::CLI.execute(command: :convert, file: File.join(@fixtures_path, "basic.csv"), output: output)
+        end
+      end
+
+      assert_match(/out\.parquet has 7 rows but .*basic\.csv has 10; the output is incomplete/, error.message)
+    end
+  end
+
+  def test_convert_refuses_to_guess_formats
+    Dir.mktmpdir do |dir|
+      input = File.join(dir, "export.dat")
+      File.write(input, "a,b\n1,2\n")
+
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :convert, file: input, output: File.join(dir, "out.parquet"))
+      end
+      assert_match(/pass --from FORMAT/, error.message)
+
+      summary = TabularTool::CLI.execute(command: :convert, file: input, output: File.join(dir, "out"),
+                                         input_format: :csv, output_format: :parquet)
+      assert_match(/: 1 rows/, summary)
+    end
+
+    args = TabularTool::CLI.parse_args(["convert", "in.csv", "out.parquet"])
+    assert_equal ["in.csv", "out.parquet"], args.values_at(:file, :output)
+
+    args = TabularTool::CLI.parse_args(["convert", "--output-template", "out/{stem}.parquet", "a.csv", "b.csv"])
+    assert_equal ["a.csv", "b.csv"], args[:convert_inputs]
+  end
+
+  def test_convert_output_template_writes_one_file_per_input
+    Dir.mktmpdir do |dir|
+      inputs = [File.join(@fixtures_path, "basic.csv"), File.join(@fixtures_path, "basic.tsv")]
+      template = File.join(dir, "out", "{stem}_{name}.parquet")
+
+      assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :convert, file: inputs.first, convert_inputs: inputs,
+                                 output_template: File.join(dir, "all.parquet"))
+      end
+
+      summary = TabularTool::CLI.execute(command: :convert, file: inputs.first, convert_inputs: inputs, output_template: template)
+      assert_equal 2, summary.lines.length
+      assert File.exist?(File.join(dir, "out", "basic_basic.csv.parquet"))
+      assert File.exist?(File.join(dir, "out", "basic_basic.tsv.parquet"))
+    end
+  end
+
+  def test_version_reports_polars_and_formats
+    assert_equal :version, TabularTool::CLI.parse_args(["version"])[:command]
+
+    report = TabularTool::CLI.execute(command: :version)
+
+    assert_equal "tt #{TabularTool::VERSION}", report.lines.first.chomp
+    assert_includes report, "polars-df #{Polars::VERSION}"
+    assert_includes report, "formats: csv, tsv, parquet, json, jsonl, ipc"
+    assert_match(/^unavailable: orc/, report)
+    assert_match(/^shell tools: gzip (found|missing), zstd (found|missing)$/, report)
+  end
+
+  # Query plan explanation
+  def test_explain_shows_predicate_pushed_into_parquet_scan
+    plan = TabularTool::CLI.execute(
+      command: :cat,
+      file: File.join(@fixtures_path, "basic.parquet"),
+      where: "age > 30",
+      explain: true,
+    )
+
+    assert_match(/SCAN/i, plan)
+    assert_match(/SELECTION|FILTER/, plan)
+    refute_match(/Alice/, plan, "Explain should not print data")
+  end
+
+  def test_explain_analyze_reports_rows
+    output = TabularTool::CLI.execute(
+      command: :head,
+      file: File.join(@fixtures_path, "basic.csv"),
+      limit: 3,
+      explain: true,
+      analyze: true,
+    )
+
+    assert_match(/Executed in [\d.]+ ms, 3 rows produced/, output)
+  end
+
+  def test_explain_rejects_unsupported_command
+    assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(
+        command: :lint,
+        file: File.join(@fixtures_path, "basic.csv"),
+        explain: true,
+      )
+    end
+  end
+
+  def test_explain_streaming_confirms_scan_to_sink
+    Dir.mktmpdir do |dir|
+      output = TabularTool::CLI.execute(
+        command: :cat,
+        file: File.join(@fixtures_path, "basic.parquet"),
+        where: "age > 30",
+        output: File.join(dir, "out.parquet"),
+        streaming: true,
+        explain_streaming: true,
+      )
+
+      assert output.start_with?("streaming: yes")
+      assert_match(/^plan:\n  .*SCAN/mi, output)
+      refute File.exist?(File.join(dir, "out.parquet")), "Nothing should be written"
+    end
+  end
+
+  def test_explain_streaming_names_in_memory_steps
+    output = TabularTool::CLI.execute(
+      command: :cat,
+      file: File.join(@fixtures_path, "basic.json"),
+      unique: true,
+      explain_streaming: true,
+    )
+
+    assert_match(/\Astreaming: no \(3 in-memory steps\)/, output)
+    assert_match(/^  read: JSON arrays have no lazy reader/, output)
+    assert_match(/^  transform: --unique/, output)
+    assert_match(/^  output: stdout output is collected/, output)
+  end
+
+  def test_explain_streaming_names_window_and_tail_steps
+    parquet = File.join(@fixtures_path, "basic.parquet")
+    {
+      ["--offset=-3"] => /^  transform: --offset -N keeps the last rows/,
+      ["--cumsum", "age", "--over", "city"] => %r{^  transform: --cumsum/--diff/--lag --over},
+      ["--with", "sum(age) OVER (ORDER BY score) AS running"] => /^  transform: --with \.\.\. OVER \(ORDER BY/,
+      ["--with", "sum(age) OVER (PARTITION BY city) AS total"] => /^  transform: --with \.\.\. OVER \(\.\.\.\)/,
+    }.each do |flags, step|
+      Dir.mktmpdir do |dir|
+        args = TabularTool::CLI.parse_args(["cat", "--streaming", "--explain-streaming", *flags, parquet,
+                                            "-o", File.join(dir, "out.parquet")])
+        output = TabularTool::CLI.execute(**args)
+
+        assert_match(/\Astreaming: no \(1 in-memory step\)/, output, flags.join(" "))
+        assert_match(step, output, flags.join(" "))
+      end
+    end
+
+    # The same steps stop --max-memory from streaming a plan that would hold the input anyway
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), offset: -3,
+                               output: "unused.parquet", max_memory: 100)
+    end
+    assert_match(/^  transform: --offset -N/, error.message)
+
+    # --limit-bytes reads the input in bounded rounds of head(k), so it still streams
+    Dir.mktmpdir do |dir|
+      args = TabularTool::CLI.parse_args(["cat", "--streaming", "--explain-streaming", "--limit-bytes", "1K", parquet,
+                                          "-o", File.join(dir, "out.csv")])
+      assert TabularTool::CLI.execute(**args).start_with?("streaming: yes")
+    end
+  end
+
+  # Arrow IPC piping between tt invocations
+  def test_arrow_pipe_preserves_datetime_dtype
+    Tempfile.create(["events", ".parquet"]) do |tmp|
+      Polars::DataFrame.new({
+        "id" => [1, 2, 3],
+        "ts" => [Time.utc(2024, 1, 1), Time.utc(2024, 1, 2), Time.utc(2024, 1, 3)],
+      }).write_parquet(tmp.path)
+
+      produced = TabularTool::CLI.execute(command: :cat, file: tmp.path, pipe_format: :arrow)
+      assert_equal Encoding::BINARY, produced.encoding
+
+      consumed = with_stdin(produced) do
+        TabularTool::CLI.execute(command: :head, file: "-", limit: 2, pipe_format: :arrow)
+      end
+
+      df = Polars.read_ipc_stream(StringIO.new(consumed))
+      assert_equal 2, df.height
+      assert df["ts"].dtype == Polars::Datetime, "Datetime dtype should survive the pipe"
+    end
+  end
+
+  def test_stdin_arrow_stream_is_auto_detected
+    df = Polars::DataFrame.new({"name" => ["Alice", "Bob"], "age" => [30, 25]})
+    bytes = TabularTool::Formats.write_to_stdout(df, format: :arrow)
+
+    result = with_stdin(bytes) do
+      TabularTool::CLI.execute(command: :cat, file: "-", pretty: false)
+    end
+
+    lines = result.split("\n")
+    assert_equal "name,age", lines[0]
+    assert_equal "Alice,30", lines[1]
+  end
+
+  def test_stdin_csv_input
+    result = with_stdin("name,age\nAlice,30\nBob,25\n") do
+      TabularTool::CLI.execute(command: :tail, file: "-", limit: 1, pretty: false)
+    end
+
+    assert_equal "name,age\nBob,25", result.strip
+  end
+
+  private
+
+  def with_stdin(content)
+    original_stdin = $stdin
+    $stdin = StringIO.new(content)
+    yield
+  ensure
+    $stdin = original_stdin
+  end
 end
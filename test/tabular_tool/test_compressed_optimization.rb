@@ -87,6 +87,15 @@ class TestCompressedOptimization < Minitest::Test
     )
   end
 
+  def test_should_not_use_shell_decompression_with_trim
+    refute TabularTool::CLI.send(
+      :should_use_shell_decompression?,
+      "file.csv.gz",
+      :head,
+      { trim: :all },
+    )
+  end
+
   def test_should_not_use_shell_decompression_with_streaming
     refute TabularTool::CLI.send(
       :should_use_shell_decompression?,
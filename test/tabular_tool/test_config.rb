@@ -0,0 +1,89 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "tempfile"
+
+class TestConfig < Minitest::Test
+  def with_config(text)
+    Tempfile.create(["config", ".toml"]) do |f|
+      f.write(text)
+      f.flush
+      yield f.path
+    end
+  end
+
+  def test_parse_values
+    values = TabularTool::Config.parse(<<~TOML)
+      # display defaults
+      compression = "gzip"  # trailing comment
+      delimiter = "#"
+      pretty = false
+      streaming = true
+      output_delimiter = ';'
+    TOML
+
+    assert_equal({
+      "compression" => "gzip",
+      "delimiter" => "#",
+      "pretty" => false,
+      "streaming" => true,
+      "output-delimiter" => ";",
+    }, values)
+  end
+
+  def test_parse_invalid_line_raises_error
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Config.parse("compression zstd\n", "tt.toml")
+    end
+    assert_match(/tt\.toml:1/, error.message)
+  end
+
+  def test_to_argv
+    argv = TabularTool::Config.to_argv({ "compression" => "gzip", "pretty" => false, "streaming" => true })
+
+    assert_equal ["--compression", "gzip", "--no-pretty", "--streaming"], argv
+  end
+
+  def test_cli_applies_config_defaults
+    with_config("compression = \"gzip\"\npretty = false\n") do |path|
+      args = TabularTool::CLI.parse_args(["head", "--config", path, "data.csv"])
+
+      assert_equal :head, args[:command]
+      assert_equal "gzip", args[:compression]
+      assert_equal false, args[:pretty]
+      assert_equal "data.csv", args[:file]
+    end
+  end
+
+  def test_cli_flags_override_config
+    with_config("compression = \"gzip\"\n") do |path|
+      args = TabularTool::CLI.parse_args(["--config=#{path}", "-c", "lz4", "data.csv"])
+
+      assert_equal "lz4", args[:compression]
+    end
+  end
+
+  def test_cli_unknown_config_key_raises_error
+    with_config("no-such-option = 1\n") do |path|
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.parse_args(["--config", path, "data.csv"])
+      end
+      assert_match(/Invalid config/, error.message)
+    end
+  end
+
+  def test_cli_missing_explicit_config_raises_error
+    assert_raises(TabularTool::Error) do
+      TabularTool::CLI.parse_args(["--config", "/nonexistent/tt.toml", "data.csv"])
+    end
+  end
+
+  def test_cli_missing_default_config_changes_nothing
+    TabularTool::Config.stub(:default_path, "/nonexistent/tt.toml") do
+      args = TabularTool::CLI.parse_args(["data.csv"])
+
+      assert_nil args[:compression]
+      assert_nil args[:pretty]
+    end
+  end
+end
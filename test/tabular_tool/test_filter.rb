@@ -1,6 +1,8 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "tmpdir"
+require "date"
 
 class TestFilter < Minitest::Test
   def setup
@@ -181,4 +183,265 @@ class TestFilter < Minitest::Test
     names = filtered["name"].to_a
     assert names.all? { |n| n.start_with?("A") || n.end_with?("e") }
   end
+
+  def test_expr_filter_compares_columns_with_arithmetic
+    df = Polars::DataFrame.new({ "revenue" => [10, 50, 30, nil], "cost" => [8, 20, 15, 1] })
+
+    filtered = TabularTool::Operations::ExprFilter.call(df, expression: "revenue >= cost * 2 && !(cost == 15)")
+    assert_equal [50], filtered["revenue"].to_a
+
+    filtered = TabularTool::Operations::ExprFilter.call(df, expression: "revenue.is_null() || abs(revenue - cost) < 3")
+    assert_equal [10, nil], filtered["revenue"].to_a
+  end
+
+  def test_expr_filter_string_methods_and_quoted_columns
+    df = Polars::DataFrame.new({ "full name" => ["Alice Smith", "bob jones"], "age" => [30, 40] })
+
+    filtered = TabularTool::Operations::ExprFilter.call(df, expression: "col(\"full name\").lower().starts_with('bob') && age % 20 == 0")
+    assert_equal ["bob jones"], filtered["full name"].to_a
+  end
+
+  def test_expr_filter_rejects_unsupported_constructs
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::ExprFilter.call(@df, expression: "age > sqrt(score)")
+    end
+    assert_match(/Unsupported function "sqrt".*position 7/, error.message)
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::ExprFilter.call(@df, expression: "age > 30 &")
+    end
+    assert_match(/unexpected character "&" at position 10/, error.message)
+  end
+
+  def test_filter_iso_date_literal_against_datetime_column
+    df = Polars::DataFrame.new({
+      "ts" => [Time.utc(2023, 12, 31, 23), Time.utc(2024, 1, 1, 8), Time.utc(2024, 2, 1)],
+      "id" => [1, 2, 3],
+    })
+
+    assert_equal [2, 3], TabularTool::Operations::Filter.call(df, expression: "ts >= '2024-01-01'")["id"].to_a
+
+    Dir.mktmpdir do |dir|
+      parquet = File.join(dir, "events.parquet")
+      df.write_parquet(parquet)
+      lazy = TabularTool::Formats.read(parquet, streaming: true)
+      assert_equal [2, 3], TabularTool::Operations::Filter.call(lazy, expression: "ts >= '2024-01-01'").collect["id"].to_a
+
+      csv = File.join(dir, "events.csv")
+      File.write(csv, "ts,id\n2023-12-31 23:00:00,1\n2024-01-01 08:00:00,2\n2024-02-01 00:00:00,3\n")
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(command: :cat, file: csv, where: "ts < '2024-01-01 09:00'", parse_dates: true,
+                                          select: ["id"])
+        assert_equal "id\n1\n2\n", result
+      end
+    end
+  end
+
+  def test_filter_relative_dates
+    today = Date.today
+    df = Polars::DataFrame.new({ "day" => [today - 2, today - 1, today, today + 1], "id" => [1, 2, 3, 4] })
+
+    assert_equal [2, 3, 4], TabularTool::Operations::Filter.call(df, expression: "day >= yesterday")["id"].to_a
+    assert_equal [1, 2], TabularTool::Operations::Filter.call(df, expression: "day < today")["id"].to_a
+  end
+
+  def test_filter_date_column_against_literals
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "signups.csv")
+      File.write(path, "id,signup_date\n1,2023-12-30\n2,2024-01-01\n3,2024-01-15\n4,2024-03-01\n")
+      df = TabularTool::Formats.read(path, parse_dates: true)
+      assert TabularTool::Dtypes.kind?(df.schema["signup_date"], Polars::Date)
+
+      ids = ->(expression) { TabularTool::Operations::Filter.call(df, expression: expression)["id"].to_a }
+      assert_equal [3, 4], ids.call("signup_date > '2024-01-01'")
+      assert_equal [2], ids.call("signup_date == '2024-01-01'")
+      assert_equal [2, 3, 4], ids.call("'2024-01-01' <= signup_date")
+      assert_equal [2, 3], ids.call("signup_date BETWEEN '2024-01-01' AND '2024-01-31'")
+      assert_equal [1, 4], ids.call("signup_date NOT BETWEEN '2024-01-01' AND '2024-01-31'")
+    end
+  end
+
+  def test_date_translation_only_touches_temporal_columns
+    schema = { "ts" => Polars::Datetime.new("us"), "name" => Polars::String }
+    now = Time.utc(2024, 3, 10, 12, 0, 0)
+
+    sql = TabularTool::Operations::DslToSql.new("ts >= now - interval '7 days'", schema: schema, now: now).translate
+    assert_equal "ts >= CAST('2024-03-03 12:00:00' AS TIMESTAMP)", sql
+
+    sql = TabularTool::Operations::DslToSql.new("name == '2024-01-01'", schema: schema, now: now).translate
+    assert_equal "name = '2024-01-01'", sql
+
+    sql = TabularTool::Operations::DslToSql.new("'2024-01-01' < name", schema: schema, now: now).translate
+    assert_equal "'2024-01-01' < name", sql
+  end
+
+  def test_where_null_and_not_null
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
+
+    assert_equal ["Eve"], TabularTool::Operations::Filter.membership(df, null: ["city"])["name"].to_a
+    kept = TabularTool::Operations::Filter.membership(df, not_null: ["city", "email"])
+    refute_includes kept["name"].to_a, "Eve"
+    refute_includes kept["name"].to_a, "Charlie"
+    assert_equal 8, kept.height
+  end
+
+  def test_where_in_parses_values_to_numeric_dtype
+    filtered = TabularTool::Operations::Filter.membership(@df, in_values: [["age", ["25", "35"]]])
+    assert_equal ["Bob", "Charlie"], filtered["name"].to_a
+
+    filtered = TabularTool::Operations::Filter.membership(@df, in_values: [["score", ["92", "94.5"]]])
+    assert_equal ["Bob", "Ivy"], filtered["name"].to_a
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Filter.membership(@df, in_values: [["age", ["25", "old"]]])
+    end
+    assert_match(/don't all parse as Int64/, error.message)
+  end
+
+  def test_where_in_value_containing_comma
+    assert_equal ["NYC", "Washington, D.C.", "LA"], TabularTool::Operations::Filter.split_values(%(NYC, "Washington, D.C.",LA))
+    assert_equal ["it's", "a,b"], TabularTool::Operations::Filter.split_values(%('it\\'s', 'a,b'))
+    assert_raises(TabularTool::Error) { TabularTool::Operations::Filter.split_values(%("a"b,c)) }
+
+    df = Polars::DataFrame.new({ "city" => ["Washington, D.C.", "Washington", "LA"], "id" => [1, 2, 3] })
+    filtered = TabularTool::Operations::Filter.membership(df, in_values: [["city", ["Washington, D.C.", "LA"]]])
+    assert_equal [1, 3], filtered["id"].to_a
+  end
+
+  def test_where_helpers_combine_with_where
+    $stdout.stub :tty?, false do
+      args = TabularTool::CLI.parse_args(
+        ["--where", "age > 26", "--where-in", "city:'New York',Dallas,Houston", "--where-not-null", "status",
+         "--select", "name", File.join(@fixtures_path, "basic.csv")],
+      )
+      assert_equal "name\nAlice\nDiana\n", TabularTool::CLI.execute(**args)
+    end
+  end
+
+  def test_where_in_unknown_column
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Filter.membership(@df, in_values: [["town", ["x"]]])
+    end
+    assert_match(/Unknown column\(s\) in --where-in: town/, error.message)
+  end
+
+  def test_keep_and_drop_keys_file
+    Dir.mktmpdir do |dir|
+      keys = File.join(dir, "names.txt")
+      File.write(keys, "Alice\nBob\n\nBob\nZed\n")
+
+      $stdout.stub :tty?, false do
+        kept = TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), select: ["name"],
+                                        keep_keys_file: keys, key_column: "name")
+        assert_equal "name\nAlice\nBob\n", kept
+
+        dropped = TabularTool::CLI.execute(command: :count, file: File.join(@fixtures_path, "basic.csv"),
+                                           drop_keys_file: keys, key_column: "name")
+        assert_equal "8", dropped
+      end
+
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), keep_keys_file: keys)
+      end
+      assert_match(/--key-column/, error.message)
+    end
+  end
+
+  def test_keys_are_cast_to_column_dtype
+    Dir.mktmpdir do |dir|
+      keys = File.join(dir, "ages.txt")
+      File.write(keys, "30\n31\n30\n")
+
+      loaded = TabularTool::Operations::Filter.read_keys(keys)
+      assert_equal ["30", "31"], loaded.to_a
+      filtered = TabularTool::Operations::Filter.by_keys(@df, column: "age", keys: loaded)
+      assert_equal ["Alice", "Henry"], filtered["name"].to_a
+
+      lazy = TabularTool::Formats.read(File.join(@fixtures_path, "basic.csv"), streaming: true)
+      dropped = TabularTool::Operations::Filter.by_keys(lazy, column: "age", keys: loaded, keep: false)
+      assert_kind_of Polars::LazyFrame, dropped
+      assert_equal 8, dropped.collect.height
+
+      File.write(keys, "30\nthirty\n")
+      assert_raises(TabularTool::Error) do
+        TabularTool::Operations::Filter.by_keys(@df, column: "age", keys: TabularTool::Operations::Filter.read_keys(keys))
+      end
+    end
+  end
+
+  def test_keys_from_parquet_file
+    Dir.mktmpdir do |dir|
+      keys = File.join(dir, "keys.parquet")
+      Polars::DataFrame.new({ "city" => ["Chicago", "Dallas", "Chicago", nil] }).write_parquet(keys)
+
+      loaded = TabularTool::Operations::Filter.read_keys(keys)
+      assert_equal ["Chicago", "Dallas"], loaded.to_a
+      assert_equal ["Charlie", "Ivy"], TabularTool::Operations::Filter.by_keys(@df, column: "city", keys: loaded)["name"].to_a
+
+      wide = File.join(dir, "wide.parquet")
+      Polars::DataFrame.new({ "id" => [1], "city" => ["Dallas"] }).write_parquet(wide)
+      assert_equal ["Dallas"], TabularTool::Operations::Filter.read_keys(wide, column: "city").to_a
+      assert_raises(TabularTool::Error) { TabularTool::Operations::Filter.read_keys(wide) }
+    end
+  end
+
+  def test_filter_ignore_case
+    df = Polars::DataFrame.new({ "city" => ["NYC", "nyc", "Boston", "LA"], "id" => [1, 2, 3, 4] })
+    filter = ->(expression) { TabularTool::Operations::Filter.call(df, expression: expression, ignore_case: true)["id"].to_a }
+
+    assert_equal [1, 2], filter.("city == 'Nyc'")
+    assert_equal [3, 4], filter.("city != 'nYC'")
+    assert_equal [3], filter.("city.contains('OST')")
+    assert_equal [1, 2], filter.("city LIKE 'N%'")
+    assert_equal [1], TabularTool::Operations::Filter.call(df, expression: "city == 'NYC'")["id"].to_a
+  end
+
+  def test_filter_ignore_case_leaves_other_columns_alone
+    schema = { "city" => Polars::String, "id" => Polars::Int64 }
+
+    sql = TabularTool::Operations::DslToSql.new("id == 2 && city == 'NYC'", schema: schema, ignore_case: true).translate
+    assert_equal "id = 2  AND  LOWER(city) = 'nyc'", sql
+
+    filtered = TabularTool::Operations::Filter.call(@df, expression: "age < 30 && score > 88", ignore_case: true)
+    assert_equal ["Bob", "Grace", "Ivy"], filtered["name"].to_a
+  end
+
+  def test_referenced_columns_skip_keywords_and_literals
+    columns = TabularTool::Operations::Filter.referenced_columns(
+      "city.contains('a = b') && ts >= now - interval '7 days' && email IS NOT NULL && name LIKE 'x%'",
+    )
+    assert_equal ["city", "ts", "email", "name"], columns
+  end
+
+  def test_where_errors_point_at_the_mistake
+    cases = {
+      "age > > 30" => ["two comparison operators in a row", 6, "e.g. age > 30"],
+      "status == 'active' && city == 'Chicago" => ["unterminated string", 30, "matching '"],
+      "age = = 30" => ["= = is not an operator", 6, "use == (or =)"],
+      "(age > 30" => ["unclosed (", 0, "add the missing )"],
+    }
+
+    cases.each do |expression, (problem, position, hint)|
+      error = assert_raises(TabularTool::Error) { TabularTool::Operations::Filter.call(@df, expression: expression) }
+      lines = error.message.lines.map(&:chomp)
+      assert_equal "Invalid --where: #{problem}", lines[0]
+      assert_equal "  #{expression}", lines[1]
+      assert_equal "  #{' ' * position}^", lines[2], expression
+      assert_includes lines[3], hint
+    end
+  end
+
+  def test_where_unquoted_string_suggests_quotes
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Filter.call(@df, expression: "city == San Diego && age > 30")
+    end
+    lines = error.message.lines.map(&:chomp)
+    assert_equal "Invalid --where: unquoted string San", lines[0]
+    assert_equal "  #{' ' * 8}^", lines[2]
+    assert_equal "  hint: quote string values: 'San Diego'", lines[3]
+
+    # Column-to-column comparisons and keywords are not literals
+    assert_equal 10, TabularTool::Operations::Filter.call(@df, expression: "age == age").height
+    assert_nil TabularTool::Operations::FilterDiagnosis.unquoted_literal("email = null", columns: ["email"])
+  end
 end
@@ -2,6 +2,8 @@
 
 require "test_helper"
 require "tempfile"
+require "tmpdir"
+require "json"
 
 class TestFormatWrite < Minitest::Test
   def setup
@@ -147,6 +149,49 @@ class TestFormatWrite < Minitest::Test
     end
   end
 
+  def test_write_parquet_with_row_group_size
+    df = Polars::DataFrame.new({ "a" => (1..1000).to_a })
+
+    Dir.mktmpdir do |dir|
+      default_path = File.join(dir, "default.parquet")
+      TabularTool::Formats.write(df, default_path)
+      assert_equal 1, TabularTool::ParquetFooter.row_groups(default_path)
+
+      tuned_path = File.join(dir, "tuned.parquet")
+      TabularTool::Formats.write(df, tuned_path, row_group_size: 100)
+      assert_equal 10, TabularTool::ParquetFooter.row_groups(tuned_path)
+      assert_equal df.height, TabularTool::Formats.read(tuned_path).height
+
+      sunk_path = File.join(dir, "sunk.parquet")
+      TabularTool::Formats.write(df.lazy, sunk_path, row_group_size: 100)
+      assert_operator TabularTool::ParquetFooter.row_groups(sunk_path), :>, 1
+    end
+  end
+
+  def test_cli_row_group_size_retunes_parquet
+    Dir.mktmpdir do |dir|
+      small = File.join(dir, "small.parquet")
+      TabularTool::Formats.write(Polars::DataFrame.new({ "a" => (1..600).to_a }), small, row_group_size: 50)
+      assert_equal 12, TabularTool::ParquetFooter.row_groups(small)
+
+      tuned = File.join(dir, "tuned.parquet")
+      TabularTool::CLI.execute(command: :cat, file: small, output: tuned, row_group_size: 300)
+
+      # The streaming sink may split a group at a batch boundary, but never merges past the size
+      assert_includes 2..3, TabularTool::ParquetFooter.row_groups(tuned)
+      assert_equal 600, TabularTool::Formats.read(tuned).height
+    end
+  end
+
+  def test_row_group_size_rejected_for_non_parquet_output
+    Dir.mktmpdir do |dir|
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::Formats.write(@df, File.join(dir, "out.csv"), row_group_size: 10)
+      end
+      assert_match(/--row-group-size only applies to Parquet output/, error.message)
+    end
+  end
+
   def test_write_to_stdout
     # Test writing to stdout (should return string)
     output = TabularTool::Formats.write_to_stdout(@df, format: :csv)
@@ -162,4 +207,305 @@ class TestFormatWrite < Minitest::Test
     tsv_output = TabularTool::Formats.write_to_stdout(@df, format: :tsv)
     assert tsv_output.include?("\t")  # TSV uses tabs
   end
+
+  def test_write_lazy_frame_jsonl_streams_without_collect
+    lf = Polars.scan_parquet(File.join(@fixtures_path, "basic.parquet"))
+
+    Tempfile.create(["output", ".jsonl"]) do |f|
+      lf.stub(:collect, -> { flunk "JSONL output should sink, not collect" }) do
+        TabularTool::Formats.write(lf, f.path)
+      end
+
+      df_read = TabularTool::Formats.read(f.path)
+      assert_equal 10, df_read.height
+      assert_equal @df.columns, df_read.columns
+    end
+  end
+
+  def test_append_csv_writes_header_once
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "running.csv")
+      TabularTool::Formats.append(@df.head(3), path)
+      TabularTool::Formats.append(@df.tail(2), path)
+
+      df_read = TabularTool::Formats.read(path)
+      assert_equal 5, df_read.height
+      assert_equal @df.columns, df_read.columns
+      assert_equal 1, File.readlines(path).count { |line| line.start_with?("name,") }
+    end
+  end
+
+  def test_append_jsonl
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "running.jsonl")
+      TabularTool::Formats.append(@df.head(3), path)
+      TabularTool::Formats.append(@df.lazy.tail(4), path)
+
+      assert_equal 7, TabularTool::Formats.read(path).height
+    end
+  end
+
+  def test_append_parquet_raises_error
+    Dir.mktmpdir do |dir|
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::Formats.append(@df, File.join(dir, "running.parquet"))
+      end
+      assert_match(/--append is not supported for parquet/, error.message)
+    end
+  end
+
+  def test_cli_append_two_batches
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "running.csv")
+      input = File.join(@fixtures_path, "basic.csv")
+      2.times { TabularTool::CLI.execute(command: :cat, file: input, output: path, append: true) }
+
+      assert_equal @df.height * 2, TabularTool::Formats.read(path).height
+    end
+  end
+
+  def test_bom_written_once_before_header
+    bom = TabularTool::Formats::UTF8_BOM
+    Dir.mktmpdir do |dir|
+      eager = File.join(dir, "eager.csv")
+      lazy = File.join(dir, "lazy.tsv")
+      TabularTool::Formats.write(@df, eager, bom: true)
+      TabularTool::Formats.write(@df.lazy, lazy, bom: true)
+
+      [eager, lazy].each do |path|
+        content = File.binread(path)
+        assert content.start_with?(bom + "name")
+        assert_equal 1, content.scan(bom).length
+        assert_equal @df.columns, TabularTool::Formats.read(path).columns
+      end
+
+      TabularTool::Formats.write(@df, eager)
+      refute File.binread(eager).start_with?(bom), "Off by default"
+
+      assert_raises(TabularTool::Error) { TabularTool::Formats.write(@df, File.join(dir, "out.parquet"), bom: true) }
+    end
+  end
+
+  def test_null_string_in_csv_output
+    df = Polars::DataFrame.new({ "id" => [1, 2], "note" => [nil, ""] })
+    Dir.mktmpdir do |dir|
+      eager = File.join(dir, "eager.csv")
+      lazy = File.join(dir, "lazy.csv")
+      TabularTool::Formats.write(df, eager, null_value: "\\N")
+      TabularTool::Formats.write(df.lazy, lazy, null_value: "\\N")
+
+      [eager, lazy].each do |path|
+        assert_equal ["id,note", "1,\\N", "2,"], File.read(path).lines.map(&:chomp)
+      end
+
+      # Containing the delimiter, it is quoted so rows keep two fields
+      TabularTool::Formats.write(df, eager, null_value: "N,A")
+      assert_equal ["id,note", "1,\"N,A\"", "2,"], File.read(eager).lines.map(&:chomp)
+
+      assert_raises(TabularTool::Error) { TabularTool::Formats.write(df, File.join(dir, "out.parquet"), null_value: "NULL") }
+    end
+
+    Tempfile.create(["nulls", ".csv"]) do |file|
+      file.write("id,note\n1,\n2,x\n")
+      file.flush
+      result = TabularTool::CLI.execute(command: :cat, file: file.path, null_string: "NULL", pretty: false)
+      assert_equal ["id,note", "1,NULL", "2,x"], result.lines.map(&:chomp)
+    end
+  end
+
+  def test_bom_not_repeated_when_appending
+    bom = TabularTool::Formats::UTF8_BOM
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "running.csv")
+      TabularTool::Formats.append(@df.head(3), path, bom: true)
+      TabularTool::Formats.append(@df.tail(2), path, bom: true)
+
+      content = File.binread(path)
+      assert content.start_with?(bom + "name,")
+      assert_equal 1, content.scan(bom).length
+      assert_equal 5, TabularTool::Formats.read(path).height
+
+      capped = File.join(dir, "capped.csv")
+      TabularTool::Formats.write_limited(@df, capped, max_bytes: 100, bom: true)
+      assert File.binread(capped).start_with?(bom)
+      assert_operator File.size(capped), :<=, 100
+    end
+  end
+
+  def test_write_partitioned_hive_layout
+    df = Polars::DataFrame.new({
+      "year" => [2024, 2024, 2023, 2024],
+      "region" => ["us", "eu/west", "us", nil],
+      "value" => [1, 2, 3, 4],
+    })
+
+    Dir.mktmpdir do |dir|
+      paths = TabularTool::Formats.write_partitioned(df, dir, by: ["year", "region"])
+      relative = paths.map { |path| path.delete_prefix("#{dir}/") }
+
+      assert_equal [
+        "year=2024/region=us/part.parquet",
+        "year=2024/region=eu%2Fwest/part.parquet",
+        "year=2023/region=us/part.parquet",
+        "year=2024/region=__HIVE_DEFAULT_PARTITION__/part.parquet",
+      ], relative
+      part = Polars.read_parquet(File.join(dir, "year=2024/region=us/part.parquet"))
+      assert_equal ["value"], part.columns
+      assert_equal [1], part["value"].to_a
+    end
+  end
+
+  def test_cli_partition_by_csv_output
+    Dir.mktmpdir do |dir|
+      out = File.join(dir, "out/")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), output: out,
+                               partition_by: ["status"], output_format: :csv)
+
+      assert_equal ["status=active", "status=inactive"], Dir.children(out).sort
+      inactive = File.read(File.join(out, "status=inactive", "part.csv"))
+      assert_equal "name,age,city,score\nCharlie,35,Chicago,78.5\nFrank,27,Philadelphia,76.0\nJack,33,San Jose,80.0\n",
+                   inactive
+
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), output: out,
+                                 partition_by: ["state"])
+      end
+      assert_match(/did you mean 'status'/, error.message)
+    end
+  end
+
+  def test_write_limited_writes_whole_rows_under_cap
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "capped.csv")
+      rows, truncated = TabularTool::Formats.write_limited(@df, path, max_bytes: 100)
+
+      assert truncated
+      assert_operator File.size(path), :<=, 100
+      content = File.read(path)
+      assert content.end_with?("\n"), "No partial last row"
+      assert_equal rows, TabularTool::Formats.read(path).height
+      assert_equal rows + 1, content.lines.length
+    end
+  end
+
+  def test_write_limited_lazy_frame_without_truncation
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "all.jsonl")
+      rows, truncated = TabularTool::Formats.write_limited(@df.lazy, path, max_bytes: 1_000_000)
+
+      refute truncated
+      assert_equal 10, rows
+      assert_equal 10, TabularTool::Formats.read(path).height
+    end
+  end
+
+  def test_write_limited_lazy_csv_across_batches
+    Dir.mktmpdir do |dir|
+      input = File.join(dir, "large.csv")
+      lines = (0...25_000).map { |i| "#{i},v#{i}\n" }
+      File.write(input, "id,value\n#{lines.join}")
+      # Lands inside the third batch, on a row boundary
+      keep = TabularTool::Formats::LIMITED_WRITE_BATCH_ROWS * 2 + 3456
+      max_bytes = "id,value\n".bytesize + lines.first(keep).sum(&:bytesize)
+
+      path = File.join(dir, "capped.csv")
+      rows, truncated = TabularTool::Formats.write_limited(Polars.scan_csv(input), path, max_bytes: max_bytes)
+
+      assert truncated
+      assert_equal keep, rows
+      assert_equal max_bytes, File.size(path)
+      assert_equal (0...keep).to_a, TabularTool::Formats.read(path)["id"].to_a
+    end
+  end
+
+  def test_write_limited_lazy_input_stops_reading_at_the_cap
+    Dir.mktmpdir do |dir|
+      input = File.join(dir, "large.csv")
+      File.write(input, "id\n#{(0...100_000).map { |i| "#{i}\n" }.join}")
+      reads = 0
+      # Counts the rows the plan produces, across every round of reading
+      lf = Polars.scan_csv(input).with_columns(
+        Polars.col("id").map_elements(->(id) { reads += 1; id }, return_dtype: Polars::Int64),
+      )
+
+      path = File.join(dir, "capped.csv")
+      rows, truncated = TabularTool::Formats.write_limited(lf, path, max_bytes: 100_000)
+
+      assert truncated
+      assert_equal (0...rows).to_a, TabularTool::Formats.read(path)["id"].to_a
+      assert_operator reads, :<, 50_000
+    end
+  end
+
+  def test_write_limited_parquet_raises_error
+    Dir.mktmpdir do |dir|
+      assert_raises(TabularTool::Error) do
+        TabularTool::Formats.write_limited(@df, File.join(dir, "out.parquet"), max_bytes: 1000)
+      end
+    end
+  end
+
+  def test_write_schema_sidecar_preserves_dtypes_across_csv
+    df = Polars::DataFrame.new({
+      "amount" => Polars::Series.new([1.0, 2.0, 3.0], dtype: Polars::Float64),
+      "code" => Polars::Series.new(["001", "002", "010"]),
+    })
+
+    Dir.mktmpdir do |dir|
+      source = File.join(dir, "data.parquet")
+      output = File.join(dir, "out.csv")
+      df.write_parquet(source)
+
+      TabularTool::CLI.execute(command: :cat, file: source, output: output, write_schema: true)
+
+      sidecar = JSON.parse(File.read("#{output}.schema.json"))
+      assert_equal [{ "name" => "amount", "dtype" => "Float64" }, { "name" => "code", "dtype" => "String" }], sidecar["columns"]
+
+      round_trip = TabularTool::Formats.read(output)
+      assert_equal ["Float64", "String"], round_trip.dtypes.map { |dtype| TabularTool::Dtypes.name(dtype) }
+      assert_equal ["001", "002", "010"], round_trip["code"].to_a
+    end
+  end
+
+  def test_write_schema_requires_text_output
+    Dir.mktmpdir do |dir|
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"),
+                                 output: File.join(dir, "out.parquet"), write_schema: true)
+      end
+      assert_match(/--write-schema requires/, error.message)
+    end
+  end
+
+  def test_dtype_names_parse_back
+    assert_equal Polars::Int64, TabularTool::Dtypes.parse("Int64")
+    datetime = TabularTool::Dtypes.parse('Datetime(time_unit: "ms", time_zone: "UTC")')
+    assert_equal ["ms", "UTC"], [datetime.time_unit, datetime.time_zone]
+    assert_nil TabularTool::Dtypes.parse("List(Int64)")
+    assert_nil TabularTool::Dtypes.parse("NotAType")
+  end
+
+  def test_output_format_overrides_extension
+    Dir.mktmpdir do |dir|
+      report = File.join(dir, "report")
+      TabularTool::CLI.execute(command: :head, file: File.join(@fixtures_path, "basic.csv"), limit: 2,
+                               output: report, output_format: :tsv)
+      assert_equal "name\tage\tcity\tscore\tstatus", File.read(report).lines.first.chomp
+
+      data = File.join(dir, "data.csv")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), output: data,
+                               output_format: :ipc)
+      assert_equal @df["name"].to_a, Polars.read_ipc(data)["name"].to_a
+    end
+  end
+
+  def test_write_and_read_ipc
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "out.arrow")
+      TabularTool::Formats.write(@df.lazy, path)
+
+      assert_equal :ipc, TabularTool::Formats.detect_format(path)
+      assert_equal @df.height, TabularTool::Formats.read(path).height
+    end
+  end
 end
@@ -1,6 +1,8 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "tempfile"
+require "json"
 
 class TestFormats < Minitest::Test
   def setup
@@ -30,12 +32,83 @@ class TestFormats < Minitest::Test
     assert_equal :jsonl, TabularTool::Formats.detect_format("file.ndjson")
   end
 
+  def test_detect_format_orc
+    assert_equal :orc, TabularTool::Formats.detect_format("file.orc")
+  end
+
+  def test_read_orc_raises_clear_error
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Formats.read("table.orc")
+    end
+    assert_match(/ORC input is not available/, error.message)
+  end
+
   def test_detect_format_with_compression
     assert_equal :csv, TabularTool::Formats.detect_format("file.csv.gz")
     assert_equal :csv, TabularTool::Formats.detect_format("file.csv.zst")
     assert_equal :parquet, TabularTool::Formats.detect_format("file.parquet.gz")
   end
 
+  def test_detect_format_with_dotted_names_and_compression
+    assert_equal :csv, TabularTool::Formats.detect_format("archive.2024.csv.gz")
+    assert_equal :tsv, TabularTool::Formats.detect_format("my.data.TSV.GZ")
+    assert_equal :jsonl, TabularTool::Formats.detect_format("events.2024-01-01.jsonl.zst")
+  end
+
+  def test_read_csv_parse_dates_is_opt_in
+    Tempfile.create(["dates", ".csv"]) do |f|
+      f.write("version,released\n2024-1,2024-01-15\n2024-2,2024-02-20\n")
+      f.flush
+
+      default = TabularTool::Formats.read(f.path)
+      assert_equal Polars::String, default["released"].dtype
+
+      parsed = TabularTool::Formats.read(f.path, parse_dates: true)
+      assert_equal Polars::Date, parsed["released"].dtype
+      assert_equal Polars::String, parsed["version"].dtype
+
+      off = TabularTool::Formats.read(f.path, parse_dates: false, streaming: true).collect
+      assert_equal Polars::String, off["released"].dtype
+    end
+  end
+
+  def test_parse_input_schema
+    schema = TabularTool::Dtypes.parse_schema('id:i64, name:str,ts:Datetime(time_unit: "ms", time_zone: "UTC")')
+    assert_equal ["id", "name", "ts"], schema.keys
+    assert_equal Polars::Int64, schema["id"]
+    assert_equal Polars::String, schema["name"]
+    assert_equal "UTC", schema["ts"].time_zone
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Dtypes.parse_schema("id:integer") }
+    assert_match(/Unknown type integer for id/, error.message)
+    assert_raises(TabularTool::Error) { TabularTool::Dtypes.parse_schema("id") }
+  end
+
+  def test_read_csv_with_input_schema
+    Tempfile.create(["typed", ".csv"]) do |f|
+      f.write("id,code,ts\n1,007,2024-01-15 10:30:00\n2,042,2024-02-20 08:00:00\n")
+      f.flush
+
+      schema = TabularTool::Dtypes.parse_schema("id:i32,code:str,ts:datetime")
+      df = TabularTool::Formats.read(f.path, schema: schema)
+      assert_equal [Polars::Int32, Polars::String], df.dtypes.first(2)
+      assert TabularTool::Dtypes.kind?(df["ts"].dtype, Polars::Datetime)
+      assert_equal ["007", "042"], df["code"].to_a
+
+      # Unlisted columns are still inferred
+      partial = TabularTool::Formats.read(f.path, schema: { "code" => Polars::String })
+      assert_equal [Polars::Int64, Polars::String, Polars::String], partial.dtypes
+
+      error = assert_raises(TabularTool::Error) do
+        TabularTool::Formats.read(f.path, schema: { "code" => Polars::String }, strict_schema: true)
+      end
+      assert_match(/no type given for id, ts/, error.message)
+
+      error = assert_raises(TabularTool::Error) { TabularTool::Formats.read(f.path, schema: { "idd" => Polars::Int64 }) }
+      assert_match(/columns not in .*: idd/, error.message)
+    end
+  end
+
   def test_read_csv
     df = TabularTool::Formats.read(File.join(@fixtures_path, "basic.csv"))
     assert_equal 10, df.height
@@ -64,6 +137,24 @@ class TestFormats < Minitest::Test
     assert_equal ["name", "age", "city", "score", "status"], df.columns
   end
 
+  def test_read_jsonl_infers_keys_from_every_line
+    Tempfile.create(["late", ".jsonl"]) do |f|
+      150.times { |i| f.puts(JSON.generate({ "id" => i })) }
+      f.puts(JSON.generate({ "id" => 150, "extra" => "late" }))
+      f.flush
+
+      [false, true].each do |streaming|
+        df = TabularTool::Formats.read(f.path, infer_rows: :all, streaming: streaming)
+        df = df.collect if streaming
+        assert_equal ["id", "extra"], df.columns
+        assert_equal [nil, nil, "late"], df["extra"].to_a.last(3)
+      end
+
+      error = assert_raises(TabularTool::Error) { TabularTool::Formats.read(File.join(@fixtures_path, "basic.csv"), infer_rows: 10) }
+      assert_match(/--json-infer-rows only applies to JSON lines input/, error.message)
+    end
+  end
+
   def test_read_with_custom_delimiter
     df = TabularTool::Formats.read(
       File.join(@fixtures_path, "basic.tsv"),
@@ -109,6 +200,96 @@ class TestFormats < Minitest::Test
     assert_equal csv_df.columns, zst_df.columns
   end
 
+  def test_dedupe_names_skips_names_already_in_the_header
+    assert_equal ["a", "b"], TabularTool::Formats.dedupe_names(["a", "b"])
+    assert_equal ["a", "a_2", "a_3"], TabularTool::Formats.dedupe_names(["a", "a", "a"])
+    assert_equal ["a", "a_2", "a_3"], TabularTool::Formats.dedupe_names(["a", "a_2", "a"])
+  end
+
+  def test_read_strips_utf8_bom_from_first_column
+    path = File.join(@fixtures_path, "bom.csv")
+
+    assert_equal ["id", "name"], TabularTool::Formats.read(path).columns
+    assert_equal ["id", "name"], TabularTool::Formats.read(path, streaming: true).collect.columns
+    assert_equal ["id", "name"], TabularTool::Formats.read_header(path)
+
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :cat, file: path, select: ["id"])
+      assert_equal ["id", "1", "2"], result.lines.map(&:chomp)
+    end
+  end
+
+  def test_latin1_input_round_trips_to_parquet
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.parquet")
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "latin1.csv"), encoding: "latin1",
+                               output: output)
+
+      df = Polars.read_parquet(output)
+      assert_equal ["José", "Renée"], df["name"].to_a
+      assert_equal ["São Paulo", "Zürich"], df["city"].to_a
+    end
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Formats.read(File.join(@fixtures_path, "basic.parquet"), encoding: "latin1")
+    end
+    assert_match(/--encoding only applies to CSV\/TSV/, error.message)
+  end
+
+  def test_decoded_copies_do_not_outlive_their_run
+    latin1 = File.join(@fixtures_path, "latin1.csv")
+    decoded = -> { Dir.glob(File.join(Dir.tmpdir, "tt-decoded*")).length }
+    before = decoded.call
+
+    # A run closes its copy when it ends, as each --watch rerun does
+    3.times do
+      result = TabularTool::CLI.execute(command: :cat, file: latin1, encoding: "latin1", pretty: false, streaming: true)
+      assert_includes result, "José"
+    end
+    assert_equal before, decoded.call
+
+    # Without a run, reading the same path again replaces the previous copy
+    3.times { TabularTool::Formats.read(latin1, encoding: "latin1") }
+    assert_equal before + 1, decoded.call
+  end
+
+  def test_sniff_delimiter_respects_quoted_fields
+    expected = { "comma_quoted" => ",", "semicolon" => ";", "tab" => "\t", "pipe" => "|" }
+
+    expected.each do |name, delimiter|
+      path = File.join(@fixtures_path, "delimited_#{name}.csv")
+      assert_equal delimiter, TabularTool::Formats.sniff_delimiter(path), name
+    end
+
+    Tempfile.create(["one_column", ".csv"]) do |file|
+      file.write("name\nAlice\nBob\n")
+      file.flush
+      assert_nil TabularTool::Formats.sniff_delimiter(file.path)
+    end
+  end
+
+  def test_one_column_header_triggers_delimiter_detection
+    path = File.join(@fixtures_path, "delimited_semicolon.csv")
+
+    $stdout.stub :tty?, false do
+      result = nil
+      _, err = capture_io { result = TabularTool::CLI.execute(command: :cat, file: path, select: ["name"]) }
+      assert_equal ["name", "\"Smith, John\"", "Jane"], result.lines.map(&:chomp)
+      assert_match(/detected delimiter ";"/, err)
+
+    end
+
+    # An explicit --delimiter always wins
+    Tempfile.create(["semicolons", ".csv"]) do |file|
+      file.write("a;b\n1;2\n")
+      file.flush
+      result = nil
+      _, err = capture_io { result = TabularTool::CLI.execute(command: :cat, file: file.path, delimiter: ",", pretty: false) }
+      assert_equal ["a;b", "1;2"], result.lines.map(&:chomp)
+      assert_empty err
+    end
+  end
+
   def test_read_headerless_csv
     df = TabularTool::Formats.read(
       File.join(@fixtures_path, "headerless.csv"),
@@ -1,6 +1,10 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "tempfile"
+require "tmpdir"
+require "json"
+require "stringio"
 
 class TestLint < Minitest::Test
   def setup
@@ -43,6 +47,39 @@ class TestLint < Minitest::Test
     refute_empty ws_issues
   end
 
+  def test_lint_profile_matches_per_column_scans
+    ["lint_test.csv", "lint_strings.csv", "lint_columns.csv"].each do |fixture|
+      df = TabularTool::Formats.read(File.join(@fixtures_path, fixture))
+      warnings = TabularTool::Operations::Lint.call(df)[:warnings]
+      reported = lambda do |type|
+        warnings.select { |w| w[:type] == type }.to_h { |w| [w[:column], w[:lines]] }
+      end
+
+      # The separate filter per column and check that lint used to run
+      scan = lambda do |columns, predicate|
+        columns.to_h do |col|
+          [col, df.with_row_index.filter(predicate.call(Polars.col(col)))["index"].to_a.map { |i| i + 2 }]
+        end.reject { |_, lines| lines.empty? }
+      end
+      strings = df.schema.select { |_, dtype| TabularTool::Dtypes.kind?(dtype, Polars::String) }.keys
+
+      assert_equal scan.call(df.columns, ->(c) { c.is_null }), reported.call(:blank_values), fixture
+      assert_equal scan.call(strings, ->(c) { c.is_not_null & (c.str.strip_chars != c) }), reported.call(:whitespace), fixture
+      assert_equal scan.call(strings, ->(c) { c == "" }), reported.call(:empty_strings), fixture
+      control = TabularTool::Operations::Lint::CONTROL_CHARACTERS
+      assert_equal scan.call(strings, ->(c) { c.str.contains(control) }), reported.call(:control_characters), fixture
+    end
+  end
+
+  def test_lint_profile_counts_and_fetches_lines_only_for_findings
+    df = Polars::DataFrame.new({ "a" => ["x", nil, "y"], "b" => ["p", "q", "r"], "c" => [1, 2, 3] })
+    lint = TabularTool::Operations::Lint
+    profile = lint.send(:column_profile, df)
+
+    assert_equal [1, 0, 0], (0..2).map { |i| profile["nulls:#{i}"] }
+    assert_equal({ "nulls:0" => [3] }, lint.send(:finding_lines, df, profile))
+  end
+
   def test_lint_detects_duplicate_column_values_when_requested
     df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
     result = TabularTool::Operations::Lint.call(df, unique_columns: ["email"])
@@ -161,4 +198,346 @@ class TestLint < Minitest::Test
     assert email_issue[:lines].include?(7)  # Frank
     assert email_issue[:lines].include?(10) # Alice duplicate
   end
+
+  def test_lint_detects_empty_strings
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_strings.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :empty_strings }
+    assert issue
+    assert_equal "city", issue[:column]
+    assert_equal [5], issue[:lines]
+  end
+
+  def test_lint_detects_control_characters
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_strings.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :control_characters }
+    assert issue
+    assert_equal [6], issue[:lines]
+  end
+
+  def test_lint_detects_case_variants
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_strings.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :case_variants }
+    assert issue
+    assert_equal "city", issue[:column]
+    assert_equal [["NYC", "nyc"]], issue[:values]
+    assert_equal [2, 3, 7], issue[:lines]
+  end
+
+  def test_lint_string_hygiene_flags_padded_values
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_strings.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :whitespace && w[:column] == "city" }
+    assert issue
+    assert_equal [4], issue[:lines]
+  end
+
+  def test_lint_string_hygiene_skips_clean_columns
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_strings.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    assert result[:warnings].none? { |w| w[:column] == "code" }
+  end
+
+  def test_lint_detects_all_null_column
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_columns.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :all_null }
+    assert issue
+    assert_equal "empty", issue[:column]
+    assert_equal 20, issue[:count]
+  end
+
+  def test_lint_detects_constant_column
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_columns.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :constant }
+    assert issue
+    assert_equal "country", issue[:column]
+    assert_equal "US", issue[:value]
+  end
+
+  def test_lint_detects_high_null_column
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_columns.csv"))
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :high_nulls }
+    assert issue
+    assert_equal "notes", issue[:column]
+    assert_equal 95.0, issue[:percent]
+    assert_equal ["empty", "country", "notes"], result[:suggested_drop]
+  end
+
+  def test_lint_high_null_threshold_is_configurable
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_columns.csv"))
+    result = TabularTool::Operations::Lint.call(df, high_null_threshold: 99.0)
+
+    assert result[:warnings].none? { |w| w[:type] == :high_nulls }
+  end
+
+  def test_cli_lint_prints_suggested_drop
+    output = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "lint_columns.csv"))
+
+    assert_includes output, "Suggested: --drop empty,country,notes"
+  end
+
+  def test_lint_flags_numbers_stored_as_strings
+    df = Polars::DataFrame.new({
+      "amount" => (1..40).map(&:to_s).tap { |values| values[4] = "N/A"; values[30] = "unknown" },
+      "name" => (1..40).map { |i| "item #{i}" },
+    })
+    result = TabularTool::Operations::Lint.call(df)
+
+    issue = result[:warnings].find { |w| w[:type] == :mistyped_strings }
+    assert issue
+    assert_equal "amount", issue[:column]
+    assert_equal :integer, issue[:inferred]
+    assert_equal ["N/A", "unknown"], issue[:values]
+    assert_equal [6, 32], issue[:lines]
+    assert result[:warnings].none? { |w| w[:type] == :mistyped_strings && w[:column] == "name" }
+  end
+
+  def test_lint_mistyped_strings_samples_unless_all
+    values = Array.new(30, "1.5") + ["oops"]
+    df = Polars::DataFrame.new({ "ratio" => values })
+
+    sampled = TabularTool::Operations::Lint.call(df, type_sample_rows: 10)
+    issue = sampled[:warnings].find { |w| w[:type] == :mistyped_strings }
+    assert_equal :float, issue[:inferred]
+    assert_empty issue[:values], "Bad row is outside the sample"
+
+    full = TabularTool::Operations::Lint.call(df, type_sample_rows: nil)
+    issue = full[:warnings].find { |w| w[:type] == :mistyped_strings }
+    assert_equal ["oops"], issue[:values]
+  end
+
+  def test_lint_reports_header_problems
+    Tempfile.create(["header", ".csv"]) do |f|
+      f.write("id, id ,Name,name,\n1,2,a,b,c\n")
+      f.flush
+
+      header = TabularTool::Formats.read_header(f.path)
+      assert_equal ["id", " id ", "Name", "name", ""], header
+
+      df = TabularTool::Formats.read(f.path)
+      warnings = TabularTool::Operations::Lint.call(df, header: header)[:warnings]
+
+      duplicate = warnings.find { |w| w[:type] == :duplicate_column_names }
+      assert_equal "id", duplicate[:column]
+      assert_equal [1, 2], duplicate[:positions]
+
+      padded = warnings.find { |w| w[:type] == :column_name_whitespace }
+      assert_equal " id ", padded[:column]
+
+      case_issue = warnings.find { |w| w[:type] == :column_name_case }
+      assert_equal ["Name", "name"], case_issue[:values]
+      assert_equal [3, 4], case_issue[:positions]
+
+      empty = warnings.find { |w| w[:type] == :empty_column_name }
+      assert_equal [5], empty[:positions]
+    end
+  end
+
+  def test_cli_lint_strict_raises_check_failed
+    Tempfile.create(["header", ".csv"]) do |f|
+      f.write("id,ID\n1,2\n")
+      f.flush
+
+      error = assert_raises(TabularTool::CheckFailed) do
+        TabularTool::CLI.execute(command: :lint, file: f.path, strict: true)
+      end
+      assert_includes error.output, "differ only by case"
+
+      # Without --strict the report is returned normally
+      assert_includes TabularTool::CLI.execute(command: :lint, file: f.path), "Header:"
+    end
+  end
+
+  def test_cli_lint_strict_passes_clean_file
+    output = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), strict: true)
+
+    assert_includes output, "All checks passed"
+  end
+
+  def test_lint_report_json_schema
+    output = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "lint_test.csv"), report: :json)
+    report = JSON.parse(output)
+
+    assert_equal ["column_count", "findings", "passed", "row_count"], report.keys.sort
+    assert_equal false, report["passed"]
+    assert_equal 10, report["row_count"]
+
+    finding = report["findings"].find { |f| f["type"] == "duplicate_rows" }
+    assert_equal ["check", "column", "count", "lines", "message", "severity", "type"], finding.keys.sort
+    assert_equal "duplicates", finding["check"]
+    assert_equal "error", finding["severity"]
+    assert_equal 1, finding["count"]
+  end
+
+  def test_lint_report_written_to_output_file
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "report.json")
+      result = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), report: :json, output: path)
+
+      assert_nil result
+      assert_equal true, JSON.parse(File.read(path))["passed"]
+    end
+  end
+
+  def test_lint_strict_fails_dirty_file
+    error = assert_raises(TabularTool::CheckFailed) do
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "lint_test.csv"), strict: true)
+    end
+
+    assert_includes error.output, "duplicate rows found"
+  end
+
+  def test_lint_fail_on_only_selected_checks
+    file = File.join(@fixtures_path, "lint_test.csv")
+
+    # lint_test.csv has nulls and duplicates but no header problems
+    output = TabularTool::CLI.execute(command: :lint, file: file, fail_on: ["header"])
+    assert_includes output, "Issues found"
+
+    assert_raises(TabularTool::CheckFailed) do
+      TabularTool::CLI.execute(command: :lint, file: file, fail_on: ["nulls"])
+    end
+  end
+
+  def test_lint_fail_on_unknown_check_raises_error
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), fail_on: ["typos"])
+    end
+
+    assert_match(/Unknown --fail-on check/, error.message)
+  end
+
+  def test_duplicate_groups_include_original_rows
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
+    groups = TabularTool::Operations::Lint.duplicate_groups(df, keys: ["email"], row_numbers: true)
+
+    # alice@ on lines 2 and 10, bob@ on lines 3 and 7, sorted by key
+    assert_equal [2, 10, 3, 7], groups["line"].to_a
+    assert_equal ["Alice", "Alice", "Bob", "Frank"], groups["name"].to_a
+  end
+
+  def test_duplicate_groups_respects_limit
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
+
+    assert_equal 3, TabularTool::Operations::Lint.duplicate_groups(df, keys: ["email"], limit: 3).height
+  end
+
+  def test_cli_lint_show_duplicates
+    output = TabularTool::CLI.execute(
+      command: :lint,
+      file: File.join(@fixtures_path, "lint_test.csv"),
+      check_unique_columns: ["email"],
+      show_duplicates: true,
+      with_row_numbers: true,
+    )
+
+    assert_includes output, "Duplicates on email (4 rows):"
+    assert_includes output, "Duplicates on all columns (2 rows):"
+    assert_includes output, "7,Frank,bob@example.com"
+  end
+
+  def test_null_rows_report_file_line_numbers
+    df = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
+    rows = TabularTool::Operations::Lint.null_rows(df, row_numbers: true)
+
+    # Charlie (blank email) is on line 4, Eve (blank city) on line 6
+    assert_equal "line", rows.columns.first
+    assert_equal [4, 6], rows["line"].to_a
+    assert_equal ["Charlie", "Eve"], rows["name"].to_a
+
+    refute_includes TabularTool::Operations::Lint.null_rows(df).columns, "line"
+  end
+
+  def test_null_rows_line_numbers_without_header
+    df = Polars::DataFrame.new({ "a" => [1, nil, 3], "b" => ["x", "y", nil] })
+    rows = TabularTool::Operations::Lint.null_rows(df, row_numbers: true, line_offset: 1)
+
+    assert_equal [2, 3], rows["line"].to_a
+  end
+
+  def test_cli_lint_show_nulls_with_row_numbers
+    output = TabularTool::CLI.execute(
+      command: :lint,
+      file: File.join(@fixtures_path, "lint_test.csv"),
+      show_nulls: true,
+      with_row_numbers: true,
+    )
+
+    assert_includes output, "Rows with nulls (2 rows):\nline,name,email,age,city\n4,Charlie,"
+  end
+
+  def test_encoding_violations_report_line_and_byte_offset
+    io = StringIO.new("id,name\n1,Ren\xE9\n2,ok\n3,\xFF\xFE\n".b)
+    result = TabularTool::Operations::Lint.encoding_violations(io)
+
+    assert_equal 2, result[:count]
+    assert_equal({ line: 2, byte_offset: 13 }, result[:violations].first)
+    assert_equal 4, result[:violations].last[:line]
+  end
+
+  def test_cli_lint_check_encoding
+    Tempfile.create(["latin1", ".csv"]) do |f|
+      f.binmode
+      f.write("id,name\n1,Ren\xE9\n2,Ana\n".b)
+      f.flush
+
+      output = TabularTool::CLI.execute(command: :lint, file: f.path, check_encoding: true)
+      assert_includes output, "Invalid UTF-8 on 1 lines"
+      assert_includes output, "line 2 (byte 13)"
+    end
+
+    clean = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv.gz"), check_encoding: true)
+    assert_includes clean, "✓ valid UTF-8"
+  end
+
+  def test_outliers_by_iqr_and_z_score
+    df = Polars::DataFrame.new({ "id" => (1..8).to_a, "reading" => [10, 11, 12, 13, 12, 11, 10, 100] })
+
+    result = TabularTool::Operations::Lint.call(df, outliers: true)
+    outliers = result[:warnings].select { |w| w[:type] == :outliers }
+    assert_equal ["reading"], outliers.map { |w| w[:column] }
+    assert_equal 1, outliers.first[:count]
+    assert_equal [9], outliers.first[:lines]
+    assert_operator outliers.first[:lower], :<, 10
+    assert_operator outliers.first[:upper], :<, 14
+
+    z2 = TabularTool::Operations::Lint.call(df, outliers: true, z: 2.0)[:warnings].select { |w| w[:type] == :outliers }
+    assert_equal [9], z2.first[:lines]
+    assert_equal "2.0σ", z2.first[:method]
+    z3 = TabularTool::Operations::Lint.call(df, outliers: true, z: 3.0)[:warnings]
+    assert_empty z3.select { |w| w[:type] == :outliers }
+
+    refute TabularTool::Operations::Lint.call(df)[:warnings].any? { |w| w[:type] == :outliers }
+  end
+
+  def test_cli_lint_show_outliers
+    Tempfile.create(["sensor", ".csv"]) do |f|
+      f.write("id,reading\n1,10\n2,11\n3,12\n4,13\n5,12\n6,11\n7,10\n8,100\n")
+      f.flush
+
+      output = TabularTool::CLI.execute(command: :lint, file: f.path, show_outliers: true)
+      assert_match(/Column 'reading': 1 outliers outside \[[\d.]+, [\d.]+\] \(1\.5×IQR\)/, output)
+      assert_includes output, "Lines: 9"
+
+      json = JSON.parse(TabularTool::CLI.execute(command: :lint, file: f.path, show_outliers: true, report: :json))
+      finding = json["findings"].find { |entry| entry["check"] == "outliers" }
+      assert_equal "1.5×IQR", finding["bounds"]["method"]
+    end
+
+    clean = TabularTool::CLI.execute(command: :lint, file: File.join(@fixtures_path, "basic.csv"), show_outliers: true)
+    assert_includes clean, "No numeric outliers"
+  end
 end
@@ -1,6 +1,7 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "date"
 
 # Test for basic operations like select, drop, unique, head, tail, sample
 class TestOperations < Minitest::Test
@@ -16,6 +17,97 @@ class TestOperations < Minitest::Test
     assert_equal 10, result.height
   end
 
+  def test_select_with_renames
+    result = TabularTool::Operations.select(@df, columns: ["user_name=name", "age", "user_city = city"])
+
+    assert_equal ["user_name", "age", "user_city"], result.columns
+    assert_equal @df["name"].to_a, result["user_name"].to_a
+  end
+
+  def test_select_with_renames_on_lazy_frame
+    result = TabularTool::Operations.select(@df.lazy, columns: ["n=name"]).collect
+
+    assert_equal ["n"], result.columns
+  end
+
+  def test_select_excluded_columns
+    result = TabularTool::Operations.select(@df, columns: ["!city", "!status"])
+    assert_equal ["name", "age", "score"], result.columns
+    assert_equal 10, result.height
+  end
+
+  def test_select_excluded_columns_on_lazy_frame
+    lf = Polars.scan_parquet(File.join(@fixtures_path, "basic.parquet"))
+    result = TabularTool::Operations.select(lf, columns: ["!name"]).collect
+    assert_equal ["age", "city", "score", "status"], result.columns
+  end
+
+  def test_select_mixed_excluded_and_plain_raises
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations.select(@df, columns: ["name", "!city"])
+    end
+    assert_match(/Cannot mix/, error.message)
+  end
+
+  def test_select_excluded_unknown_column_raises
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations.select(@df, columns: ["!password"])
+    end
+    assert_match(/password/, error.message)
+  end
+
+  def test_select_column_ranges
+    assert_equal ["age", "city", "score"], TabularTool::Operations.select(@df, columns: ["age..score"]).columns
+    assert_equal ["status", "name", "age"], TabularTool::Operations.select(@df.lazy, columns: ["status", "name..age"]).columns
+    assert_equal ["name", "status"], TabularTool::Operations.select(@df, columns: ["!age..score"]).columns
+    assert_equal ["city"], TabularTool::Operations.select(@df, columns: ["city..city"]).columns
+  end
+
+  def test_select_column_range_errors
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations.select(@df, columns: ["score..age"]) }
+    assert_match(/range score\.\.age is reversed: score comes after age/, error.message)
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations.select(@df, columns: ["age..total"]) }
+    assert_match(/Unknown column\(s\) in --select: total/, error.message)
+  end
+
+  def test_selection_regex_patterns
+    assert_equal ["city", "score", "status"], TabularTool::Operations.select(@df, columns: ["^[cs].*$"]).columns
+    assert_equal ["name", "age"], TabularTool::Operations.drop(@df, columns: ["^[cs].*$"]).columns
+    assert_equal @df.columns, TabularTool::Operations.drop(@df, columns: ["^tmp_.*$"]).columns
+  end
+
+  def test_selection_strict_mode_fails_on_empty_matches
+    [
+      -> { TabularTool::Operations.select(@df, columns: ["name", "^tmp_.*$"], mode: :strict) },
+      -> { TabularTool::Operations.drop(@df, columns: ["^tmp_.*$"], mode: :strict) },
+      -> { TabularTool::Operations.select(@df, columns: ["n=^nom.*$"], mode: :strict) },
+      -> { TabularTool::Operations.select_dtypes(@df, groups: [:numeric, :temporal], mode: :strict) },
+    ].each do |call|
+      error = assert_raises(TabularTool::Error) { call.call }
+      assert_match(/matches no columns \(--strict\)/, error.message)
+    end
+
+    assert_equal ["n", "age"], TabularTool::Operations.select(@df, columns: ["n=name", "age"], mode: :strict).columns
+  end
+
+  def test_selection_lenient_mode_skips_missing_names
+    _, err = capture_io do
+      assert_equal ["name"], TabularTool::Operations.select(@df, columns: ["name", "email"], mode: :lenient).columns
+      assert_equal ["n"], TabularTool::Operations.select(@df, columns: ["n=name", "e=email"], mode: :lenient).columns
+      assert_equal ["name", "age", "score"],
+                   TabularTool::Operations.drop(@df, columns: ["city", "status", "email"], mode: :lenient).columns
+    end
+    assert_match(/--select: skipping missing column\(s\) email/, err)
+    assert_match(/--drop: skipping missing column\(s\) email/, err)
+
+    assert_raises(TabularTool::Error) { TabularTool::Operations.drop(@df, columns: ["email"]) }
+    assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: File.join(@fixtures_path, "basic.csv"), drop: ["city"], strict: true,
+                               lenient: true)
+    end
+  end
+
   def test_drop_columns
     result = TabularTool::Operations.drop(@df, columns: ["city", "status"])
     assert_equal 3, result.width
@@ -174,4 +266,286 @@ class TestOperations < Minitest::Test
     scores = result["score"].to_a
     assert_equal scores.sort.reverse, scores  # Descending order
   end
+
+  def test_unique_without_maintain_order_keeps_same_rows
+    df_with_dups = TabularTool::Formats.read(File.join(@fixtures_path, "lint_test.csv"))
+
+    stable = TabularTool::Operations.unique(df_with_dups)
+    unordered = TabularTool::Operations.unique(df_with_dups, maintain_order: false)
+
+    assert_equal stable.height, unordered.height
+    assert_equal stable["name"].to_a.sort, unordered["name"].to_a.sort
+  end
+
+  def test_shuffle_is_a_permutation
+    shuffled = TabularTool::Operations.shuffle(@df, seed: 42)
+
+    assert_equal @df.height, shuffled.height
+    assert_equal @df["name"].to_a.sort, shuffled["name"].to_a.sort
+  end
+
+  def test_shuffle_with_seed_is_reproducible
+    first = TabularTool::Operations.shuffle(@df, seed: 7)["name"].to_a
+    second = TabularTool::Operations.shuffle(@df.lazy, seed: 7)["name"].to_a
+
+    assert_equal first, second
+    refute_equal @df["name"].to_a, first
+  end
+
+  def test_external_shuffle_keeps_every_row_and_is_reproducible
+    orders = 2.times.map do
+      TabularTool::Operations.external_shuffle(@df, buckets: 3, seed: 11) { |lf| lf.collect["name"].to_a }
+    end
+
+    assert_equal @df["name"].to_a.sort, orders.first.sort
+    assert_equal orders.first, orders.last
+    refute_equal @df["name"].to_a, orders.first
+  end
+
+  def test_external_shuffle_runs_input_plan_once
+    reads = 0
+    # Counts each time the plan evaluates the source column: once per row per run of the plan
+    source = @df.lazy.with_columns(
+      Polars.col("name").map_elements(->(name) { reads += 1; name }, return_dtype: Polars::String),
+    )
+    names = TabularTool::Operations.external_shuffle(source, buckets: 3, seed: 11) { |lf| lf.collect["name"].to_a }
+
+    assert_equal @df["name"].to_a.sort, names.sort
+    assert_equal @df.height, reads
+  end
+
+  def test_sample_per_group_caps_each_group
+    sampled = TabularTool::Operations.sample_per_group(@df, by: ["status"], n: 2, seed: 3)
+    assert_equal({ "active" => 2, "inactive" => 2 }, sampled["status"].to_a.tally)
+
+    # The inactive group only has 3 rows, so it's returned whole; original order is kept
+    sampled = TabularTool::Operations.sample_per_group(@df, by: ["status"], n: 5, seed: 3)
+    assert_equal({ "active" => 5, "inactive" => 3 }, sampled["status"].to_a.tally)
+    assert_equal @df["name"].to_a & sampled["name"].to_a, sampled["name"].to_a
+  end
+
+  def test_hash_values_is_deterministic_per_salt
+    df = Polars::DataFrame.new({ "email" => ["a@x.com", "b@x.com", "a@x.com", nil] })
+
+    hashed = TabularTool::Operations::Anonymize.hash_values(df, columns: ["email"], salt: "s1")["email"].to_a
+    assert_equal hashed[0], hashed[2]
+    refute_equal hashed[0], hashed[1]
+    assert_nil hashed[3]
+    assert_equal Digest::SHA256.hexdigest("s1a@x.com"), hashed[0]
+
+    resalted = TabularTool::Operations::Anonymize.hash_values(df, columns: ["email"], salt: "s2")["email"].to_a
+    refute_equal hashed[0], resalted[0]
+
+    md5 = TabularTool::Operations::Anonymize.hash_values(df, columns: ["email"], algorithm: "md5")["email"].to_a
+    assert_equal 32, md5[0].length
+  end
+
+  def test_redact_masks_values_and_keeps_nulls
+    df = Polars::DataFrame.new({ "ssn" => ["123-45-6789", nil], "id" => [1, 2] })
+    redacted = TabularTool::Operations::Anonymize.redact(df, columns: ["ssn"])
+
+    assert_equal ["***", nil], redacted["ssn"].to_a
+    assert_equal [1, 2], redacted["id"].to_a
+  end
+
+  def test_anonymize_rejects_unknown_columns
+    df = Polars::DataFrame.new({ "email" => ["a@x.com"] })
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations::Anonymize.hash_values(df, columns: ["emial"]) }
+    assert_match(/Unknown column\(s\) in --hash-columns: emial \(did you mean 'email'\?\)/, error.message)
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations::Anonymize.redact(df.lazy, columns: ["ssn"]) }
+    assert_match(/Unknown column\(s\) in --redact-columns: ssn/, error.message)
+  end
+
+  def test_truncate_strings_counts_characters_not_bytes
+    df = Polars::DataFrame.new({ "note" => ["héllo wörld", "日本語のテキスト", "ok", nil], "n" => [1, 2, 3, 4] })
+
+    truncated = TabularTool::Operations.truncate_strings(df, max_chars: 5)
+    assert_equal ["héll…", "日本語の…", "ok", nil], truncated["note"].to_a
+    assert truncated["note"].to_a.compact.all?(&:valid_encoding?)
+
+    bare = TabularTool::Operations.truncate_strings(df, max_chars: 3, ellipsis: false)
+    assert_equal ["hél", "日本語", "ok", nil], bare["note"].to_a
+  end
+
+  def test_limit_cell_bytes_errors_or_truncates_on_character_boundary
+    df = Polars::DataFrame.new({ "note" => ["日本語", "abc"] })
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations.limit_cell_bytes(df, max_bytes: 4) }
+    assert_match(/Column note has a 9-byte value/, error.message)
+
+    # 4 bytes lands inside the second character, which is dropped rather than split
+    truncated = TabularTool::Operations.limit_cell_bytes(df, max_bytes: 4, action: :truncate)
+    assert_equal ["日", "abc"], truncated["note"].to_a
+  end
+
+  def test_select_dtypes_keeps_matching_columns_in_order
+    df = @df.with_columns(Polars.lit(true).alias("flag"), Polars.lit(Date.new(2024, 1, 1)).alias("day"))
+
+    assert_equal ["age", "score"], TabularTool::Operations.select_dtypes(df, groups: [:numeric]).columns
+    assert_equal ["name", "city", "status"], TabularTool::Operations.select_dtypes(df, groups: [:string]).columns
+    assert_equal ["age", "score", "flag", "day"], TabularTool::Operations.select_dtypes(df, groups: [:numeric, :bool, :temporal]).columns
+    assert_raises(TabularTool::Error) { TabularTool::Operations.select_dtypes(df, groups: [:decimal]) }
+  end
+
+  def test_window_columns_follow_row_order
+    df = Polars::DataFrame.new({ "region" => ["a", "b", "a", "b", "a"], "amount" => [1, 10, 2, 20, 3] })
+    specs = ->(flag, spec) { [TabularTool::Operations::Window.parse_spec(spec, flag)] }
+
+    result = TabularTool::Operations::Window.call(df, cumsum: specs.(:cumsum, "amount"), diff: specs.(:diff, "amount:delta"),
+                                                      lag: specs.(:lag, "amount:2"))
+    assert_equal [1, 11, 13, 33, 36], result["amount_cumsum"].to_a
+    assert_equal [nil, 9, -8, 18, -17], result["delta"].to_a
+    assert_equal [nil, nil, 1, 10, 2], result["amount_lag2"].to_a
+
+    grouped = TabularTool::Operations::Window.call(df, cumsum: specs.(:cumsum, "amount"), diff: specs.(:diff, "amount"),
+                                                       over: ["region"])
+    assert_equal [1, 10, 3, 30, 6], grouped["amount_cumsum"].to_a
+    assert_equal [nil, nil, 1, 10, 1], grouped["amount_diff"].to_a
+  end
+
+  def test_window_rejects_unknown_columns
+    df = Polars::DataFrame.new({ "region" => ["a"], "amount" => [1] })
+    spec = ->(flag, text) { [TabularTool::Operations::Window.parse_spec(text, flag)] }
+
+    error = assert_raises(TabularTool::Error) { TabularTool::Operations::Window.call(df, lag: spec.(:lag, "amout:1")) }
+    assert_match(/Unknown column\(s\) in --lag: amout \(did you mean 'amount'\?\)/, error.message)
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Window.call(df.lazy, cumsum: spec.(:cumsum, "amount"), over: ["regoin"])
+    end
+    assert_match(/Unknown column\(s\) in --over: regoin/, error.message)
+  end
+
+  def test_window_lag_spec_requires_periods
+    assert_raises(TabularTool::Error) { TabularTool::Operations::Window.parse_spec("amount", :lag) }
+    assert_equal({ column: "amount", periods: 1, alias: "prev" }, TabularTool::Operations::Window.parse_spec("amount:1:prev", :lag))
+  end
+
+  def test_with_window_functions_keep_rows_in_input_order
+    df = Polars::DataFrame.new({
+      "city" => ["a", "b", "a", "b", "a"],
+      "ts" => [3, 1, 1, 2, 2],
+      "value" => [30, 5, 10, 7, 20],
+    })
+    specs = [
+      "sum(value) OVER (PARTITION BY city) AS city_total",
+      "row_number() OVER (PARTITION BY city ORDER BY ts) AS n",
+      "sum(value) OVER (PARTITION BY city ORDER BY ts) AS running",
+      "lag(value) OVER (PARTITION BY city ORDER BY ts) AS previous",
+      "rank() OVER (ORDER BY ts DESC) AS ts_rank",
+      "value * 2 AS doubled",
+    ].map { |spec| TabularTool::Operations::Window.parse_with(spec) }
+
+    result = TabularTool::Operations::Window.with_columns(df, specs)
+
+    assert_equal [3, 1, 1, 2, 2], result["ts"].to_a
+    assert_equal [60, 12, 60, 12, 60], result["city_total"].to_a
+    assert_equal [3, 1, 1, 2, 2], result["n"].to_a
+    assert_equal [60, 5, 10, 12, 30], result["running"].to_a
+    assert_equal [20, nil, nil, 5, 10], result["previous"].to_a
+    assert_equal [1, 4, 4, 2, 2], result["ts_rank"].to_a
+    assert_equal [60, 10, 20, 14, 40], result["doubled"].to_a
+  end
+
+  def test_with_rejects_window_frames_and_bad_specs
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Window.parse_with("sum(x) OVER (ORDER BY ts ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS s")
+    end
+    assert_match(/window frames \(ROWS\/RANGE\/GROUPS\) aren't supported/, error.message)
+
+    assert_raises(TabularTool::Error) { TabularTool::Operations::Window.parse_with("sum(x)") }
+    assert_raises(TabularTool::Error) { TabularTool::Operations::Window.parse_with("sum(x) OVER (ORDER BY ts sideways) AS s") }
+  end
+
+  def test_sample_stratified_proportional_and_balanced
+    proportional = TabularTool::Operations.sample_stratified(@df, by: ["status"], n: 4, seed: 1)
+    assert_equal({ "active" => 3, "inactive" => 1 }, proportional["status"].to_a.tally)
+
+    # inactive only has 3 rows, so the balanced share it can't fill goes to active
+    balanced = TabularTool::Operations.sample_stratified(@df, by: ["status"], n: 8, balanced: true, seed: 1)
+    assert_equal({ "active" => 5, "inactive" => 3 }, balanced["status"].to_a.tally)
+    assert_equal @df["name"].to_a & balanced["name"].to_a, balanced["name"].to_a
+
+    first = TabularTool::Operations.sample_stratified(@df, by: ["status"], fraction: 0.5, seed: 9)["name"].to_a
+    second = TabularTool::Operations.sample_stratified(@df, by: ["status"], fraction: 0.5, seed: 9)["name"].to_a
+    assert_equal 5, first.length
+    assert_equal first, second
+  end
+
+  def test_trim_strips_string_columns_only
+    df = Polars::DataFrame.new({
+      "city" => ["NYC ", " Boston", "Chicago"],
+      "code" => [" a", "b ", "c"],
+      "n" => [1, 2, 3],
+    })
+    trimmed = TabularTool::Operations.trim(df)
+
+    assert_equal ["NYC", "Boston", "Chicago"], trimmed["city"].to_a
+    assert_equal ["a", "b", "c"], trimmed["code"].to_a
+    assert_equal [1, 2, 3], trimmed["n"].to_a
+
+    only_city = TabularTool::Operations.trim(df, columns: ["city", "n"])
+    assert_equal ["NYC", "Boston", "Chicago"], only_city["city"].to_a
+    assert_equal [" a", "b ", "c"], only_city["code"].to_a
+  end
+
+  def test_change_case_lower_and_upper
+    lowered = TabularTool::Operations.change_case(@df, columns: ["name", "city"], to: :lower)
+    assert_equal "alice", lowered["name"][0]
+    assert_equal "new york", lowered["city"][0]
+    assert_equal "active", lowered["status"][0]
+
+    uppered = TabularTool::Operations.change_case(@df.lazy, columns: ["status"], to: :upper).collect
+    assert_equal "ACTIVE", uppered["status"][0]
+    assert_equal "Alice", uppered["name"][0]
+  end
+
+  def test_change_case_non_string_column_raises
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations.change_case(@df, columns: ["age"], to: :lower)
+    end
+
+    assert_match(/--lower only applies to String columns, not: age/, error.message)
+  end
+
+  def test_sample_methods_return_requested_rows
+    lf = Polars.scan_parquet(File.join(@fixtures_path, "basic.parquet"))
+
+    TabularTool::Operations::SAMPLE_METHODS.each do |method|
+      assert_equal 4, TabularTool::Operations.sample(@df, n: 4, method: method).height, method.to_s
+      assert_equal 4, TabularTool::Operations.sample(lf, n: 4, method: method).height, method.to_s
+    end
+  end
+
+  def test_sample_reservoir_has_no_repeats_and_keeps_file_order
+    result = TabularTool::Operations.sample(@df, n: 6, method: :reservoir, seed: 1)
+    names = result["name"].to_a
+
+    assert_equal names.uniq, names
+    assert_equal @df["name"].to_a & names, names
+  end
+
+  def test_sample_positions_are_drawn_without_scanning_the_count
+    indices = TabularTool::Operations.send(:distinct_indices, 10**12, 1000, Random.new(5))
+
+    assert_equal 1000, indices.uniq.length
+    assert_equal indices.sort, indices
+    assert indices.all? { |i| i >= 0 && i < 10**12 }
+    assert_equal (0...10).to_a, TabularTool::Operations.send(:distinct_indices, 10, 10, Random.new(5))
+  end
+
+  def test_sample_with_seed_is_reproducible
+    TabularTool::Operations::SAMPLE_METHODS.each do |method|
+      first = TabularTool::Operations.sample(@df, n: 3, method: method, seed: 42)["name"].to_a
+      second = TabularTool::Operations.sample(@df, n: 3, method: method, seed: 42)["name"].to_a
+
+      assert_equal first, second, method.to_s
+    end
+  end
+
+  def test_sample_unknown_method_raises
+    assert_raises(TabularTool::Error) { TabularTool::Operations.sample(@df, n: 3, method: :magic) }
+  end
 end
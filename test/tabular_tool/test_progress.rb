@@ -0,0 +1,105 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "stringio"
+require "tmpdir"
+
+class TestProgress < Minitest::Test
+  def tty_io
+    io = StringIO.new
+    def io.tty?
+      true
+    end
+    io
+  end
+
+  def test_counting_io_counts_bytes_from_each_line
+    progress = TabularTool::Progress.new(enabled: true, io: tty_io)
+    progress.start("Reading")
+
+    io = progress.wrap(StringIO.new("id,name\n1,Alice\n2,Bob\n"))
+    lines = io.each_line.to_a
+
+    assert_equal 3, lines.length
+    assert_equal 22, progress.bytes
+  end
+
+  def test_counting_io_counts_bytes_from_read
+    progress = TabularTool::Progress.new(enabled: true, io: tty_io)
+    progress.start("Reading")
+
+    data = progress.wrap(StringIO.new("abcdef")).read
+
+    assert_equal "abcdef", data
+    assert_equal 6, progress.bytes
+  end
+
+  def test_disabled_when_stderr_is_not_a_tty
+    io = StringIO.new
+    progress = TabularTool::Progress.new(enabled: true, io: io)
+
+    refute progress.enabled?
+    progress.start("Reading")
+    progress.finish(rows: 10)
+    assert_empty io.string
+
+    # No wrapping overhead when disabled
+    source = StringIO.new("data")
+    assert_same source, progress.wrap(source)
+  end
+
+  def test_finish_reports_rows
+    io = tty_io
+    progress = TabularTool::Progress.new(enabled: true, io: io)
+    progress.start("Writing out.parquet")
+    progress.finish(rows: 10)
+
+    assert_match(/Writing out\.parquet: 10 rows in/, io.string)
+    assert io.string.end_with?("\n")
+  end
+
+  def test_cli_progress_never_writes_to_stdout
+    result = TabularTool::CLI.execute(
+      command: :head,
+      file: File.join(__dir__, "..", "fixtures", "basic.csv"),
+      limit: 2,
+      progress: true,
+      pretty: false,
+    )
+
+    assert_equal "name,age,city,score,status", result.split("\n").first
+  end
+
+  def test_cli_file_reads_advance_the_bar
+    csv = File.join(__dir__, "..", "fixtures", "basic.csv")
+    io = tty_io
+    progress = TabularTool::Progress.new(enabled: true, io: io)
+    TabularTool::CLI.execute(command: :cat, file: csv, pretty: false, progress: true, progress_bar: progress)
+
+    assert_equal File.size(csv), progress.bytes
+    assert_match(/Reading basic\.csv: 10 rows in/, io.string)
+
+    Dir.mktmpdir do |dir|
+      parquet = File.join(dir, "groups.parquet")
+      TabularTool::Formats.read(csv).write_parquet(parquet, row_group_size: 4)
+      progress = TabularTool::Progress.new(enabled: true, io: tty_io)
+      result = TabularTool::CLI.execute(command: :cat, file: parquet, pretty: false, progress: true,
+                                        progress_bar: progress)
+
+      assert_equal TabularTool::ParquetFooter.row_group_sizes(parquet).sum(&:last), progress.bytes
+      assert_equal 11, result.lines.length
+    end
+  end
+
+  def test_cli_progress_counts_rows_sunk_to_csv
+    Dir.mktmpdir do |dir|
+      output = File.join(dir, "out.csv")
+      io = tty_io
+      progress = TabularTool::Progress.new(enabled: true, io: io)
+      TabularTool::CLI.execute(command: :cat, file: File.join(__dir__, "..", "fixtures", "basic.csv"), output: output,
+                               streaming: true, progress: true, progress_bar: progress)
+
+      assert_match(/Writing out\.csv: 10 rows in/, io.string)
+    end
+  end
+end
@@ -0,0 +1,87 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "tmpdir"
+
+class TestResample < Minitest::Test
+  def setup
+    @fixtures_path = File.join(__dir__, "..", "fixtures")
+
+    # Two days of events, two per hour, except 05:00 on the first day which has none
+    start = Time.utc(2024, 1, 1)
+    times = (0...48).reject { |hour| hour == 5 }.flat_map { |hour| [start + hour * 3600 + 60, start + hour * 3600 + 1800] }
+    @events = Polars::DataFrame.new({
+      "ts" => times,
+      "host" => times.each_with_index.map { |_, i| i.even? ? "a" : "b" },
+      "bytes" => times.each_index.map { 10 },
+    })
+  end
+
+  def resample(**options)
+    TabularTool::Operations::Resample.call(
+      @events,
+      column: "ts",
+      every: "1h",
+      aggregations: TabularTool::Operations::Resample.parse_aggregations("count(),sum(bytes)"),
+      **options,
+    )
+  end
+
+  def test_hourly_counts_skip_empty_hours
+    result = resample
+
+    assert_equal ["ts", "count", "sum_bytes"], result.columns
+    assert_equal 47, result.height
+    assert_equal [2], result["count"].to_a.uniq
+    assert_equal [20], result["sum_bytes"].to_a.uniq
+    refute_includes result["ts"].to_a.first(6).map { |ts| ts.utc.hour }, 5
+  end
+
+  def test_fill_gaps_adds_empty_buckets
+    result = resample(fill_gaps: true)
+
+    assert_equal 48, result.height
+    assert_equal 5, result["ts"][5].utc.hour
+    assert_equal 0, result["count"][5]
+    assert_nil result["sum_bytes"][5]
+  end
+
+  def test_by_groups_each_bucket
+    result = resample(by: ["host"], fill_gaps: true)
+
+    assert_equal ["ts", "host", "count", "sum_bytes"], result.columns
+    assert_equal 96, result.height
+    assert_equal [1], result.filter(Polars.col("count") > 0)["count"].to_a.uniq
+  end
+
+  def test_non_temporal_column_is_rejected
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::Operations::Resample.call(@events, column: "host", every: "1h", aggregations: [Polars.len])
+    end
+    assert_match(/needs a Date or Datetime column, but host is String/, error.message)
+  end
+
+  def test_unknown_aggregation_is_rejected
+    assert_raises(TabularTool::Error) { TabularTool::Operations::Resample.parse_aggregations("avg(bytes)") }
+  end
+
+  def test_cli_resample
+    args = TabularTool::CLI.parse_args(["resample", "ts", "--every", "1d", "--agg", "count(), max(bytes)", "events.parquet"])
+    assert_equal :resample, args[:command]
+    assert_equal "ts", args[:resample_column]
+    assert_equal "events.parquet", args[:file]
+
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "events.parquet")
+      @events.write_parquet(path)
+
+      $stdout.stub :tty?, false do
+        result = TabularTool::CLI.execute(**args, file: path)
+        lines = result.lines.map(&:chomp)
+        assert_equal "ts,count,max_bytes", lines.first
+        assert_equal [["46", "10"], ["48", "10"]], lines.drop(1).map { |line| line.split(",").drop(1) }
+        assert lines[1].start_with?("2024-01-01")
+      end
+    end
+  end
+end
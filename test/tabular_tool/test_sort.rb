@@ -1,6 +1,7 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "tmpdir"
 
 class TestSort < Minitest::Test
   def setup
@@ -69,6 +70,13 @@ class TestSort < Minitest::Test
     assert scores[0] >= scores[1]
   end
 
+  def test_sort_key_direction_suffix
+    sorted = TabularTool::Operations::Sort.call(@df, keys: ["status", "age:desc"])
+
+    assert_equal ["active"] * 7 + ["inactive"] * 3, sorted["status"].to_a
+    assert_equal [32, 31, 30, 29, 28, 26, 25, 35, 33, 27], sorted["age"].to_a
+  end
+
   def test_sort_numeric_vs_string
     # Numeric column should sort numerically
     sorted = TabularTool::Operations::Sort.call(@df, keys: ["age"])
@@ -111,4 +119,128 @@ class TestSort < Minitest::Test
     assert_equal ["alice", "Bob", "CHARLIE", "diana", "Eve"], names_insensitive,
                  "Case-insensitive sort should order: alice, Bob, CHARLIE, diana, Eve"
   end
+
+  def test_sort_case_insensitive_keeps_original_values
+    df = Polars::DataFrame.new({
+      "name" => ["bob", "ALICE", "Carol", "alice"],
+      "age" => [10, 2, 30, 1],
+    })
+
+    sorted = TabularTool::Operations::Sort.call(df, keys: ["name", "age"], ignore_case: true)
+
+    assert_equal ["alice", "ALICE", "bob", "Carol"], sorted["name"].to_a
+    # Numeric keys in the same sort still compare as numbers
+    assert_equal [1, 2, 10, 30], sorted["age"].to_a
+    assert_equal ["name", "age"], sorted.columns
+  end
+
+  def test_sort_ignore_case_on_numeric_keys_warns
+    df = Polars::DataFrame.new({ "n" => [10, 9, 100] })
+
+    sorted = nil
+    _, err = capture_io do
+      sorted = TabularTool::Operations::Sort.call(df, keys: ["n"], ignore_case: true)
+    end
+
+    assert_equal [9, 10, 100], sorted["n"].to_a
+    assert_match(/--ignore-case has no effect: n is not a string column/, err)
+  end
+
+  def test_sort_nulls_last
+    df = Polars::DataFrame.new({"name" => ["a", "b", "c"], "city" => [nil, "Boston", "Austin"]})
+
+    sorted = TabularTool::Operations::Sort.call(df, keys: ["city"], nulls_last: true)
+    assert_equal ["Austin", "Boston", nil], sorted["city"].to_a
+
+    sorted = TabularTool::Operations::Sort.call(df, keys: ["city"], nulls_last: false)
+    assert_equal [nil, "Austin", "Boston"], sorted["city"].to_a
+  end
+
+  def test_sort_nulls_last_with_reverse_and_ignore_case
+    df = Polars::DataFrame.new({"city" => ["boston", nil, "Austin"]})
+
+    sorted = TabularTool::Operations::Sort.call(
+      df,
+      keys: ["city"],
+      reverse: true,
+      ignore_case: true,
+      nulls_last: true,
+    )
+    assert_equal ["boston", "Austin", nil], sorted["city"].to_a
+  end
+
+  def test_external_sort_matches_in_memory_sort
+    random = Random.new(11)
+    df = Polars::DataFrame.new({
+      "city" => Array.new(5000) { [nil, "Austin", "boston", "Boston", "chicago", "Denver"].sample(random: random) },
+      "n" => Array.new(5000) { random.rand(100) },
+      "id" => (1..5000).to_a,
+    })
+
+    [
+      { keys: ["n", "id"] },
+      { keys: ["city", "n:desc", "id"], ignore_case: true },
+      { keys: ["city", "id"], reverse: true, nulls_last: true },
+    ].each do |options|
+      expected = TabularTool::Operations::Sort.call(df, **options)
+      TabularTool::Operations::Sort.external(df.lazy, runs: 7, **options) do |sorted|
+        result = sorted.collect
+        # id breaks every tie, so both orders are fully determined
+        assert_equal expected["id"].to_a, result["id"].to_a, options.inspect
+      end
+    end
+  end
+
+  def test_cli_external_sort_writes_sorted_output
+    Dir.mktmpdir do |dir|
+      input = File.join(__dir__, "..", "fixtures", "basic.csv")
+      output = File.join(dir, "sorted.parquet")
+      TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["-k", "score", "-r", "--external-sort", "--temp-dir", dir,
+                                                              input, "-o", output]))
+
+      assert_equal @df.sort("score", descending: true)["name"].to_a, TabularTool::Formats.read(output)["name"].to_a
+      assert_equal ["sorted.parquet"], Dir.children(dir)
+    end
+  end
+
+  def test_convert_with_external_sort_counts_input_rows_once
+    calls = 0
+    count = TabularTool::Operations.method(:row_count)
+    Dir.mktmpdir do |dir|
+      input = File.join(__dir__, "..", "fixtures", "basic.csv")
+      output = File.join(dir, "sorted.csv")
+      TabularTool::Operations.stub(:row_count, ->(df) { calls += 1; count.call(df) }) do
+        capture_io do
+          TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["convert", "-k", "age", "--external-sort",
+                                                                  "--temp-dir", dir, input, output]))
+        end
+      end
+
+      assert_equal @df.sort("age")["name"].to_a, TabularTool::Formats.read(output)["name"].to_a
+    end
+    # Once for convert's check of the input and once to verify the output; the sort counts its own spill file
+    assert_equal 2, calls
+  end
+
+  def test_external_sort_needs_output_and_key
+    input = File.join(__dir__, "..", "fixtures", "basic.csv")
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: input, sort_keys: ["age"], external_sort: true)
+    end
+    assert_match(/--external-sort needs -o/, error.message)
+
+    error = assert_raises(TabularTool::Error) do
+      TabularTool::CLI.execute(command: :cat, file: input, sort_keys: ["age"], external_sort: true, output: "x.csv",
+                               with_row_index: "n")
+    end
+    assert_match(/cannot be combined with --with-row-index/, error.message)
+  end
+
+  def test_parse_nulls_option
+    args = TabularTool::CLI.parse_args(["--nulls", "last", "-k", "city", "file.csv"])
+    assert_equal true, args[:nulls_last]
+
+    args = TabularTool::CLI.parse_args(["--nulls", "first", "file.csv"])
+    assert_equal false, args[:nulls_last]
+  end
 end
@@ -1,6 +1,8 @@
 # frozen_string_literal: true
 
 require "test_helper"
+require "json"
+require "tmpdir"
 
 class TestStats < Minitest::Test
   def setup
@@ -88,4 +90,194 @@ class TestStats < Minitest::Test
     assert result[:stats]
     assert_equal 0, result[:row_count]
   end
+
+  def test_fast_stats_min_max_match_full_stats
+    lf = Polars.scan_parquet(File.join(@fixtures_path, "basic.parquet"))
+    fast = TabularTool::Operations::Stats.fast(lf)[:stats]
+    full = TabularTool::Operations::Stats.call(@df)[:stats]
+
+    label = full.columns.first
+    ["min", "max"].each do |stat|
+      fast_row = fast.filter(Polars.col("statistic") == stat)
+      full_row = full.filter(Polars.col(label) == stat)
+
+      ["age", "score"].each do |col|
+        assert_equal full_row[col][0].to_f, fast_row[col][0].to_f, "#{stat} of #{col}"
+      end
+    end
+  end
+
+  def test_fast_stats_from_parquet_footer_compute_only_columns_without_statistics
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "basic.parquet")
+      @df.write_parquet(path, statistics: true, row_group_size: 4)
+      footer = TabularTool::ParquetFooter.method(:statistics)
+      # As if the writer had left out age's statistics
+      without_age = lambda do |file|
+        rows, columns = footer.call(file)
+        [rows, columns.merge("age" => columns["age"].map { nil })]
+      end
+
+      lf = Polars.scan_parquet(path)
+      select = lf.method(:select)
+      passes = []
+      fast = TabularTool::ParquetFooter.stub(:statistics, without_age) do
+        lf.stub(:select, ->(aggs) { passes << aggs; select.call(aggs) }) do
+          TabularTool::Operations::Stats.fast(lf, path: path)
+        end
+      end
+
+      # One pass, over age alone: its null count, min and max; the row count comes from the footer
+      assert_equal [3], passes.map(&:length)
+      computed = TabularTool::Operations::Stats.fast(lf)
+      assert_equal computed.values_at(:row_count, :column_count), fast.values_at(:row_count, :column_count)
+      assert_equal computed[:stats].rows, fast[:stats].rows
+
+      full = TabularTool::Operations::Stats.call(@df)[:stats]
+      ["min", "max"].each do |stat|
+        fast_row = fast[:stats].filter(Polars.col("statistic") == stat)
+        full_row = full.filter(Polars.col(full.columns.first) == stat)
+        ["age", "score"].each do |col|
+          assert_equal full_row[col][0].to_f, fast_row[col][0].to_f, "#{stat} of #{col}"
+        end
+      end
+    end
+  end
+
+  def test_fast_stats_with_full_parquet_footer_read_no_data
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "basic.parquet")
+      @df.write_parquet(path, statistics: true)
+      lf = Polars.scan_parquet(path)
+
+      fast = lf.stub(:select, ->(_) { flunk "every column has footer statistics" }) do
+        TabularTool::Operations::Stats.fast(lf, path: path)
+      end
+
+      assert_equal 10, fast[:row_count]
+      assert_equal TabularTool::Operations::Stats.fast(@df)[:stats].rows, fast[:stats].rows
+    end
+  end
+
+  def test_cli_fast_stats_resolves_the_input_schema_once
+    calls = 0
+    schema = TabularTool::Operations.method(:schema)
+    path = File.join(@fixtures_path, "basic.csv")
+    output = TabularTool::Operations.stub(:schema, ->(df) { calls += 1; schema.call(df) }) do
+      TabularTool::CLI.execute(**TabularTool::CLI.parse_args(["stats", "--fast", path]))
+    end
+
+    assert_equal 1, calls
+    assert_includes output, TabularTool::Operations::Stats.fast(Polars.scan_csv(path))[:stats].to_s
+  end
+
+  def test_fast_stats_marks_unavailable_statistics
+    result = TabularTool::Operations::Stats.fast(@df)
+    stats = result[:stats]
+
+    assert_equal 10, result[:row_count]
+    mean_row = stats.filter(Polars.col("statistic") == "mean")
+    assert_equal "n/a", mean_row["age"][0]
+  end
+
+  def test_fast_stats_mixes_available_and_unavailable_columns
+    df = Polars::DataFrame.new({
+      "id" => [3, 1, 2],
+      "tags" => [["a"], ["b", "c"], nil],
+    })
+    stats = TabularTool::Operations::Stats.fast(df)[:stats]
+
+    min_row = stats.filter(Polars.col("statistic") == "min")
+    assert_equal "1", min_row["id"][0]
+    assert_equal "n/a", min_row["tags"][0], "Nested column has no min; marked unavailable"
+
+    null_row = stats.filter(Polars.col("statistic") == "null_count")
+    assert_equal "1", null_row["tags"][0]
+  end
+
+  def test_stats_records_one_object_per_column
+    records = TabularTool::Operations::Stats.records(TabularTool::Operations::Stats.call(@df)[:stats])
+
+    assert_equal ["name", "age", "city", "score", "status"], records.map { |r| r["column"] }
+    age = records.find { |r| r["column"] == "age" }
+    assert_equal 29.6, age["mean"]
+    assert age.key?("p50"), "Percentile rows are renamed to p-keys"
+    assert_equal 25.0, age["min"]
+  end
+
+  def test_cli_stats_json
+    output = TabularTool::CLI.execute(command: :stats, file: File.join(@fixtures_path, "basic.csv"), json: true, fast: true)
+    records = JSON.parse(output)
+
+    assert_equal 5, records.length
+    assert_nil records.first["mean"], "Unavailable fast statistics are null"
+  end
+
+  def test_nunique_counts_every_column
+    counts = TabularTool::Operations::Stats.nunique(@df).to_h
+
+    assert_equal @df.columns, counts.keys
+    assert_equal 10, counts["name"]
+    assert_equal 2, counts["status"]
+  end
+
+  def test_nunique_approx_is_close
+    counts = TabularTool::Operations::Stats.nunique(@df.lazy, approx: true).to_h
+
+    assert_equal 2, counts["status"]
+    assert_in_delta 10, counts["name"], 1
+  end
+
+  def test_cli_nunique_outputs_frame
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(command: :nunique, file: File.join(@fixtures_path, "basic.csv"))
+      lines = result.split("\n")
+
+      assert_equal "column,n_unique", lines.first
+      assert_equal "name,10", lines[1]
+      assert_includes lines, "status,2"
+    end
+  end
+
+  def test_cli_nunique_respects_select
+    $stdout.stub :tty?, false do
+      result = TabularTool::CLI.execute(
+        command: :nunique,
+        file: File.join(@fixtures_path, "basic.parquet"),
+        select: ["status", "city"],
+      )
+
+      assert_equal ["column,n_unique", "status,2", "city,10"], result.split("\n")
+    end
+  end
+
+  def test_nunique_approx_within_tolerance_on_larger_data
+    df = Polars::DataFrame.new({
+      "id" => (0...20_000).to_a,
+      "bucket" => (0...20_000).map { |i| i % 500 },
+    })
+    exact = TabularTool::Operations::Stats.nunique(df).to_h
+    approx = TabularTool::Operations::Stats.nunique(df, approx: true).to_h
+
+    assert_equal 20_000, exact["id"]
+    assert_in_delta exact["id"], approx["id"], exact["id"] * 0.05
+    assert_in_delta exact["bucket"], approx["bucket"], exact["bucket"] * 0.05
+  end
+
+  def test_stats_follow_select_order
+    ["basic.csv", "basic.parquet"].each do |name|
+      [false, true].each do |fast|
+        output = TabularTool::CLI.execute(
+          command: :stats,
+          file: File.join(@fixtures_path, name),
+          select: ["score", "name", "age"],
+          fast: fast,
+        )
+        header = output.lines.find { |line| line.include?("score") }
+
+        assert_operator header.index("score"), :<, header.index("name"), "#{name} fast=#{fast}"
+        assert_operator header.index("name"), :<, header.index("age"), "#{name} fast=#{fast}"
+      end
+    end
+  end
 end
@@ -0,0 +1,98 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "tmpdir"
+
+class TestTimings < Minitest::Test
+  def test_measure_returns_block_value_and_records_phase
+    timings = TabularTool::Timings.new
+
+    value = timings.measure("read") { 42 }
+
+    assert_equal 42, value
+    assert_includes timings.phases.keys, "read"
+    assert_operator timings.phases["read"], :>=, 0
+  end
+
+  def test_measure_accumulates_repeated_phases
+    timings = TabularTool::Timings.new
+    timings.record("write", 1.5)
+    timings.record("write", 2.0)
+
+    assert_in_delta 3.5, timings.phases["write"]
+  end
+
+  def test_nested_phases_are_not_counted_twice
+    timings = TabularTool::Timings.new
+    clock = [0.0, 1.0, 3.0, 10.0]
+    timings.stub(:now, -> { clock.shift }) do
+      timings.measure("sort") { timings.measure("write") {} }
+    end
+
+    assert_in_delta 8000.0, timings.phases["sort"]
+    assert_in_delta 2000.0, timings.phases["write"]
+    assert_equal "sort\t8000.0\nwrite\t2000.0\ntotal\t10000.0", timings.report
+  end
+
+  def test_report_is_tab_separated_with_total
+    timings = TabularTool::Timings.new
+    timings.record("read", 10.0)
+    timings.record("transform", 2.5)
+
+    assert_equal "read\t10.0\ntransform\t2.5\ntotal\t12.5", timings.report
+  end
+
+  def test_cli_time_flag_reports_phases_to_stderr
+    result = nil
+    _, err = capture_io do
+      result = TabularTool::CLI.execute(
+        command: :stats,
+        file: File.join(__dir__, "..", "fixtures", "basic.csv"),
+        time: true,
+      )
+    end
+
+    assert_includes result, "age"
+    phases = err.lines.map { |line| line.chomp.split("\t") }
+    assert phases.all? { |parts| parts.length == 2 && parts[1].match?(/\A\d+(\.\d+)?\z/) }
+    assert_equal ["read", "transform", "aggregate", "format", "total"], phases.map(&:first)
+
+    # The external sort's write happens inside its sort phase, and is still counted once
+    Dir.mktmpdir do |dir|
+      started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+      _, err = capture_io do
+        TabularTool::CLI.execute(command: :cat, file: File.join(__dir__, "..", "fixtures", "basic.csv"),
+                                 sort_keys: ["age"], external_sort: true, output: File.join(dir, "out.csv"), time: true)
+      end
+      wall = (Process.clock_gettime(Process::CLOCK_MONOTONIC) - started) * 1000
+
+      phases = err.lines.to_h { |line| line.chomp.split("\t") }.transform_values(&:to_f)
+      assert_includes phases.keys, "sort"
+      assert_includes phases.keys, "write"
+      assert_in_delta phases.except("total").values.sum, phases["total"], 0.5
+      assert_operator phases["total"], :<=, wall
+    end
+  end
+
+  def test_cli_timings_summary_parses
+    _, err = capture_io do
+      TabularTool::CLI.execute(
+        command: :head,
+        file: File.join(__dir__, "..", "fixtures", "basic.csv"),
+        limit: 3,
+        pretty: false,
+        timings: true,
+      )
+    end
+
+    summary = err.lines.to_h { |line| line.chomp.split("\t", 2) }
+    ["read", "transform", "collect", "format", "total"].each do |phase|
+      assert_operator Float(summary.fetch(phase)), :>=, 0, "#{phase} should be non-negative"
+    end
+    assert_equal "10", summary["rows_in"]
+    assert_equal "3", summary["rows_out"]
+    if File.exist?("/proc/self/status")
+      assert_match(/\A\d+\z/, summary["peak_rss_kb"])
+    end
+  end
+end
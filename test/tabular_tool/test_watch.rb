@@ -0,0 +1,85 @@
+# frozen_string_literal: true
+
+require "test_helper"
+require "stringio"
+require "tmpdir"
+
+class TestWatch < Minitest::Test
+  def test_reruns_when_file_changes
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "data.csv")
+      File.write(path, "id\n1\n")
+      io = StringIO.new
+      outputs = []
+      waits = []
+
+      runs = TabularTool::Watch.new(path, io: io, sleeper: ->(seconds) { waits << seconds }).run(max_runs: 2) do
+        outputs << TabularTool::CLI.execute(command: :count, file: path)
+        next unless outputs.length == 1
+
+        # The edit lands between polls; a later mtime makes the change visible regardless of clock resolution
+        File.write(path, "id\n1\n2\n3\n")
+        File.utime(Time.now + 5, Time.now + 5, path)
+      end
+
+      assert_equal 2, runs
+      assert_equal ["1", "3"], outputs
+      assert_equal [TabularTool::Watch::DEFAULT_INTERVAL] * 2, waits
+      assert_equal 2, io.string.scan(/==> .*data\.csv \(run \d/).length
+    end
+  end
+
+  def test_errors_do_not_stop_watching
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "data.csv")
+      File.write(path, "id\n1\n")
+      calls = 0
+
+      _, err = capture_io do
+        runs = TabularTool::Watch.new(path, interval: 0.01, io: StringIO.new).run(max_runs: 1) do
+          calls += 1
+          raise TabularTool::Error, "half-written"
+        end
+        assert_equal 1, runs
+      end
+
+      assert_equal 1, calls
+      assert_match(/half-written/, err)
+    end
+  end
+
+  def test_quiet_skips_separators
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "data.csv")
+      File.write(path, "id\n1\n")
+      io = StringIO.new
+      TabularTool::Log.level = :quiet
+
+      TabularTool::Watch.new(path, interval: 0.01, io: io).run(max_runs: 1) {}
+
+      assert_empty io.string
+    end
+  ensure
+    TabularTool::Log.level = :normal
+  end
+
+  def test_separators_stay_off_stdout
+    Dir.mktmpdir do |dir|
+      path = File.join(dir, "data.csv")
+      File.write(path, "id\n1\n")
+
+      out, err = capture_io do
+        TabularTool::Watch.new(path, interval: 0.01).run(max_runs: 1) do
+          puts TabularTool::CLI.execute(command: :cat, file: path, pretty: false)
+        end
+      end
+
+      assert_equal "id\n1\n", out
+      assert_match(/==> .*data\.csv \(run 1/, err)
+    end
+  end
+
+  def test_rejects_stdin
+    assert_raises(TabularTool::Error) { TabularTool::Watch.new("-") }
+  end
+end
@@ -13,4 +13,17 @@ class TestTabularTool < Minitest::Test
     assert_respond_to TabularTool::Operations, :select
     assert_respond_to TabularTool::CLI, :parse_args
   end
+
+  def test_filter_and_stats_in_process
+    path = File.join(__dir__, "fixtures", "basic.csv")
+    options = { where: "age > 30", select: ["name", "score"], sort_keys: ["score"] }
+    df = TabularTool.transform(TabularTool.read(path, streaming: true), **options)
+
+    expected = TabularTool::CLI.execute(command: :cat, file: path, pretty: false, **options)
+    assert_equal expected.chomp, TabularTool::Formats.write_to_stdout(df.collect, format: :csv).chomp
+
+    stats = TabularTool.stats(df)
+    assert_equal ["name", "score"], stats.columns.drop(1)
+    assert_kind_of TabularTool::Operations::LintReport, TabularTool.lint(df)
+  end
 end